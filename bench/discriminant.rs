@@ -0,0 +1,39 @@
+/// See https://bheisler.github.io/criterion.rs/book/getting_started.html to add more benchmarks.
+#[macro_use]
+extern crate criterion;
+
+use classygroup::{create_discriminant, create_discriminant_parallel};
+use criterion::{BenchmarkId, Criterion};
+
+// The search from each seed stops at a different point, so every thread count sees the same seeds.
+const SEEDS: [&[u8]; 4] = [b"\x00", b"\x01", b"\x02", b"\x03"];
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let max_threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    for &length in [1024u64, 2048].iter() {
+        let mut group = c.benchmark_group(format!("create_discriminant_{}", length));
+        group.sample_size(10);
+        group.bench_function("sequential", |b| {
+            b.iter(|| {
+                SEEDS
+                    .iter()
+                    .for_each(|seed| drop(create_discriminant(seed, length)))
+            })
+        });
+        let mut threads = 1;
+        while threads <= max_threads.max(8) {
+            group.bench_with_input(BenchmarkId::new("parallel", threads), &threads, |b, &t| {
+                b.iter(|| {
+                    SEEDS
+                        .iter()
+                        .for_each(|seed| drop(create_discriminant_parallel(seed, length, t)))
+                })
+            });
+            threads *= 2;
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
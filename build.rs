@@ -81,6 +81,14 @@ fn generate(f: &mut dyn Write) {
     };
     assert_eq!(residues.len(), RESIDUES_LEN);
     emit(f, "RESIDUES", "u32", &residues[..]);
+    // The same for 3 mod 4, which also takes in the residues equal to 3 mod 8.
+    let residues_3_mod_4: Vec<usize> = {
+        let primes = [3, 5, 7, 11, 13];
+        let not_divisible = |&x: &usize| primes.iter().all(|p| x % p != 0);
+        (3..M).step_by(4).filter(not_divisible).collect()
+    };
+    assert_eq!(residues_3_mod_4.len(), 2 * RESIDUES_LEN);
+    emit(f, "RESIDUES_3_MOD_4", "u32", &residues_3_mod_4[..]);
     let sieve_info: Vec<(usize, usize)> = odd_primes_below_65536()[5..]
         .iter()
         .map(|&i| (i, mod_exponentiation(M % i, i - 2, i)))
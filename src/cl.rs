@@ -0,0 +1,558 @@
+//! Linearly homomorphic encryption modulo a prime `q` over class groups, the HSM-CL variant of
+//! the Castagnos–Laguillaumie scheme.
+//!
+//! The group is `Cl(Δ_q)` for `Δ_q = -p q^3`, the order of conductor `q` in the field of
+//! discriminant `Δ_K = -p q`. It holds the subgroup `F` of order `q` generated by
+//! `f = (q^2, q, (1 + p q) / 4)`, whose discrete logarithms are easy, and an element `g_q` of the
+//! `q`-th powers, where they are believed hard. A message `m` is encrypted under `h = g_q^x` as
+//! `(g_q^r, f^m h^r)`. `p` is derived from a seed, so the parameters need no trusted setup.
+//!
+//! References: Castagnos and Laguillaumie, "Linearly homomorphic encryption from DDH", CT-RSA
+//! 2015; Castagnos, Catalano, Laguillaumie, Savasta and Tucker, "Bandwidth-efficient threshold
+//! EC-DSA", PKC 2020.
+
+use crate::group::{ClassElem, ClassGroupOf, NonMaximalOrder};
+use crate::hash::primality::big::is_prob_prime;
+use crate::hash::{hash_to_prime_with, HashToPrimeParams};
+use crate::num::BigNum;
+use bacteria::Transcript;
+use rug::integer::Order;
+use std::fmt;
+
+/// Domain tag for the primes `p` drawn by `ClParams::new`.
+const PARAMS_DOMAIN: &[u8] = b"Classygroup.cl_params";
+
+/// Bits of statistical distance between `g_q^r`, for `r` below the exponent bound, and a uniform
+/// element of `<g_q>`.
+const STATISTICAL_DISTANCE_BITS: u64 = 40;
+
+/// Size of the challenges of `DecryptionProof`.
+const CHALLENGE_BITS: u64 = 128;
+
+/// Why parameters could not be generated or a ciphertext could not be decrypted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClError {
+    /// The message modulus is not an odd prime.
+    NotPrime,
+    /// The discriminant is too small for `p > 4q`, which the easy subgroup needs.
+    DiscriminantTooSmall,
+    /// An element of the input does not have discriminant `Δ_q`.
+    WrongDiscriminant,
+    /// The ciphertext does not decrypt to an element of `F` under the key.
+    NotInSubgroup,
+}
+
+impl fmt::Display for ClError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClError::NotPrime => write!(f, "message modulus is not an odd prime"),
+            ClError::DiscriminantTooSmall => write!(f, "discriminant is too small for q"),
+            ClError::WrongDiscriminant => {
+                write!(f, "element does not have the scheme's discriminant")
+            }
+            ClError::NotInSubgroup => write!(f, "ciphertext does not decrypt to a message"),
+        }
+    }
+}
+
+impl std::error::Error for ClError {}
+
+/// The public parameters of the scheme for messages modulo `q`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClParams<B: BigNum> {
+    q: B,
+    order: NonMaximalOrder<B>,
+    f: ClassElem<B>,
+    g_q: ClassElem<B>,
+    bound: B,
+}
+
+/// A secret key `x`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SecretKey<B: BigNum>(B);
+
+/// A public key `h = g_q^x`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PublicKey<B: BigNum>(pub ClassElem<B>);
+
+/// A ciphertext `(c1, c2) = (g_q^r, f^m h^r)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ciphertext<B: BigNum> {
+    /// `g_q^r`.
+    pub c1: ClassElem<B>,
+    /// `f^m h^r`.
+    pub c2: ClassElem<B>,
+}
+
+/// A proof that a ciphertext decrypts to a message under the secret key of a public key: a
+/// Fiat–Shamir proof of knowledge of `x` with `h = g_q^x` and `c2 f^-m = c1^x`.
+///
+/// `x` is unknown modulo the order of the group, so the response `u = k + e x` is computed over
+/// the integers, with `k` large enough to hide `e x`. Soundness rests on the low order and strong
+/// root assumptions in `Cl(Δ_q)`, as in the CL-based threshold EC-DSA protocols.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecryptionProof<B: BigNum> {
+    /// The commitment `g_q^k`.
+    pub t1: ClassElem<B>,
+    /// The commitment `c1^k`.
+    pub t2: ClassElem<B>,
+    /// The response `k + e x`.
+    pub u: B,
+}
+
+impl<B: BigNum> ClParams<B> {
+    /// Parameters for messages modulo the prime `q` with a discriminant `Δ_K = -p q` of about
+    /// `disc_bits` bits, where `p` is the first prime derived from `seed` with `p q ≡ 3 mod 4`,
+    /// `(q / p) = -1` and `p > 4q`. Anyone can rerun this to check the parameters.
+    ///
+    /// For 128-bit security the CL literature uses a `Δ_K` of 1827 bits.
+    pub fn new(q: &B, disc_bits: usize, seed: &[u8]) -> Result<Self, ClError> {
+        if !q.odd() || q.cmp_si(3) < 0 || !is_prob_prime(q) {
+            return Err(ClError::NotPrime);
+        }
+        if disc_bits < 2 * q.bit_length() + 3 {
+            return Err(ClError::DiscriminantTooSmall);
+        }
+
+        let mut four_q = B::default();
+        four_q.mul_ui(q, 4);
+        let prime_params = HashToPrimeParams::new(disc_bits - q.bit_length(), PARAMS_DOMAIN);
+        let mut input = seed.to_vec();
+        input.extend_from_slice(&[0; 8]);
+        let mut pq = B::default();
+        for counter in 0_u64.. {
+            input[seed.len()..].copy_from_slice(&counter.to_le_bytes());
+            let prime = hash_to_prime_with(&prime_params, &input);
+            let p = B::from_be_bytes(&prime.to_digits::<u8>(Order::Msf));
+            pq.mul(&p, q);
+            if pq.fdiv_ui(4) == 3 && q.kronecker(&p) == -1 && p > four_q {
+                break;
+            }
+        }
+
+        let mut fundamental = B::default();
+        fundamental.neg(&pq);
+        let order = NonMaximalOrder::new(&fundamental, q).expect("-pq is a discriminant");
+
+        // f = (q^2, q, (1 + pq) / 4), reduced as p > 4q.
+        let (mut q_sq, mut c) = (B::default(), B::default());
+        q_sq.mul(q, q);
+        c.set(&pq);
+        c.add_ui_mut(1);
+        c.fdiv_q_ui_mut(4);
+        let f = ClassGroupOf::with_discriminant(order.discriminant(), || {
+            ClassGroupOf::elem((q_sq, q.clone(), c))
+        });
+
+        // g_q = φ_q^-1(r^2)^q for the smallest split prime r of Δ_K.
+        let r_sq = ClassGroupOf::with_discriminant(&fundamental, || {
+            let mut r = ClassGroupOf::<B>::small_prime_forms(1).remove(0);
+            ClassGroupOf::square(&mut r);
+            r
+        });
+        let lifted = order.lift(&r_sq).expect("r is prime to q");
+        let g_q =
+            ClassGroupOf::with_discriminant(order.discriminant(), || ClassGroupOf::pow(&lifted, q));
+
+        // The class number of Δ_K is below ln|Δ_K| sqrt|Δ_K| / π, which bits(Δ_K) ⌈sqrt|Δ_K|⌉
+        // bounds.
+        let mut bound = B::default();
+        bound.set(&pq);
+        bound.root_mut(2);
+        bound.add_ui_mut(1);
+        bound.mul_ui_mut(pq.bit_length() as u64);
+        bound.mul_mut(&power_of_two(STATISTICAL_DISTANCE_BITS));
+
+        Ok(ClParams {
+            q: q.clone(),
+            order,
+            f,
+            g_q,
+            bound,
+        })
+    }
+
+    /// The message modulus `q`.
+    pub fn q(&self) -> &B {
+        &self.q
+    }
+
+    /// The order of conductor `q` whose class group holds the ciphertexts.
+    pub fn order(&self) -> &NonMaximalOrder<B> {
+        &self.order
+    }
+
+    /// `Δ_q = -p q^3`, the discriminant of the ciphertexts.
+    pub fn discriminant(&self) -> &B {
+        self.order.discriminant()
+    }
+
+    /// The generator `f` of the subgroup of order `q`.
+    pub fn f(&self) -> &ClassElem<B> {
+        &self.f
+    }
+
+    /// The generator `g_q` of the public keys and the first halves of ciphertexts.
+    pub fn g_q(&self) -> &ClassElem<B> {
+        &self.g_q
+    }
+
+    /// The exclusive bound of secret keys and encryption randomness.
+    pub fn exponent_bound(&self) -> &B {
+        &self.bound
+    }
+
+    /// A key pair, with the secret key drawn from `rng`, which must fill its argument with bytes
+    /// from a cryptographically secure generator.
+    pub fn keygen(&self, rng: &mut impl FnMut(&mut [u8])) -> (SecretKey<B>, PublicKey<B>) {
+        let x = random_below(&self.bound, rng);
+        let h = self.in_group(|| ClassGroupOf::pow(&self.g_q, &x));
+        (SecretKey(x), PublicKey(h))
+    }
+
+    /// The public key of `sk`.
+    pub fn public_key(&self, sk: &SecretKey<B>) -> PublicKey<B> {
+        PublicKey(self.in_group(|| ClassGroupOf::pow(&self.g_q, &sk.0)))
+    }
+
+    /// Encrypts `m mod q` under `pk`, with randomness drawn from `rng` as in `keygen`.
+    pub fn encrypt(
+        &self,
+        pk: &PublicKey<B>,
+        m: &B,
+        rng: &mut impl FnMut(&mut [u8]),
+    ) -> Ciphertext<B> {
+        let r = random_below(&self.bound, rng);
+        self.encrypt_with(pk, m, &r)
+    }
+
+    /// Encrypts `m mod q` under `pk` with the randomness `r`, for protocols that prove
+    /// statements about it. `r` should be uniform below `exponent_bound`.
+    pub fn encrypt_with(&self, pk: &PublicKey<B>, m: &B, r: &B) -> Ciphertext<B> {
+        let mut m = m.clone();
+        m.modulo_mut(&self.q);
+        self.in_group(|| {
+            let c1 = ClassGroupOf::pow(&self.g_q, r);
+            let c2 = ClassGroupOf::op(
+                &ClassGroupOf::pow(&self.f, &m),
+                &ClassGroupOf::pow(&pk.0, r),
+            );
+            Ciphertext { c1, c2 }
+        })
+    }
+
+    /// The message of `ct`, in `[0, q)`.
+    pub fn decrypt(&self, sk: &SecretKey<B>, ct: &Ciphertext<B>) -> Result<B, ClError> {
+        self.check(&ct.c1)?;
+        self.check(&ct.c2)?;
+        let fm = self.in_group(|| {
+            let mask = ClassGroupOf::pow(&ct.c1, &sk.0);
+            ClassGroupOf::op(&ct.c2, &ClassGroupOf::inv(&mask))
+        });
+        self.solve_f(&fm)
+    }
+
+    /// A ciphertext of the sum of the messages of `x` and `y`.
+    pub fn add(&self, x: &Ciphertext<B>, y: &Ciphertext<B>) -> Ciphertext<B> {
+        self.in_group(|| Ciphertext {
+            c1: ClassGroupOf::op(&x.c1, &y.c1),
+            c2: ClassGroupOf::op(&x.c2, &y.c2),
+        })
+    }
+
+    /// A ciphertext of `k` times the message of `x`. `k` is reduced modulo `q` first.
+    pub fn scalar_mul(&self, x: &Ciphertext<B>, k: &B) -> Ciphertext<B> {
+        let mut k = k.clone();
+        k.modulo_mut(&self.q);
+        self.in_group(|| Ciphertext {
+            c1: ClassGroupOf::pow(&x.c1, &k),
+            c2: ClassGroupOf::pow(&x.c2, &k),
+        })
+    }
+
+    /// Decrypts `ct` and proves the result correct to anyone holding `pk`, see `DecryptionProof`.
+    pub fn decrypt_with_proof(
+        &self,
+        sk: &SecretKey<B>,
+        pk: &PublicKey<B>,
+        ct: &Ciphertext<B>,
+        rng: &mut impl FnMut(&mut [u8]),
+    ) -> Result<(B, DecryptionProof<B>), ClError> {
+        let m = self.decrypt(sk, ct)?;
+
+        let k = random_below(&self.proof_bound(), rng);
+        let (t1, t2) = self.in_group(|| {
+            (
+                ClassGroupOf::pow(&self.g_q, &k),
+                ClassGroupOf::pow(&ct.c1, &k),
+            )
+        });
+        let e = self.challenge(pk, ct, &m, &t1, &t2);
+        let mut u = k;
+        u.add_mul(&e, &sk.0);
+        Ok((m, DecryptionProof { t1, t2, u }))
+    }
+
+    /// Whether `proof` shows that `ct` decrypts to `m` under the secret key of `pk`.
+    pub fn verify_decryption(
+        &self,
+        pk: &PublicKey<B>,
+        ct: &Ciphertext<B>,
+        m: &B,
+        proof: &DecryptionProof<B>,
+    ) -> bool {
+        let mut u_bound = self.proof_bound();
+        u_bound.mul_ui_mut(2);
+        if proof.u.is_neg() || proof.u >= u_bound || m.is_neg() || *m >= self.q {
+            return false;
+        }
+        let elements = [&pk.0, &ct.c1, &ct.c2, &proof.t1, &proof.t2];
+        if elements.iter().any(|x| self.check(x).is_err()) {
+            return false;
+        }
+
+        let e = self.challenge(pk, ct, m, &proof.t1, &proof.t2);
+        self.in_group(|| {
+            // g_q^u = t1 h^e and c1^u = t2 (c2 f^-m)^e.
+            let fm = ClassGroupOf::pow(&self.f, m);
+            let unmasked = ClassGroupOf::op(&ct.c2, &ClassGroupOf::inv(&fm));
+            ClassGroupOf::pow(&self.g_q, &proof.u)
+                == ClassGroupOf::op(&proof.t1, &ClassGroupOf::pow(&pk.0, &e))
+                && ClassGroupOf::pow(&ct.c1, &proof.u)
+                    == ClassGroupOf::op(&proof.t2, &ClassGroupOf::pow(&unmasked, &e))
+        })
+    }
+
+    // The `m` with `x = f^m`: `f^m` is the identity for `m = 0` and `(q^2, L q, c)` otherwise,
+    // where `L` is the odd integer in `[-q, q]` congruent to `m^-1` modulo `q`.
+    fn solve_f(&self, x: &ClassElem<B>) -> Result<B, ClError> {
+        if x.is_identity() {
+            return Ok(B::default());
+        }
+        let (mut q_sq, mut l, mut r) = (B::default(), B::default(), B::default());
+        q_sq.mul(&self.q, &self.q);
+        l.fdiv_qr(&mut r, &x.b, &self.q);
+        if x.a != q_sq || !r.is_zero() {
+            return Err(ClError::NotInSubgroup);
+        }
+        // L = 0 mod q only for the non-primitive form (q^2, q^2, q (q + p) / 4).
+        let (mut g, mut m, mut t) = (B::default(), B::default(), B::default());
+        g.gcdext(&mut m, &mut t, &l, &self.q);
+        if !g.is_one() {
+            return Err(ClError::NotInSubgroup);
+        }
+        m.modulo_mut(&self.q);
+        Ok(m)
+    }
+
+    // The exclusive bound of the proof nonce `k`, large enough that `k + e x` hides `e x`.
+    fn proof_bound(&self) -> B {
+        let mut bound = self.bound.clone();
+        bound.mul_mut(&power_of_two(CHALLENGE_BITS + STATISTICAL_DISTANCE_BITS));
+        bound
+    }
+
+    fn challenge(
+        &self,
+        pk: &PublicKey<B>,
+        ct: &Ciphertext<B>,
+        m: &B,
+        t1: &ClassElem<B>,
+        t2: &ClassElem<B>,
+    ) -> B {
+        let mut transcript = Transcript::new(b"Classygroup.cl_decryption");
+        transcript.append_message(b"disc", &self.discriminant().to_be_bytes());
+        transcript.append_message(b"g_q", &self.g_q.to_bytes());
+        transcript.append_message(b"h", &pk.0.to_bytes());
+        transcript.append_message(b"c1", &ct.c1.to_bytes());
+        transcript.append_message(b"c2", &ct.c2.to_bytes());
+        transcript.append_message(b"m", &m.to_be_bytes());
+        transcript.append_message(b"t1", &t1.to_bytes());
+        transcript.append_message(b"t2", &t2.to_bytes());
+        let mut e = [0; CHALLENGE_BITS as usize / 8];
+        transcript.challenge_bytes(b"e", &mut e);
+        B::from_be_bytes(&e)
+    }
+
+    fn check(&self, x: &ClassElem<B>) -> Result<(), ClError> {
+        if x.discriminant() != *self.discriminant() {
+            return Err(ClError::WrongDiscriminant);
+        }
+        Ok(())
+    }
+
+    fn in_group<R>(&self, f: impl FnOnce() -> R) -> R {
+        ClassGroupOf::with_discriminant(self.discriminant(), f)
+    }
+}
+
+fn power_of_two<B: BigNum>(bits: u64) -> B {
+    let mut bytes = vec![0; bits as usize / 8 + 1];
+    bytes[0] = 1 << (bits % 8);
+    B::from_be_bytes(&bytes)
+}
+
+// A uniform integer in `[0, bound)`, by rejection sampling from bytes of `rng`.
+fn random_below<B: BigNum>(bound: &B, rng: &mut impl FnMut(&mut [u8])) -> B {
+    let bits = bound.bit_length();
+    let mut bytes = vec![0; (bits + 7) / 8];
+    loop {
+        rng(&mut bytes);
+        if bits % 8 != 0 {
+            bytes[0] &= (1 << (bits % 8)) - 1;
+        }
+        let x = B::from_be_bytes(&bytes);
+        if x < *bound {
+            return x;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::blake2b_256;
+    use crate::num::DefaultBigNum;
+
+    fn big(x: i64) -> DefaultBigNum {
+        let mut r = DefaultBigNum::default();
+        r.set_si(x);
+        r
+    }
+
+    // A deterministic stream of bytes, which is all the tests need.
+    fn test_rng(seed: u8) -> impl FnMut(&mut [u8]) {
+        let mut counter = 0_u64;
+        move |bytes: &mut [u8]| {
+            for chunk in bytes.chunks_mut(32) {
+                let mut input = vec![seed];
+                input.extend_from_slice(&counter.to_le_bytes());
+                chunk.copy_from_slice(&blake2b_256(&input)[..chunk.len()]);
+                counter += 1;
+            }
+        }
+    }
+
+    fn params() -> ClParams<DefaultBigNum> {
+        ClParams::new(&big(1_000_003), 256, b"test").unwrap()
+    }
+
+    #[test]
+    fn test_params() {
+        let params = params();
+        let q = params.q().clone();
+        let mut p = params.order().fundamental_discriminant().clone();
+        p.neg_mut();
+        p.divexact_mut(&q);
+        assert!(is_prob_prime(&p));
+        assert_eq!(q.kronecker(&p), -1);
+        assert_eq!(params, ClParams::new(&q, 256, b"test").unwrap());
+        assert_ne!(params, ClParams::new(&q, 256, b"other").unwrap());
+
+        // f has order q, and f^m = (q^2, L q, c) with L ≡ m^-1 mod q.
+        let f = params.f();
+        params.in_group(|| {
+            assert!(!f.is_identity());
+            assert!(ClassGroupOf::pow(f, &q).is_identity());
+        });
+        for m in &[0, 1, 2, 3, 500_001, 1_000_002] {
+            let fm = params.in_group(|| ClassGroupOf::pow(f, &big(*m)));
+            assert_eq!(params.solve_f(&fm), Ok(big(*m)));
+        }
+        assert_eq!(params.solve_f(params.g_q()), Err(ClError::NotInSubgroup));
+
+        assert_eq!(
+            ClParams::new(&big(1_000_001), 256, b"test"),
+            Err(ClError::NotPrime)
+        );
+        assert_eq!(
+            ClParams::new(&big(1_000_003), 42, b"test"),
+            Err(ClError::DiscriminantTooSmall)
+        );
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let params = params();
+        let mut rng = test_rng(1);
+        let (sk, pk) = params.keygen(&mut rng);
+        assert_eq!(params.public_key(&sk), pk);
+
+        for m in &[0, 1, 42, 1_000_002, 1_000_003 + 7, -1] {
+            let ct = params.encrypt(&pk, &big(*m), &mut rng);
+            let mut expected = big(*m);
+            expected.modulo_mut(params.q());
+            assert_eq!(params.decrypt(&sk, &ct), Ok(expected));
+        }
+
+        let (other, _) = params.keygen(&mut rng);
+        let ct = params.encrypt(&pk, &big(5), &mut rng);
+        assert_eq!(params.decrypt(&other, &ct), Err(ClError::NotInSubgroup));
+
+        // (q^2, q^2, q (q + p) / 4) has the shape of f^m but is not primitive, so it is no
+        // power of f. p + q = 0 mod 4 as pq = 3 mod 4.
+        let q = params.q().clone();
+        let mut p = params.order().fundamental_discriminant().clone();
+        p.neg_mut();
+        p.divexact_mut(&q);
+        let (mut q_sq, mut c) = (big(0), q.clone());
+        q_sq.mul(&q, &q);
+        c.add_mut(&p);
+        c.mul_mut(&q);
+        c.fdiv_q_ui_mut(4);
+        let forged = ClassElem::new(q_sq.clone(), q_sq, c);
+        assert_eq!(forged.discriminant(), *params.discriminant());
+        assert_eq!(params.solve_f(&forged), Err(ClError::NotInSubgroup));
+        let forged = Ciphertext {
+            c1: params.in_group(ClassGroupOf::id),
+            c2: forged,
+        };
+        assert_eq!(params.decrypt(&sk, &forged), Err(ClError::NotInSubgroup));
+
+        let mut wrong = ct.clone();
+        wrong.c1 = ClassElem::new(big(2), big(1), big(3));
+        assert_eq!(params.decrypt(&sk, &wrong), Err(ClError::WrongDiscriminant));
+    }
+
+    #[test]
+    fn test_homomorphism() {
+        let params = params();
+        let mut rng = test_rng(2);
+        let (sk, pk) = params.keygen(&mut rng);
+        let x = params.encrypt(&pk, &big(700_000), &mut rng);
+        let y = params.encrypt(&pk, &big(400_000), &mut rng);
+
+        let sum = params.add(&x, &y);
+        assert_eq!(params.decrypt(&sk, &sum), Ok(big(99_997)));
+        let scaled = params.scalar_mul(&x, &big(3));
+        assert_eq!(params.decrypt(&sk, &scaled), Ok(big(99_994)));
+        let negated = params.scalar_mul(&y, &big(-1));
+        assert_eq!(params.decrypt(&sk, &negated), Ok(big(600_003)));
+        let combined = params.add(
+            &params.scalar_mul(&x, &big(4)),
+            &params.scalar_mul(&y, &big(-7)),
+        );
+        assert_eq!(params.decrypt(&sk, &combined), Ok(big(0)));
+    }
+
+    #[test]
+    fn test_decryption_proof() {
+        let params = params();
+        let mut rng = test_rng(3);
+        let (sk, pk) = params.keygen(&mut rng);
+        let ct = params.encrypt(&pk, &big(31_337), &mut rng);
+
+        let (m, proof) = params.decrypt_with_proof(&sk, &pk, &ct, &mut rng).unwrap();
+        assert_eq!(m, big(31_337));
+        assert!(params.verify_decryption(&pk, &ct, &m, &proof));
+
+        assert!(!params.verify_decryption(&pk, &ct, &big(31_338), &proof));
+        let (_, other) = params.keygen(&mut rng);
+        assert!(!params.verify_decryption(&other, &ct, &m, &proof));
+        let other_ct = params.encrypt(&pk, &big(31_337), &mut rng);
+        assert!(!params.verify_decryption(&pk, &other_ct, &m, &proof));
+        let mut forged = proof.clone();
+        forged.u.add_ui_mut(1);
+        assert!(!params.verify_decryption(&pk, &ct, &m, &forged));
+    }
+}
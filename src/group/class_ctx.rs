@@ -1,81 +1,92 @@
 //! Reusable memory context for class groups.
 
 use super::lin_congruence_ctx::LinCongruenceCtx;
-use crate::group::CLASS_GROUP_DISCRIMINANT;
-use crate::num::{partial, Mpz};
+use super::discriminant::DISCRIMINANT2048_DECIMAL;
+use super::GroupId;
+use crate::num::{partial, BigNum};
 
 #[allow(clippy::type_complexity)]
-pub struct OpCtx {
+pub struct OpCtx<B: BigNum> {
     pub inner: (
-        Mpz,
-        Mpz,
-        Mpz,
-        Mpz,
-        Mpz,
-        Mpz,
-        Mpz,
-        Mpz,
-        Mpz,
-        Mpz,
-        Mpz,
-        Mpz,
-        Mpz,
-        Mpz,
-        Mpz,
-        Mpz,
-        Mpz,
+        B,
+        B,
+        B,
+        B,
+        B,
+        B,
+        B,
+        B,
+        B,
+        B,
+        B,
+        B,
+        B,
+        B,
+        B,
+        B,
+        B,
     ),
 }
 
-impl Default for OpCtx {
+impl<B: BigNum> Default for OpCtx<B> {
     fn default() -> Self {
         Self {
             inner: (
-                Mpz::default(),
-                Mpz::default(),
-                Mpz::default(),
-                Mpz::default(),
-                Mpz::default(),
-                Mpz::default(),
-                Mpz::default(),
-                Mpz::default(),
-                Mpz::default(),
-                Mpz::default(),
-                Mpz::default(),
-                Mpz::default(),
-                Mpz::default(),
-                Mpz::default(),
-                Mpz::default(),
-                Mpz::default(),
-                Mpz::default(),
+                B::default(),
+                B::default(),
+                B::default(),
+                B::default(),
+                B::default(),
+                B::default(),
+                B::default(),
+                B::default(),
+                B::default(),
+                B::default(),
+                B::default(),
+                B::default(),
+                B::default(),
+                B::default(),
+                B::default(),
+                B::default(),
+                B::default(),
             ),
         }
     }
 }
 
+/// The class group of one discriminant, with the scratch space its operations work in.
+///
+/// The group operations are methods taking `&mut self`, so a context can be kept per group,
+/// moved to another thread or task, and used while another context is in use. `ClassGroupOf`
+/// is a layer over a context per thread, created for the built-in discriminant.
 #[allow(non_snake_case)]
 #[allow(clippy::type_complexity)]
-pub struct ClassCtx {
-    pub L: Mpz,
+pub struct ClassCtx<B: BigNum> {
+    pub(crate) L: B,
 
     // Discrimenant
-    pub D: Mpz,
+    pub(crate) D: B,
+
+    // Identifier of the group of `D`, which elements produced here are tagged with.
+    pub(crate) group: GroupId,
 
     // Context for general class group ops implemented in mod.rs
-    pub op_ctx: OpCtx,
+    pub(crate) op_ctx: OpCtx<B>,
 
     // Context that knows how to solve linear congruences.
-    pub lin_cong_ctx: LinCongruenceCtx,
+    pub(crate) lin_cong_ctx: LinCongruenceCtx<B>,
 
     // Context that handles partial extended GCD.
-    pub partial_context: partial::PartialGCDContext,
+    pub(crate) partial_context: partial::PartialGCDContext<B>,
 }
 
-impl ClassCtx {
-    fn from_discriminant(disc: &Mpz) -> Self {
+impl<B: BigNum> ClassCtx<B> {
+    /// A context for the group of discriminant `disc`.
+    pub fn from_discriminant(disc: &B) -> Self {
         let mut s = Self {
-            L: Mpz::default(),
+            L: B::default(),
             D: disc.clone(),
+            group: GroupId::of(disc),
             op_ctx: OpCtx::default(),
             lin_cong_ctx: LinCongruenceCtx::default(),
             partial_context: Default::default(),
@@ -86,21 +97,22 @@ impl ClassCtx {
         s.L.root_mut(4);
         s
     }
+
+    /// The discriminant of the group.
+    pub fn discriminant(&self) -> &B {
+        &self.D
+    }
+
+    /// The identifier of the group, which the elements produced here carry.
+    pub fn group(&self) -> GroupId {
+        self.group
+    }
 }
 
-impl Default for ClassCtx {
+impl<B: BigNum> Default for ClassCtx<B> {
     fn default() -> Self {
-        let mut s = Self {
-            L: Mpz::default(),
-            D: CLASS_GROUP_DISCRIMINANT.clone(),
-            op_ctx: OpCtx::default(),
-            lin_cong_ctx: LinCongruenceCtx::default(),
-            partial_context: Default::default(),
-        };
-
-        // Precomputation needed for NUDULP.
-        s.L.abs(&CLASS_GROUP_DISCRIMINANT);
-        s.L.root_mut(4);
-        s
+        let disc = B::from_str(DISCRIMINANT2048_DECIMAL)
+            .unwrap_or_else(|_| panic!("the built-in discriminant is a valid integer"));
+        Self::from_discriminant(&disc)
     }
 }
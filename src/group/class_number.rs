@@ -0,0 +1,496 @@
+//! Class numbers and group structure for small discriminants, for tests that need a class group of
+//! known order.
+
+use super::{ClassElem, ClassGroupOf, GroupId};
+use crate::num::BigNum;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Largest bit length of |D| accepted by `ClassGroupStructure::compute`. The computation keeps
+/// every element of the group in memory, and h(D) grows roughly like sqrt(|D|).
+pub const MAX_STRUCTURE_BITS: usize = 32;
+
+/// Why the structure of a class group could not be computed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClassNumberError {
+    /// D is zero or positive.
+    NotNegative,
+    /// |D| has more bits than `MAX_STRUCTURE_BITS`.
+    TooLarge { bits: usize, max_bits: usize },
+    /// D is 2 or 3 mod 4, so it is not the discriminant of any form.
+    NotDiscriminant,
+    /// D is the discriminant of a non-maximal order.
+    NotFundamental,
+}
+
+impl fmt::Display for ClassNumberError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClassNumberError::NotNegative => write!(f, "discriminant is not negative"),
+            ClassNumberError::TooLarge { bits, max_bits } => write!(
+                f,
+                "discriminant has {} bits, at most {} are supported",
+                bits, max_bits
+            ),
+            ClassNumberError::NotDiscriminant => write!(f, "discriminant is not 0 or 1 mod 4"),
+            ClassNumberError::NotFundamental => write!(f, "discriminant is not fundamental"),
+        }
+    }
+}
+
+impl std::error::Error for ClassNumberError {}
+
+/// The class group of a small fundamental discriminant: its order h(D) and its decomposition
+/// into cyclic factors.
+#[derive(Clone, Debug)]
+pub struct ClassGroupStructure<B: BigNum> {
+    discriminant: B,
+    order: u64,
+    invariants: Vec<u64>,
+    generators: Vec<ClassElem<B>>,
+}
+
+impl<B: BigNum> ClassGroupStructure<B> {
+    /// Computes the class group of discriminant `disc`.
+    ///
+    /// The group is generated by the prime forms (p, b, c) with p <= sqrt(|D| / 3), since every
+    /// class has a reduced form whose `a` is a product of such primes. The generators are added
+    /// one at a time, as in Buchmann and Schoof: the baby steps are all elements of the subgroup
+    /// found so far, and the giant steps are powers of the new generator until one lands in it.
+    /// The exponents of these relations form a matrix whose Smith normal form gives the
+    /// invariant factors.
+    pub fn compute(disc: &B) -> Result<Self, ClassNumberError> {
+        if disc.sgn() >= 0 {
+            return Err(ClassNumberError::NotNegative);
+        }
+        let bits = disc.bit_length();
+        if bits > MAX_STRUCTURE_BITS {
+            return Err(ClassNumberError::TooLarge {
+                bits,
+                max_bits: MAX_STRUCTURE_BITS,
+            });
+        }
+        let d = disc.get_si();
+        if d.rem_euclid(4) > 1 {
+            return Err(ClassNumberError::NotDiscriminant);
+        }
+        if !is_fundamental(d) {
+            return Err(ClassNumberError::NotFundamental);
+        }
+
+        ClassGroupOf::<B>::with_discriminant(disc, || {
+            let id = ClassGroupOf::<B>::id();
+            let mut elements = vec![id.clone()];
+            let mut index = HashMap::new();
+            index.insert(id, 0);
+            let mut generators = Vec::new();
+            let mut exponents = Vec::new();
+            let mut relations = Vec::new();
+
+            for p in primes_up_to(isqrt(d.unsigned_abs() / 3)) {
+                let g = match prime_form(disc, d, p) {
+                    Some(g) => g,
+                    None => continue,
+                };
+
+                let mut e = 1;
+                let mut power = g.clone();
+                let position = loop {
+                    if let Some(&position) = index.get(&power) {
+                        break position;
+                    }
+                    power = ClassGroupOf::op(&power, &g);
+                    e += 1;
+                };
+                if e == 1 {
+                    continue;
+                }
+
+                // g^e is the element at `position`, whose index encodes its exponents in the
+                // generators so far, in mixed radix.
+                let mut relation = Vec::with_capacity(exponents.len() + 1);
+                let mut rest = position as u64;
+                for &order in exponents.iter() {
+                    relation.push(-((rest % order) as i128));
+                    rest /= order;
+                }
+                relation.push(e as i128);
+                relations.push(relation);
+
+                let size = elements.len();
+                let mut step = g.clone();
+                for j in 1..e {
+                    for m in 0..size {
+                        let x = ClassGroupOf::op(&elements[m], &step);
+                        index.insert(x.clone(), m + size * j);
+                        elements.push(x);
+                    }
+                    step = ClassGroupOf::op(&step, &g);
+                }
+                generators.push(g);
+                exponents.push(e as u64);
+            }
+
+            let order = elements.len() as u64;
+            let invariants = invariant_factors(relations);
+            debug_assert_eq!(invariants.iter().product::<u64>(), order);
+            Ok(ClassGroupStructure {
+                discriminant: disc.clone(),
+                order,
+                invariants,
+                generators,
+            })
+        })
+    }
+
+    /// The discriminant D.
+    pub fn discriminant(&self) -> &B {
+        &self.discriminant
+    }
+
+    /// The class number h(D).
+    pub fn order(&self) -> u64 {
+        self.order
+    }
+
+    /// The invariant factors d_1 | d_2 | ... of the group, which is the product of cyclic groups
+    /// of these orders. Empty when the group is trivial.
+    pub fn invariants(&self) -> &[u64] {
+        &self.invariants
+    }
+
+    /// Prime forms that generate the group.
+    pub fn generators(&self) -> &[ClassElem<B>] {
+        &self.generators
+    }
+
+    /// The order of `x`, an element of this group.
+    pub fn element_order(&self, x: &ClassElem<B>) -> u64 {
+        ClassGroupOf::<B>::with_discriminant(&self.discriminant, || {
+            let id = ClassGroupOf::<B>::id();
+            let mut order = self.order;
+            for p in prime_factors(self.order) {
+                while order % p == 0 && ClassGroupOf::pow(x, &B::from(order / p)) == id {
+                    order /= p;
+                }
+            }
+            order
+        })
+    }
+
+    /// Whether `x^h` is the identity, as for every element of this group.
+    pub fn verify_order(&self, x: &ClassElem<B>) -> bool {
+        ClassGroupOf::<B>::with_discriminant(&self.discriminant, || {
+            ClassGroupOf::pow(x, &B::from(self.order)) == ClassGroupOf::<B>::id()
+        })
+    }
+}
+
+impl<B: BigNum> ClassGroupOf<B> {
+    /// The class number h(D) of the discriminant `disc`, see `ClassGroupStructure::compute`.
+    pub fn class_number(disc: &B) -> Result<u64, ClassNumberError> {
+        ClassGroupStructure::compute(disc).map(|structure| structure.order())
+    }
+}
+
+// The reduced prime form of norm `p`, if `p` splits or ramifies. Must run inside
+// `with_discriminant(disc)`.
+fn prime_form<B: BigNum>(disc: &B, d: i64, p: u64) -> Option<ClassElem<B>> {
+    let b = if p == 2 {
+        match d.rem_euclid(8) {
+            0 => 0,
+            1 => 1,
+            4 => 2,
+            _ => return None,
+        }
+    } else {
+        let mut root = B::default();
+        if !root.sqrt_mod_prime(disc, &B::from(p)) {
+            return None;
+        }
+        let root = root.get_si();
+        // b must have the parity of D for 4p to divide b^2 - D.
+        if (root - d) % 2 == 0 {
+            root
+        } else {
+            p as i64 - root
+        }
+    };
+
+    let p = p as i64;
+    let mut form = ClassElem::new(
+        B::from(p as u64),
+        B::from(b as u64),
+        B::from(((b * b - d) / (4 * p)) as u64),
+    );
+    ClassGroupOf::reduce_mut(&mut form);
+    form.group = Some(GroupId::of(disc));
+    Some(form)
+}
+
+fn is_fundamental(d: i64) -> bool {
+    if d.rem_euclid(4) == 1 {
+        return is_squarefree(d.unsigned_abs());
+    }
+    let m = d / 4;
+    matches!(m.rem_euclid(4), 2 | 3) && is_squarefree(m.unsigned_abs())
+}
+
+fn is_squarefree(n: u64) -> bool {
+    let mut p = 2;
+    while p * p <= n {
+        if n % (p * p) == 0 {
+            return false;
+        }
+        p += 1;
+    }
+    true
+}
+
+fn isqrt(n: u64) -> u64 {
+    let mut r = (n as f64).sqrt() as u64;
+    while r * r > n {
+        r -= 1;
+    }
+    while (r + 1) * (r + 1) <= n {
+        r += 1;
+    }
+    r
+}
+
+pub(crate) fn primes_up_to(n: u64) -> Vec<u64> {
+    let n = n as usize;
+    let mut composite = vec![false; n + 1];
+    let mut primes = Vec::new();
+    for i in 2..=n {
+        if composite[i] {
+            continue;
+        }
+        primes.push(i as u64);
+        for j in (i * i..=n).step_by(i) {
+            composite[j] = true;
+        }
+    }
+    primes
+}
+
+fn prime_factors(mut n: u64) -> Vec<u64> {
+    let mut factors = Vec::new();
+    let mut p = 2;
+    while p * p <= n {
+        if n % p == 0 {
+            factors.push(p);
+            while n % p == 0 {
+                n /= p;
+            }
+        }
+        p += 1;
+    }
+    if n > 1 {
+        factors.push(n);
+    }
+    factors
+}
+
+// The diagonal of the Smith normal form of the lower triangular relation matrix, without its
+// ones. Each row i holds the relation g_i^e_i = g_1^-r_1 ... g_(i-1)^-r_(i-1), so the rows span
+// the relation lattice of the generators.
+fn invariant_factors(relations: Vec<Vec<i128>>) -> Vec<u64> {
+    let n = relations.len();
+    let mut m: Vec<Vec<i128>> = relations
+        .into_iter()
+        .map(|mut row| {
+            row.resize(n, 0);
+            row
+        })
+        .collect();
+
+    for t in 0..n {
+        loop {
+            // Move the smallest nonzero entry of the remaining block to the pivot.
+            let mut pivot = (t, t);
+            for i in t..n {
+                for j in t..n {
+                    let x = m[i][j].abs();
+                    if x != 0 && (m[pivot.0][pivot.1] == 0 || x < m[pivot.0][pivot.1].abs()) {
+                        pivot = (i, j);
+                    }
+                }
+            }
+            m.swap(t, pivot.0);
+            for row in m.iter_mut() {
+                row.swap(t, pivot.1);
+            }
+
+            let p = m[t][t];
+            let mut cleared = true;
+            let (pivot_row, rows) = m[t..].split_first_mut().unwrap();
+            for row in rows.iter_mut() {
+                let q = row[t] / p;
+                for (x, y) in row[t..].iter_mut().zip(&pivot_row[t..]) {
+                    *x -= q * y;
+                }
+                cleared &= row[t] == 0;
+            }
+            for j in t + 1..n {
+                let q = m[t][j] / p;
+                for row in m.iter_mut().skip(t) {
+                    row[j] -= q * row[t];
+                }
+                cleared &= m[t][j] == 0;
+            }
+            if !cleared {
+                continue;
+            }
+
+            // The pivot must divide the rest of the block; otherwise fold an offending row into
+            // the pivot row and go again.
+            let offending = (t + 1..n).find(|&i| (t + 1..n).any(|j| m[i][j] % p != 0));
+            match offending {
+                Some(i) => {
+                    let (upper, lower) = m.split_at_mut(i);
+                    for (x, y) in upper[t][t..].iter_mut().zip(&lower[0][t..]) {
+                        *x += y;
+                    }
+                }
+                None => break,
+            }
+        }
+    }
+
+    (0..n)
+        .map(|i| m[i][i].unsigned_abs() as u64)
+        .filter(|&x| x != 1)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::group::ClassGroup;
+    use crate::num::DefaultBigNum;
+
+    fn structure(d: i64) -> Result<ClassGroupStructure<DefaultBigNum>, ClassNumberError> {
+        let mut disc = DefaultBigNum::default();
+        disc.set_si(d);
+        ClassGroupStructure::compute(&disc)
+    }
+
+    // h(D) by counting the reduced primitive forms |b| <= a <= c, with b >= 0 if either is equal.
+    fn count_reduced_forms(d: i64) -> u64 {
+        let gcd = |mut x: i64, mut y: i64| {
+            while y != 0 {
+                let r = x % y;
+                x = y;
+                y = r;
+            }
+            x.abs()
+        };
+        let mut count = 0;
+        let mut a = 1;
+        while 3 * a * a <= -d {
+            for b in -a + 1..=a {
+                if (b * b - d) % (4 * a) != 0 {
+                    continue;
+                }
+                let c = (b * b - d) / (4 * a);
+                if c < a || (c == a && b < 0) || gcd(gcd(a, b), c) != 1 {
+                    continue;
+                }
+                count += 1;
+            }
+            a += 1;
+        }
+        count
+    }
+
+    #[test]
+    fn test_known_structures() {
+        let cases: [(i64, &[u64]); 11] = [
+            (-3, &[]),
+            (-4, &[]),
+            (-7, &[]),
+            (-8, &[]),
+            (-20, &[2]),
+            (-23, &[3]),
+            (-47, &[5]),
+            (-71, &[7]),
+            (-84, &[2, 2]),
+            (-420, &[2, 2, 2]),
+            (-3299, &[3, 9]),
+        ];
+        for &(d, invariants) in cases.iter() {
+            let group = structure(d).unwrap();
+            assert_eq!(group.invariants(), invariants, "{}", d);
+            assert_eq!(group.order(), invariants.iter().product::<u64>(), "{}", d);
+        }
+    }
+
+    #[test]
+    fn test_class_number_matches_form_count() {
+        for d in -2000..0 {
+            let group = match structure(d) {
+                Ok(group) => group,
+                Err(_) => continue,
+            };
+            assert_eq!(group.order(), count_reduced_forms(d), "{}", d);
+        }
+    }
+
+    #[test]
+    fn test_element_orders() {
+        let group = structure(-3299).unwrap();
+        let mut disc = DefaultBigNum::default();
+        disc.set_si(-3299);
+        assert_eq!(ClassGroupOf::class_number(&disc), Ok(27));
+
+        for g in group.generators() {
+            assert!(group.verify_order(g));
+            let order = group.element_order(g);
+            assert_eq!(27 % order, 0);
+            assert!(order > 1);
+        }
+        let orders: Vec<u64> = group
+            .generators()
+            .iter()
+            .map(|g| group.element_order(g))
+            .collect();
+        assert_eq!(orders.iter().max(), Some(&9));
+
+        let id = ClassGroup::with_discriminant(&disc, ClassGroup::id);
+        assert_eq!(group.element_order(&id), 1);
+        assert!(group.verify_order(&id));
+    }
+
+    #[test]
+    fn test_rejects_unsupported_discriminants() {
+        assert_eq!(structure(0).unwrap_err(), ClassNumberError::NotNegative);
+        assert_eq!(structure(23).unwrap_err(), ClassNumberError::NotNegative);
+        assert_eq!(
+            structure(-5).unwrap_err(),
+            ClassNumberError::NotDiscriminant
+        );
+        assert_eq!(
+            structure(-12).unwrap_err(),
+            ClassNumberError::NotFundamental
+        );
+        assert_eq!(
+            structure(-99).unwrap_err(),
+            ClassNumberError::NotFundamental
+        );
+        assert_eq!(
+            structure(-(1 << 40) + 1).unwrap_err(),
+            ClassNumberError::TooLarge {
+                bits: 40,
+                max_bits: MAX_STRUCTURE_BITS
+            }
+        );
+    }
+
+    #[test]
+    fn test_with_discriminant_restores_group() {
+        let before = ClassGroup::unknown_order_elem();
+        let _ = structure(-84).unwrap();
+        assert_eq!(ClassGroup::unknown_order_elem(), before);
+    }
+}
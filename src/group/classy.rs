@@ -1,70 +1,93 @@
 //! Class Group implementation
 
-use super::CLASS_GROUP_DISCRIMINANT;
-use crate::group::{ClassCtx, ClassElem};
+use crate::group::class_number::primes_up_to;
+use crate::group::{ClassCtx, ClassElem, GroupId, GroupMismatch};
 use crate::mut_tuple_elems;
-use crate::num::Mpz;
-use rug::Integer;
+use crate::num::{BigNum, DefaultBigNum};
+use std::any::{Any, TypeId};
 use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+#[cfg(feature = "gmp")]
+use crate::hash::{hash_to_prime_with, HashToPrimeParams};
+#[cfg(feature = "gmp")]
+use rug::integer::Order;
 
 const EXP_THRESH: i64 = 31;
 const THRESH: i64 = ((1 as u64) << 31) as i64;
 
+/// Domain tag for the primes drawn by `hash_to_elem`.
+#[cfg(feature = "gmp")]
+const HASH_TO_ELEM_DOMAIN: &[u8] = b"Classygroup.hash_to_elem";
+
+/// Largest bound accepted by `ClassGroupOf::small_order`. The primes up to the bound are sieved
+/// in memory, and the exponent `x` is raised to is about 1.44 times the bound in bits.
+pub const MAX_SMALL_ORDER_BOUND: u64 = 1 << 20;
+
 thread_local! {
-  // Thread-local context for class group operations.
-  static CTX: RefCell<ClassCtx> = Default::default();
+  // Thread-local contexts for class group operations, one per big-number backend.
+  static CTX: RefCell<HashMap<TypeId, Box<dyn Any>>> = Default::default();
+}
+
+// Runs `logic` with this thread's context for the backend `B`, creating it on first use.
+pub(crate) fn with_class_ctx<B: BigNum, R>(logic: impl FnOnce(&mut ClassCtx<B>) -> R) -> R {
+    CTX.with(|refcell| {
+        let mut ctxs = refcell.borrow_mut();
+        let ctx = ctxs
+            .entry(TypeId::of::<B>())
+            .or_insert_with(|| Box::new(ClassCtx::<B>::default()))
+            .downcast_mut::<ClassCtx<B>>()
+            .expect("class context registered under the wrong backend");
+        logic(ctx)
+    })
 }
 
 // Runs the given closure with the Class Context. The expression passed must be
-// a closure that takes in an element of type &mut ClassElem. Furthermore, the lambda
-// cannot contain subroutines which themselves call the `with_ctx` macro, or the
-// compiler will not be happy.
+// a closure that takes in an element of type &mut ClassCtx<B>. The thread's context stays
+// borrowed while the closure runs, so the closure must not reach the thread-local layer again
+// (this macro, a `ClassGroupOf` function or a `ClassElem` method that uses one): that panics
+// with `BorrowMutError` at run time. Code that needs nested contexts should use `ClassCtx`
+// values, which do not touch the thread-local layer.
 macro_rules! with_ctx {
     ($logic:expr) => {
-        CTX.with(|refcell| {
-            let mut ctx_ = refcell.borrow_mut();
-            $logic(&mut ctx_)
-        })
+        with_class_ctx($logic)
     };
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub enum ClassGroup {}
-
-#[inline]
-fn log2(x: u64) -> u32 {
-    63 - x.leading_zeros()
-}
-
-#[inline]
-pub fn signed_shift(op: u64, shift: i64) -> u64 {
-    match shift {
-        x if x > 0 => op << shift,
-        x if x <= -64 => 0,
-        _ => op >> (-shift),
+// Elements built by hand carry no group and are taken to belong to that of their discriminant.
+fn check_group<B: BigNum>(ctx: &ClassCtx<B>, x: &ClassElem<B>) -> Result<(), GroupMismatch> {
+    let found = match x.group {
+        Some(found) => found,
+        None => {
+            let disc = x.discriminant();
+            if disc == ctx.D {
+                return Ok(());
+            }
+            GroupId::of(&disc)
+        }
+    };
+    if found == ctx.group {
+        Ok(())
+    } else {
+        Err(GroupMismatch {
+            expected: ctx.group,
+            found,
+        })
     }
 }
 
-#[inline]
-pub fn mpz_get_si_2exp(op: &Mpz) -> (i64, i64) {
-    let size = op.size();
-    let last = op.getlimbn((size - 1) as i64);
-    let lg2 = log2(last) + 1;
-    let mut exp = lg2 as i64;
-    let mut ret = signed_shift(last, 63 - exp);
-    if size > 1 {
-        exp += ((size - 1) * 64) as i64;
-        let prev = op.getlimbn((size - 2) as i64);
-        ret += signed_shift(prev, (-1 - lg2 as i32) as i64);
-    }
-    if op.is_neg() {
-        return (-(ret as i64), exp);
-    }
-    (ret as i64, exp)
-}
+/// Class group arithmetic over the big-number backend `B`, run on the calling thread's
+/// `ClassCtx`. Use a `ClassCtx` directly to pick the context explicitly.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ClassGroupOf<B: BigNum>(PhantomData<B>);
+
+/// Class group arithmetic over the default big-number backend.
+pub type ClassGroup = ClassGroupOf<DefaultBigNum>;
 
 #[inline]
-pub fn test_reduction(x: &mut ClassElem) -> bool {
+fn test_reduction<B: BigNum>(x: &mut ClassElem<B>) -> bool {
     let a_b = x.a.cmpabs(&x.b);
     let c_b = x.c.cmpabs(&x.b);
 
@@ -72,337 +95,590 @@ pub fn test_reduction(x: &mut ClassElem) -> bool {
         return false;
     }
 
-    let a_c = x.a.cmp_mpz(&x.c);
+    let a_c = x.a.cmp(&x.c);
 
-    if a_c > 0 {
+    if a_c == Ordering::Greater {
         x.a.swap(&mut x.c);
         x.b.neg_mut();
     }
-    if a_c == 0 && x.b.is_neg() {
+    if a_c == Ordering::Equal && x.b.is_neg() {
         x.b.neg_mut();
     }
     true
 }
 
-impl ClassGroup {
-    fn discriminant(a: &Mpz, b: &Mpz, c: &Mpz) -> Mpz {
-        with_ctx!(|ctx: &mut ClassCtx| {
-            let (scratch,) = mut_tuple_elems!(ctx.op_ctx, 0);
-
-            let mut d = Mpz::default();
-            d.mul(&b, &b);
-            scratch.mul(&a, &c);
-            scratch.mul_ui_mut(4);
-            d.sub_mut(&scratch);
-            d
-        })
+fn prime_power_product<B: BigNum>(powers: &[(u64, u64)]) -> B {
+    let mut product = B::from(1);
+    for &(_, q) in powers {
+        product.mul_ui_mut(q);
     }
+    product
+}
 
-    #[allow(non_snake_case)]
-    pub fn square(x: &mut ClassElem) {
-        // Jacobson, Michael J., and Alfred J. Van Der Poorten. "Computational aspects of NUCOMP."
-        // Algorithm 2 (Alg 2).
+impl<B: BigNum> ClassGroupOf<B> {
+    /// Runs `f` with this thread's group switched to the discriminant `disc`, switching back
+    /// afterwards even if `f` panics. Elements created inside `f` belong to that group and must not
+    /// be combined with elements of any other.
+    pub fn with_discriminant<R>(disc: &B, f: impl FnOnce() -> R) -> R {
+        struct Restore<B: BigNum>(Option<ClassCtx<B>>);
+
+        impl<B: BigNum> Drop for Restore<B> {
+            fn drop(&mut self) {
+                if let Some(previous) = self.0.take() {
+                    with_class_ctx(|ctx: &mut ClassCtx<B>| *ctx = previous);
+                }
+            }
+        }
+
+        let previous = with_class_ctx(|ctx: &mut ClassCtx<B>| {
+            std::mem::replace(ctx, ClassCtx::from_discriminant(disc))
+        });
+        let _restore = Restore(Some(previous));
+        f()
+    }
+
+    /// The discriminant of the calling thread's group.
+    pub fn current_discriminant() -> B {
+        with_ctx!(|ctx: &mut ClassCtx<B>| ctx.D.clone())
+    }
 
-        with_ctx!(|ctx: &mut ClassCtx| {
-            let (
-                G_sq_op,
-                scratch,
-                mut y_sq_op,
-                By_sq_op,
-                Dy_sq_op,
-                mut bx_sq_op,
-                mut by_sq_op,
-                dx_sq_op,
-                q_sq_op,
-                t_sq_op,
-                ax_sq_op,
-                ay_sq_op,
-                Q1_sq_op,
-                mut x_sq_op,
-                z_sq_op,
-                dy_sq_op,
-            ) = mut_tuple_elems!(ctx.op_ctx, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15);
-
-            let L_sq_op = &mut ctx.L;
-
-            // Step 1 in Alg 2.
-            G_sq_op.gcdext(scratch, y_sq_op, &x.a, &x.b);
-            By_sq_op.divexact(&x.a, &G_sq_op);
-            Dy_sq_op.divexact(&x.b, &G_sq_op);
-
-            // Step 2 in Alg 2.
-            bx_sq_op.mul(&y_sq_op, &x.c);
-            bx_sq_op.modulo_mut(&By_sq_op);
-            by_sq_op.set(&By_sq_op);
-
-            if by_sq_op.cmpabs(&L_sq_op) <= 0 {
-                // Step 4 in Alg 2.
-                dx_sq_op.mul(&bx_sq_op, &Dy_sq_op);
-                dx_sq_op.sub_mut(&x.c);
-                dx_sq_op.divexact_mut(&By_sq_op);
-                x.a.mul(&by_sq_op, &by_sq_op);
-                x.c.mul(&bx_sq_op, &bx_sq_op);
-                t_sq_op.add(&bx_sq_op, &by_sq_op);
-                t_sq_op.square_mut();
-
-                x.b.sub_mut(&t_sq_op);
-                x.b.add_mut(&x.a);
-                x.b.add_mut(&x.c);
-                t_sq_op.mul(&G_sq_op, &dx_sq_op);
-                x.c.sub_mut(&t_sq_op);
-                return;
+    /// The identifier of the calling thread's group, which the elements it produces carry.
+    pub fn current_group() -> GroupId {
+        with_ctx!(|ctx: &mut ClassCtx<B>| ctx.group)
+    }
+
+    /// Squares `x` in place.
+    ///
+    /// # Panics
+    ///
+    /// If `x` belongs to a group other than the calling thread's.
+    pub fn square(x: &mut ClassElem<B>) {
+        with_ctx!(|ctx: &mut ClassCtx<B>| ctx.square(x))
+    }
+
+    pub(crate) fn reduce_mut(x: &mut ClassElem<B>) {
+        with_ctx!(|ctx: &mut ClassCtx<B>| ctx.reduce_mut(x))
+    }
+
+    fn reduce(elem: &mut ClassElem<B>) {
+        with_ctx!(|ctx: &mut ClassCtx<B>| ctx.reduce(elem))
+    }
+
+    pub(crate) fn normalize_mut(x: &mut ClassElem<B>) {
+        with_ctx!(|ctx: &mut ClassCtx<B>| ctx.normalize_mut(x))
+    }
+
+    //WIP NUCOMP
+    pub fn nucomp(x: &ClassElem<B>, y: &ClassElem<B>) -> ClassElem<B> {
+        with_ctx!(|ctx: &mut ClassCtx<B>| ctx.nucomp(x, y))
+    }
+
+    /// Composes `x` and `y`.
+    ///
+    /// # Panics
+    ///
+    /// If either belongs to a group other than the calling thread's; `try_op` returns the
+    /// error instead.
+    pub fn op(x: &ClassElem<B>, y: &ClassElem<B>) -> ClassElem<B> {
+        with_ctx!(|ctx: &mut ClassCtx<B>| ctx.op(x, y))
+    }
+
+    /// Composes `x` and `y`, or fails if either belongs to a group other than the calling
+    /// thread's.
+    pub fn try_op(x: &ClassElem<B>, y: &ClassElem<B>) -> Result<ClassElem<B>, GroupMismatch> {
+        with_ctx!(|ctx: &mut ClassCtx<B>| ctx.try_op(x, y))
+    }
+
+    /// Sets `out` to `x * y`, reusing the limbs of `out`.
+    ///
+    /// # Panics
+    ///
+    /// If `x` or `y` belongs to a group other than the calling thread's.
+    pub fn op_into(out: &mut ClassElem<B>, x: &ClassElem<B>, y: &ClassElem<B>) {
+        with_ctx!(|ctx: &mut ClassCtx<B>| ctx.op_into(out, x, y))
+    }
+
+    /// Sets `x` to `x * y` in place.
+    ///
+    /// # Panics
+    ///
+    /// If `x` or `y` belongs to a group other than the calling thread's.
+    pub fn op_assign(x: &mut ClassElem<B>, y: &ClassElem<B>) {
+        with_ctx!(|ctx: &mut ClassCtx<B>| ctx.op_assign(x, y))
+    }
+
+    /// The identity element, the principal form of the current discriminant.
+    pub fn id() -> ClassElem<B> {
+        with_ctx!(|ctx: &mut ClassCtx<B>| ctx.id())
+    }
+
+    pub(crate) fn inv(x: &ClassElem<B>) -> ClassElem<B> {
+        let mut ret = x.clone();
+        ret.invert();
+        ret
+    }
+
+
+    /// Raises `a` to the power `n`. The exponent may use any big-number backend.
+    ///
+    /// # Panics
+    ///
+    /// If `a` belongs to a group other than the calling thread's.
+    pub fn pow<E: BigNum>(a: &ClassElem<B>, n: &E) -> ClassElem<B> {
+        with_ctx!(|ctx: &mut ClassCtx<B>| ctx.pow(a, n))
+    }
+
+    /// Whether `x^k` is the identity for some `1 <= k <= bound`, see `small_order`.
+    ///
+    /// # Panics
+    ///
+    /// If `bound` exceeds `MAX_SMALL_ORDER_BOUND`.
+    pub fn has_small_order(x: &ClassElem<B>, bound: u64) -> bool {
+        Self::small_order(x, bound).is_some()
+    }
+
+    /// The order of `x` if it is at most `bound`.
+    ///
+    /// Every order up to `bound` divides the product of the largest powers of the primes up to
+    /// `bound` that do not exceed it, so one exponentiation by this product, about 1.44 `bound`
+    /// bits long, clears almost every element. Only if it gives the identity is the order found,
+    /// by dividing the prime powers out of the product, see `order_dividing`.
+    ///
+    /// # Panics
+    ///
+    /// If `bound` exceeds `MAX_SMALL_ORDER_BOUND`.
+    pub fn small_order(x: &ClassElem<B>, bound: u64) -> Option<u64> {
+        assert!(
+            bound <= MAX_SMALL_ORDER_BOUND,
+            "small order bound exceeds MAX_SMALL_ORDER_BOUND"
+        );
+        let powers: Vec<(u64, u64)> = primes_up_to(bound)
+            .into_iter()
+            .map(|p| {
+                let mut q = p;
+                while q <= bound / p {
+                    q *= p;
+                }
+                (p, q)
+            })
+            .collect();
+        let id = Self::id();
+        if Self::pow(x, &prime_power_product::<B>(&powers)) != id {
+            return None;
+        }
+        Some(Self::order_dividing(x, &powers, &id)).filter(|&order| order <= bound)
+    }
+
+    // The order of `x`, given that `x` raised to the product of the prime powers `(p, p^k)` in
+    // `powers` is the identity, saturated at `u64::MAX`. Raising `x` to the product of one half
+    // of the powers leaves an element whose order is the part of the order of `x` in the other
+    // half, so each level of halving costs about one exponentiation by the product, and halves
+    // that hold no part of the order stop at once.
+    fn order_dividing(x: &ClassElem<B>, powers: &[(u64, u64)], id: &ClassElem<B>) -> u64 {
+        if x == id {
+            return 1;
+        }
+        if let [(p, _)] = *powers {
+            let (mut y, mut order) = (Self::pow(x, &B::from(p)), p);
+            while y != *id {
+                y = Self::pow(&y, &B::from(p));
+                order *= p;
             }
+            return order;
+        }
+        let (left, right) = powers.split_at(powers.len() / 2);
+        let left_order =
+            Self::order_dividing(&Self::pow(x, &prime_power_product::<B>(right)), left, id);
+        let right_order =
+            Self::order_dividing(&Self::pow(x, &prime_power_product::<B>(left)), right, id);
+        left_order.saturating_mul(right_order)
+    }
 
-            // Subroutine as handled by top entry to the Chia VDF competition "bulaiden."
-            // Lehmer partial extended GCD.
-            ctx.partial_context.xgcd_partial(
-                &mut y_sq_op,
-                &mut x_sq_op,
-                &mut by_sq_op,
-                &mut bx_sq_op,
-                &L_sq_op,
-            ); //L should be const
+    /// The generator element
+    pub fn unknown_order_elem() -> ClassElem<B> {
+        with_ctx!(|ctx: &mut ClassCtx<B>| ctx.unknown_order_elem())
+    }
 
-            x_sq_op.neg_mut();
-            if x_sq_op.sgn() > 0 {
-                y_sq_op.neg_mut();
-            } else {
-                by_sq_op.neg_mut();
+    /// The generator element
+    pub fn unknown_order_elem_disc(disc: &B) -> ClassElem<B> {
+        // Binary Quadratic Forms, Definition 5.4
+        let mut ret = ClassElem::<B>::default();
+        ret.a.set_ui(2);
+        ret.b.set_ui(1);
+        ret.c.set_ui(1);
+        ret.c.sub_mut(disc);
+        ret.c.fdiv_q_ui_mut(8);
+
+        Self::reduce(&mut ret);
+        ret.group = Some(GroupId::of(disc));
+        ret
+    }
+
+    /// Hashes `msg` to a group element whose discrete logarithm to any other element is unknown.
+    ///
+    /// For `k = 0, 1, ...` this takes the 256-bit prime `a = hash_to_prime_with(params, msg || k)`,
+    /// with `k` as a little-endian `u64`, until `(D/a) = 1`. Then `b` is the odd square root of
+    /// `D` mod `a`, which is also a square root of `D` mod `4a`, and the form `(a, b, (b^2 - D)/4a)`
+    /// is reduced. Only integer arithmetic is involved, so the result is the same on every
+    /// platform and backend.
+    #[cfg(feature = "gmp")]
+    pub fn hash_to_elem(msg: &[u8]) -> ClassElem<B> {
+        let (disc, group) = with_ctx!(|ctx: &mut ClassCtx<B>| (ctx.D.clone(), ctx.group));
+        let params = HashToPrimeParams::new(256, HASH_TO_ELEM_DOMAIN);
+
+        let mut input = msg.to_vec();
+        input.extend_from_slice(&[0; 8]);
+        for k in 0_u64.. {
+            input[msg.len()..].copy_from_slice(&k.to_le_bytes());
+            let prime = hash_to_prime_with(&params, &input);
+
+            let a = B::from_be_bytes(&prime.to_digits::<u8>(Order::Msf));
+            let (mut b, mut c) = (B::default(), B::default());
+            if !b.sqrt_mod_prime(&disc, &a) || b.is_zero() {
+                continue;
             }
+            if !b.odd() {
+                b.sub(&a, &b.clone());
+            }
+
+            c.mul(&b, &b);
+            c.sub_mut(&disc);
+            c.divexact_mut(&a);
+            c.fdiv_q_ui_mut(4);
+
+            let mut ret = ClassElem::new(a, b, c);
+            Self::reduce_mut(&mut ret);
+            ret.group = Some(group);
+            return ret;
+        }
+        unreachable!("ran out of counter values")
+    }
+
+    pub(crate) fn validate(a: &B, b: &B, c: &B) -> bool {
+        with_ctx!(|ctx: &mut ClassCtx<B>| ctx.validate(a, b, c))
+    }
+
+    pub fn elem(abc: (B, B, B)) -> ClassElem<B> {
+        let mut el = ClassElem::new(abc.0, abc.1, abc.2);
+        Self::reduce_mut(&mut el);
 
-            ax_sq_op.mul(&G_sq_op, &x_sq_op);
-            ay_sq_op.mul(&G_sq_op, &y_sq_op);
-
-            // Step 5 in Alg 2.
-            t_sq_op.mul(&Dy_sq_op, &bx_sq_op);
-            t_sq_op.submul(&x.c, &x_sq_op);
-            dx_sq_op.divexact(&t_sq_op, &By_sq_op);
-            Q1_sq_op.mul(&y_sq_op, &dx_sq_op);
-            dy_sq_op.add(&Q1_sq_op, &Dy_sq_op);
-            x.b.add(&dy_sq_op, &Q1_sq_op);
-            x.b.mul_mut(&G_sq_op);
-            dy_sq_op.divexact_mut(&x_sq_op);
+        // Ideally, this should return an error and the
+        // return type of ElemFrom should be Result<Self::Elem, Self:err>,
+        // but this would require a lot of ugly "unwraps" in the accumulator
+        // library. Besides, users should not need to create new class group
+        // elements, so an invalid ElemFrom here should signal a severe internal error.
+        assert!(Self::validate(&el.a, &el.b, &el.c));
+        el.group = Some(Self::current_group());
+
+        el
+    }
+}
+
+// The group operations. `ClassGroupOf` runs them on a context per thread and backend.
+impl<B: BigNum> ClassCtx<B> {
+    /// Squares `x` in place.
+    ///
+    /// # Panics
+    ///
+    /// If `x` belongs to another group.
+    #[allow(non_snake_case)]
+    pub fn square(&mut self, x: &mut ClassElem<B>) {
+        // Jacobson, Michael J., and Alfred J. Van Der Poorten. "Computational aspects of NUCOMP."
+        // Algorithm 2 (Alg 2).
+
+        if let Err(e) = check_group(self, x) {
+            panic!("{}", e);
+        }
+        x.group = Some(self.group);
+
+        let (
+            G_sq_op,
+            scratch,
+            mut y_sq_op,
+            By_sq_op,
+            Dy_sq_op,
+            mut bx_sq_op,
+            mut by_sq_op,
+            dx_sq_op,
+            q_sq_op,
+            t_sq_op,
+            ax_sq_op,
+            ay_sq_op,
+            Q1_sq_op,
+            mut x_sq_op,
+            z_sq_op,
+            dy_sq_op,
+        ) = mut_tuple_elems!(self.op_ctx, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15);
+
+        let L_sq_op = &mut self.L;
+
+        // Step 1 in Alg 2.
+        G_sq_op.gcdext(scratch, y_sq_op, &x.a, &x.b);
+        By_sq_op.divexact(&x.a, &G_sq_op);
+        Dy_sq_op.divexact(&x.b, &G_sq_op);
+
+        // Step 2 in Alg 2.
+        bx_sq_op.mul(&y_sq_op, &x.c);
+        bx_sq_op.modulo_mut(&By_sq_op);
+        by_sq_op.set(&By_sq_op);
+
+        if by_sq_op.cmpabs(&L_sq_op) <= 0 {
+            // Step 4 in Alg 2.
+            dx_sq_op.mul(&bx_sq_op, &Dy_sq_op);
+            dx_sq_op.sub_mut(&x.c);
+            dx_sq_op.divexact_mut(&By_sq_op);
             x.a.mul(&by_sq_op, &by_sq_op);
             x.c.mul(&bx_sq_op, &bx_sq_op);
             t_sq_op.add(&bx_sq_op, &by_sq_op);
-            x.b.submul(&t_sq_op, &t_sq_op);
+            t_sq_op.square_mut();
+
+            x.b.sub_mut(&t_sq_op);
             x.b.add_mut(&x.a);
             x.b.add_mut(&x.c);
-            x.a.submul(&ay_sq_op, &dy_sq_op);
-            x.c.submul(&ax_sq_op, &dx_sq_op);
-        });
+            t_sq_op.mul(&G_sq_op, &dx_sq_op);
+            x.c.sub_mut(&t_sq_op);
+            self.reduce_mut(x);
+            return;
+        }
 
-        Self::reduce_mut(x);
-    }
-
-    fn reduce_mut(x: &mut ClassElem) {
-        Self::normalize_mut(x);
-        Self::reduce(x);
-        Self::normalize_mut(x);
-    }
-
-    fn reduce(elem: &mut ClassElem) {
-        with_ctx!(|ctx: &mut ClassCtx| {
-            let (
-                x,
-                s,
-                ra,
-                rb,
-                h,
-                g,
-                j,
-                k,
-                rw,
-                l,
-                mut r_norm,
-                mut denom_norm,
-                mut mu_norm,
-                mut s_norm,
-                mut ra_norm,
-                mut rb_norm,
-            ) = mut_tuple_elems!(ctx.op_ctx, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15);
-
-            while !test_reduction(elem) {
-                let (mut a, a_exp) = mpz_get_si_2exp(&elem.a);
-                let (mut b, b_exp) = mpz_get_si_2exp(&elem.b);
-                let (mut c, c_exp) = mpz_get_si_2exp(&elem.c);
-
-                let mut max_exp = a_exp;
-                let mut min_exp = a_exp;
-
-                use std::cmp::max;
-                use std::cmp::min;
-
-                max_exp = max(max_exp, b_exp);
-                max_exp = max(max_exp, c_exp);
-                min_exp = min(min_exp, b_exp);
-                min_exp = min(min_exp, c_exp);
-
-                //println!("about to check normalize");
-
-                if max_exp - min_exp > EXP_THRESH {
-                    //Self::normalize_mut(elem);
-                    ClassGroup::normalizer(
-                        elem,
-                        &mut r_norm,
-                        &mut denom_norm,
-                        &mut mu_norm,
-                        &mut s_norm,
-                        &mut ra_norm,
-                        &mut rb_norm,
-                    );
-                    //ClassGroup::normalize_(&mut elem.a, &mut elem.b, &mut elem.c);
-                    continue;
-                }
-                //  println!("a: {}", x.a);
-                //  println!("b: {}", x.b);
-                //  println!("c: {}", x.c);
-                max_exp += 1; // for overflow safety
-                a >>= max_exp - a_exp;
-                b >>= max_exp - b_exp;
-                c >>= max_exp - c_exp;
-
-                let mut u_ = 1;
-                let mut v_ = 0;
-                let mut w_ = 0;
-                let mut y_ = 1;
-
-                let mut u;
-                let mut v;
-                let mut w;
-                let mut y;
-
-                //    println!("starting do-while loop");
-                loop {
-                    //println!("start of loop");
-                    u = u_;
-                    v = v_;
-                    w = w_;
-                    y = y_;
-                    let delta = if b >= 0 {
-                        //      println!("top");
-                        (b + c) / (c << 1)
-                    } else {
-                        //      println!("bottom");
-                        -(-b + c) / (c << 1)
-                    };
-                    let a_ = c;
-                    let mut c_ = c * delta;
-                    let b_ = -b + (c_ << 1);
-                    let gamma = b - c_;
-                    //    println!("a: {}", a);
-                    //    println!("delta: {}", delta);
-                    //    println!("gamma: {}", gamma);
-                    c_ = a - delta * gamma;
-
-                    a = a_;
-                    b = b_;
-                    c = c_;
-
-                    u_ = v;
-                    v_ = -u + delta * v;
-                    w_ = y;
-                    y_ = -w + delta * y;
-                    if !((v_.abs() | y_.abs()) <= THRESH && a > c && c > 0) {
-                        break;
-                    }
-                }
-                //println!("finished loop");
-                if (v_.abs() | y_.abs()) <= THRESH {
-                    u = u_;
-                    v = v_;
-                    w = w_;
-                    y = y_;
-                }
-                let aa = u * u;
-                //println!("aa: {}", aa);
-                let ab = u * w;
-                //println!("ab: {}", ab);
-                let ac = w * w;
-                //println!("ac: {}", ac);
-                let ba = (u * v) << 1;
-                //println!("ba: {}", ba);
-                let bb = u * y + v * w;
-                //println!("bb: {}", bb);
-                let bc = (w * y) << 1;
-                //println!("bc: {}", bc);
-                let ca = v * v;
-                //println!("ca: {}", ca);
-                let cb = v * y;
-                //println!("cb: {}", cb);
-                let cc = y * y;
-                //sprintln!("cc: {}", cc);
-
-                ra.mul_si(&elem.a, aa); // a = faa
-                rb.mul_si(&elem.b, ab); // b = fab
-                h.mul_si(&elem.c, ac); // h = fac
-
-                g.mul_si(&elem.a, ba); // g = fba
-                j.mul_si(&elem.b, bb); // j = fbb
-                k.mul_si(&elem.c, bc); // k = fbc
-
-                s.mul_si(&elem.a, ca); // s = fca
-                rw.mul_si(&elem.b, cb); // w = fcb
-                l.mul_si(&elem.c, cc); // l = fcc
-
-                elem.a.add(&ra, &rb);
-                elem.a.add_mut(&h);
-
-                elem.b.add(&g, &j);
-                elem.b.add_mut(&k);
-
-                elem.c.add(&s, &rw);
-                elem.c.add_mut(&l);
-            }
-        })
+        // Subroutine as handled by top entry to the Chia VDF competition "bulaiden."
+        // Lehmer partial extended GCD.
+        self.partial_context.xgcd_partial(
+            &mut y_sq_op,
+            &mut x_sq_op,
+            &mut by_sq_op,
+            &mut bx_sq_op,
+            &L_sq_op,
+        ); //L should be const
+
+        x_sq_op.neg_mut();
+        if x_sq_op.sgn() > 0 {
+            y_sq_op.neg_mut();
+        } else {
+            by_sq_op.neg_mut();
+        }
+
+        ax_sq_op.mul(&G_sq_op, &x_sq_op);
+        ay_sq_op.mul(&G_sq_op, &y_sq_op);
+
+        // Step 5 in Alg 2.
+        t_sq_op.mul(&Dy_sq_op, &bx_sq_op);
+        t_sq_op.submul(&x.c, &x_sq_op);
+        dx_sq_op.divexact(&t_sq_op, &By_sq_op);
+        Q1_sq_op.mul(&y_sq_op, &dx_sq_op);
+        dy_sq_op.add(&Q1_sq_op, &Dy_sq_op);
+        x.b.add(&dy_sq_op, &Q1_sq_op);
+        x.b.mul_mut(&G_sq_op);
+        dy_sq_op.divexact_mut(&x_sq_op);
+        x.a.mul(&by_sq_op, &by_sq_op);
+        x.c.mul(&bx_sq_op, &bx_sq_op);
+        t_sq_op.add(&bx_sq_op, &by_sq_op);
+        x.b.submul(&t_sq_op, &t_sq_op);
+        x.b.add_mut(&x.a);
+        x.b.add_mut(&x.c);
+        x.a.submul(&ay_sq_op, &dy_sq_op);
+        x.c.submul(&ax_sq_op, &dx_sq_op);
+
+        self.reduce_mut(x);
+    }
+
+    pub(crate) fn reduce_mut(&mut self, x: &mut ClassElem<B>) {
+        self.normalize_mut(x);
+        self.reduce(x);
+        self.normalize_mut(x);
     }
 
-    fn normalize_mut(x: &mut ClassElem) {
-        let already_normal = with_ctx!(|ctx: &mut ClassCtx| {
-            let (scratch,) = mut_tuple_elems!(ctx.op_ctx, 0);
-            if Self::elem_is_normal(scratch, &x.a, &x.b, &x.c) {
-                return true;
+    fn reduce(&mut self, elem: &mut ClassElem<B>) {
+        let (
+            x,
+            s,
+            ra,
+            rb,
+            h,
+            g,
+            j,
+            k,
+            rw,
+            l,
+            mut r_norm,
+            mut denom_norm,
+            mut mu_norm,
+            mut s_norm,
+            mut ra_norm,
+            mut rb_norm,
+        ) = mut_tuple_elems!(self.op_ctx, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15);
+
+        while !test_reduction(elem) {
+            let (mut a, a_exp) = elem.a.get_si_2exp();
+            let (mut b, b_exp) = elem.b.get_si_2exp();
+            let (mut c, c_exp) = elem.c.get_si_2exp();
+
+            let mut max_exp = a_exp;
+            let mut min_exp = a_exp;
+
+            use std::cmp::max;
+            use std::cmp::min;
+
+            max_exp = max(max_exp, b_exp);
+            max_exp = max(max_exp, c_exp);
+            min_exp = min(min_exp, b_exp);
+            min_exp = min(min_exp, c_exp);
+
+            //println!("about to check normalize");
+
+            if max_exp - min_exp > EXP_THRESH {
+                //self.normalize_mut(elem);
+                Self::normalizer(
+                    elem,
+                    &mut r_norm,
+                    &mut denom_norm,
+                    &mut mu_norm,
+                    &mut s_norm,
+                    &mut ra_norm,
+                    &mut rb_norm,
+                );
+                //ClassGroup::normalize_(&mut elem.a, &mut elem.b, &mut elem.c);
+                continue;
             }
-            false
-        });
+            //  println!("a: {}", x.a);
+            //  println!("b: {}", x.b);
+            //  println!("c: {}", x.c);
+            max_exp += 1; // for overflow safety
+            a >>= max_exp - a_exp;
+            b >>= max_exp - b_exp;
+            c >>= max_exp - c_exp;
+
+            let mut u_ = 1;
+            let mut v_ = 0;
+            let mut w_ = 0;
+            let mut y_ = 1;
+
+            let mut u;
+            let mut v;
+            let mut w;
+            let mut y;
+
+            //    println!("starting do-while loop");
+            loop {
+                //println!("start of loop");
+                u = u_;
+                v = v_;
+                w = w_;
+                y = y_;
+                let delta = if b >= 0 {
+                    //      println!("top");
+                    (b + c) / (c << 1)
+                } else {
+                    //      println!("bottom");
+                    -(-b + c) / (c << 1)
+                };
+                let a_ = c;
+                let mut c_ = c * delta;
+                let b_ = -b + (c_ << 1);
+                let gamma = b - c_;
+                //    println!("a: {}", a);
+                //    println!("delta: {}", delta);
+                //    println!("gamma: {}", gamma);
+                c_ = a - delta * gamma;
+
+                a = a_;
+                b = b_;
+                c = c_;
+
+                u_ = v;
+                v_ = -u + delta * v;
+                w_ = y;
+                y_ = -w + delta * y;
+                if !((v_.abs() | y_.abs()) <= THRESH && a > c && c > 0) {
+                    break;
+                }
+            }
+            //println!("finished loop");
+            if (v_.abs() | y_.abs()) <= THRESH {
+                u = u_;
+                v = v_;
+                w = w_;
+                y = y_;
+            }
+            let aa = u * u;
+            //println!("aa: {}", aa);
+            let ab = u * w;
+            //println!("ab: {}", ab);
+            let ac = w * w;
+            //println!("ac: {}", ac);
+            let ba = (u * v) << 1;
+            //println!("ba: {}", ba);
+            let bb = u * y + v * w;
+            //println!("bb: {}", bb);
+            let bc = (w * y) << 1;
+            //println!("bc: {}", bc);
+            let ca = v * v;
+            //println!("ca: {}", ca);
+            let cb = v * y;
+            //println!("cb: {}", cb);
+            let cc = y * y;
+            //sprintln!("cc: {}", cc);
+
+            ra.mul_si(&elem.a, aa); // a = faa
+            rb.mul_si(&elem.b, ab); // b = fab
+            h.mul_si(&elem.c, ac); // h = fac
+
+            g.mul_si(&elem.a, ba); // g = fba
+            j.mul_si(&elem.b, bb); // j = fbb
+            k.mul_si(&elem.c, bc); // k = fbc
+
+            s.mul_si(&elem.a, ca); // s = fca
+            rw.mul_si(&elem.b, cb); // w = fcb
+            l.mul_si(&elem.c, cc); // l = fcc
+
+            elem.a.add(&ra, &rb);
+            elem.a.add_mut(&h);
+
+            elem.b.add(&g, &j);
+            elem.b.add_mut(&k);
+
+            elem.c.add(&s, &rw);
+            elem.c.add_mut(&l);
+        }
+    }
 
-        if !already_normal {
-            ClassGroup::normalize(&mut x.a, &mut x.b, &mut x.c);
+    pub(crate) fn normalize_mut(&mut self, x: &mut ClassElem<B>) {
+        let (scratch,) = mut_tuple_elems!(self.op_ctx, 0);
+        if !Self::elem_is_normal(scratch, &x.a, &x.b, &x.c) {
+            self.normalize(&mut x.a, &mut x.b, &mut x.c);
         }
     }
 
-    fn normalize(a: &mut Mpz, b: &mut Mpz, c: &mut Mpz) {
-        with_ctx!(|ctx: &mut ClassCtx| {
-            let (r, denom, old_b, ra) = mut_tuple_elems!(ctx.op_ctx, 0, 1, 2, 3);
+    fn normalize(&mut self, a: &mut B, b: &mut B, c: &mut B) {
+        let (r, denom, old_b, ra) = mut_tuple_elems!(self.op_ctx, 0, 1, 2, 3);
 
-            // Binary Quadratic Forms, 5.1.1
-            r.sub(&a, &b);
-            denom.mul_ui(&a, 2);
-            r.fdiv_q_mut(&denom);
+        // Binary Quadratic Forms, 5.1.1
+        r.sub(&a, &b);
+        denom.mul_ui(&a, 2);
+        r.fdiv_q_mut(&denom);
 
-            old_b.set(&b);
+        old_b.set(&b);
 
-            ra.mul(&r, &a);
-            b.add_mut(&ra);
-            b.add_mut(&ra);
+        ra.mul(&r, &a);
+        b.add_mut(&ra);
+        b.add_mut(&ra);
 
-            ra.mul_mut(&r);
-            c.add_mut(&ra);
+        ra.mul_mut(&r);
+        c.add_mut(&ra);
 
-            ra.set(&r);
-            ra.mul_mut(&old_b);
-            c.add_mut(&ra);
-        })
+        ra.set(&r);
+        ra.mul_mut(&old_b);
+        c.add_mut(&ra);
     }
 
     fn normalizer(
-        elem: &mut ClassElem,
-        r: &mut Mpz,
-        denom: &mut Mpz,
-        mu: &mut Mpz,
-        s: &mut Mpz,
-        ra: &mut Mpz,
-        rb: &mut Mpz,
+        elem: &mut ClassElem<B>,
+        r: &mut B,
+        denom: &mut B,
+        mu: &mut B,
+        s: &mut B,
+        ra: &mut B,
+        rb: &mut B,
     ) {
         mu.add(&elem.b, &elem.c);
         s.mul_ui(&elem.c, 2);
@@ -426,315 +702,338 @@ impl ClassGroup {
     }
 
     //WIP NUCOMP
-    pub fn nucomp(x: &ClassElem, y: &ClassElem) -> ClassElem {
+    pub fn nucomp(&mut self, x: &ClassElem<B>, y: &ClassElem<B>) -> ClassElem<B> {
         //a1, a2, c2, ca, cb, cc, k, s, sp, ss, m, t, u2, v1, v2;
-        let mut unreduced = with_ctx!(|ctx: &mut ClassCtx| {
-            let (a1, a2, c2, mut co1, mut co2, m1, k, s, sp, ss, m, t, mut u2, v1, mut v2, mut temp) = mut_tuple_elems!(
-                ctx.op_ctx, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15
-            );
+        if x.a > y.a {
+            return self.nucomp(y, x);
+        }
 
-            let L_sq_op = &mut ctx.L;
+        let (a1, a2, c2, mut co1, mut co2, m1, k, s, sp, ss, m, t, mut u2, v1, mut v2, mut temp) = mut_tuple_elems!(
+            self.op_ctx, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15
+        );
 
-            if x.a.cmp_mpz(&y.a) > 0 {
-                Self::nucomp(y, x);
-            } 
-            
-            /* nucomp calculation */
-            let mut ret = ClassElem::default();
-            //TODO: Remove these allocations
-            let mut r1 = Mpz::default();
-            let mut r2 = Mpz::default();
-            let mut m2 = Mpz::default();
-            // a1 = x.a;
-            // a2 = y.a;
-            // c2 = y.c;
-
-            ss.add(&x.a, &y.a);
-            ss.fdiv_q_ui_mut(2);
-
-            m.sub(&x.b, &y.b);
-            m.fdiv_q_ui_mut(2);
-
-            t.fdiv_r(&y.a, &x.a);
-
-            if t.is_zero() {
-                v1.set_ui(0);
-                sp.set(&x.a);
-            } else {
-                //gcdinv
-            }
+        let L_sq_op = &mut self.L;
 
-            k.mul(&m, &v1);
-            k.fdiv_r_mut(&a1);
+        /* nucomp calculation */
+        let mut ret = ClassElem::<B>::default();
+        //TODO: Remove these allocations
+        let mut r1 = B::default();
+        let mut r2 = B::default();
+        let mut m2 = B::default();
+        // a1 = x.a;
+        // a2 = y.a;
+        // c2 = y.c;
 
-            if !sp.is_one() {
-                s.gcdext(&mut v2, &mut u2, &ss, &sp);
-                k.mul_mut(&u2);
-                t.mul(&v2, &c2);
-                k.sub_mut(&t);
+        ss.add(&x.a, &y.a);
+        ss.fdiv_q_ui_mut(2);
 
-                if !s.is_one() {
-                    a1.fdiv_q_mut(&s);
-                    a2.fdiv_q_mut(&s);
-                    c2.mul_mut(&s);
-                }
+        m.sub(&x.b, &y.b);
+        m.fdiv_q_ui_mut(2);
+
+        t.fdiv_r(&y.a, &x.a);
+
+        if t.is_zero() {
+            v1.set_ui(0);
+            sp.set(&x.a);
+        } else {
+            //gcdinv
+        }
 
-                k.fdiv_r_mut(&a1);
+        k.mul(&m, &v1);
+        k.fdiv_r_mut(&a1);
+
+        if !sp.is_one() {
+            s.gcdext(&mut v2, &mut u2, &ss, &sp);
+            k.mul_mut(&u2);
+            t.mul(&v2, &c2);
+            k.sub_mut(&t);
+
+            if !s.is_one() {
+                a1.fdiv_q_mut(&s);
+                a2.fdiv_q_mut(&s);
+                c2.mul_mut(&s);
             }
 
-            if a1.cmp_mpz(&L_sq_op) < 0 {
-                t.mul(&a2, &k);
-                ret.a.mul(&a2, &a1);
+            k.fdiv_r_mut(&a1);
+        }
+
+        if *a1 < *L_sq_op {
+            t.mul(&a2, &k);
+            ret.a.mul(&a2, &a1);
 
-                ret.b.mul_ui(&t, 2);
-                ret.b.add_mut(&y.b);
+            ret.b.mul_ui(&t, 2);
+            ret.b.add_mut(&y.b);
 
-                ret.c.add(&y.b, &t);
-                ret.c.mul_mut(&k);
-                ret.c.add_mut(&c2);
-                
-                ret.c.fdiv_q_mut(&a1);
-            } else {
-                // fmpz_t m1, m2, r1, r2, co1, co2, temp;
-                // fmpz_set(r2, a1);
-                // fmpz_set(r1, k);
+            ret.c.add(&y.b, &t);
+            ret.c.mul_mut(&k);
+            ret.c.add_mut(&c2);
+            
+            ret.c.fdiv_q_mut(&a1);
+        } else {
+            // fmpz_t m1, m2, r1, r2, co1, co2, temp;
+            // fmpz_set(r2, a1);
+            // fmpz_set(r1, k);
           
-                // Lehmer partial extended GCD.
-                ctx.partial_context.xgcd_partial(
-                    &mut co2,
-                    &mut co1,
-                    &mut r2,
-                    &mut r1,
-                    &L_sq_op,
-                ); //L should be const
-
-                t.mul(&a2, &r1);
-                m1.mul(&m, &co1);
-                m1.add_mut(&t);
-                m1.tdiv_q_mut(&a1);
-
-                m2.mul(&ss, &r1);
-                temp.mul(&c2, &co1);
-                m2.sub_mut(&temp);
-                m2.tdiv_q_mut(&a1);
-
-                ret.a.mul(&r1, &m1);
-                temp.mul(&co1, &m2);
-
-                if co1.sgn() < 0 {
-                    ret.a.sub_mut(&temp); //could be sub other way around
-                } else {
-                    ret.a.sub_mut(&temp);//could be sub other way around
-                }
-                
-                ret.b.mul(&ret.a, &co2);
-                ret.b.sub_mut(&t); //could be sub other way around
-                ret.b.mul_ui_mut(2);
-                ret.b.fdiv_q_mut(&co1);
-                ret.b.sub_mut(&y.b);
-
-                temp.mul_ui(&ret.a, 2);
-                ret.b.fdiv_r_mut(&temp);
-
-                ret.c.mul(&ret.b, &ret.b);
-                ret.c.sub_mut(&ctx.D);
-                ret.c.fdiv_q_mut(&ret.a);
-                ret.c.fdiv_q_ui_mut(2);
-
-                if ret.a.sgn() < 0 {
-                    ret.a.neg_mut();
-                    ret.c.neg_mut();
-                }
+            // Lehmer partial extended GCD.
+            self.partial_context.xgcd_partial(
+                &mut co2,
+                &mut co1,
+                &mut r2,
+                &mut r1,
+                &L_sq_op,
+            ); //L should be const
 
-            }
+            t.mul(&a2, &r1);
+            m1.mul(&m, &co1);
+            m1.add_mut(&t);
+            m1.tdiv_q_mut(&a1);
 
-            ret
-        });
+            m2.mul(&ss, &r1);
+            temp.mul(&c2, &co1);
+            m2.sub_mut(&temp);
+            m2.tdiv_q_mut(&a1);
 
-        Self::reduce_mut(&mut unreduced);
-        unreduced
-    }
-
-    pub fn op(x: &ClassElem, y: &ClassElem) -> ClassElem {
-        let mut unreduced = with_ctx!(|ctx: &mut ClassCtx| {
-            let (g, h, j, w, r, s, t, u, a, b, l, m, mut mu, mut v, mut lambda, mut sigma, k) = mut_tuple_elems!(
-                ctx.op_ctx, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16
-            );
-
-            // Binary Quadratic Forms, 6.1.1
-            g.add(&x.b, &y.b);
-            g.fdiv_q_ui_mut(2);
-            h.sub(&y.b, &x.b);
-            h.fdiv_q_ui_mut(2);
-            w.gcd(&x.a, &y.a);
-            w.gcd_mut(&g);
-            j.set(&w);
-            r.set_ui(0);
-            s.fdiv_q(&x.a, &w);
-            t.fdiv_q(&y.a, &w);
-            u.fdiv_q(&g, &w);
-            a.mul(&t, &u);
-            b.mul(&h, &u);
-            m.mul(&s, &x.c);
-            b.add_mut(&m);
-            m.mul(&s, &t);
-            ctx.lin_cong_ctx
-                .solve_linear_congruence(&mut mu, &mut v, &a, &b, &m)
-                .unwrap();
-
-            a.mul(&t, &v);
-            m.mul(&t, &mu);
-            b.sub(&h, &m);
-            m.set(&s);
-            ctx.lin_cong_ctx
-                .solve_linear_congruence(&mut lambda, &mut sigma, &a, &b, &m)
-                .unwrap();
-
-            a.mul(&v, &lambda);
-            k.add(&mu, &a);
-            l.mul(&k, &t);
-            l.sub_mut(&h);
-            l.fdiv_q_mut(&s);
-            m.mul(&t, &u);
-            m.mul_mut(&k);
-            a.mul(&h, &u);
-            m.sub_mut(&a);
-            a.mul(&x.c, &s);
-            m.sub_mut(&a);
-            a.mul(&s, &t);
-            m.fdiv_q_mut(&a);
-
-            let mut ret = ClassElem::default();
-
-            ret.a.mul(&s, &t);
-            a.mul(&r, &u);
-            ret.a.sub_mut(&a);
-
-            ret.b.mul(&j, &u);
-            a.mul(&m, &r);
-            ret.b.add_mut(&a);
-            a.mul(&k, &t);
-            ret.b.sub_mut(&a);
-            a.mul(&l, &s);
-            ret.b.sub_mut(&a);
-
-            ret.c.mul(&k, &l);
-            a.mul(&j, &m);
-            ret.c.sub_mut(&a);
-            ret
-        });
+            ret.a.mul(&r1, &m1);
+            temp.mul(&co1, &m2);
 
-        Self::reduce_mut(&mut unreduced);
-        unreduced
-    }
+            if co1.sgn() < 0 {
+                ret.a.sub_mut(&temp); //could be sub other way around
+            } else {
+                ret.a.sub_mut(&temp);//could be sub other way around
+            }
+            
+            ret.b.mul(&ret.a, &co2);
+            ret.b.sub_mut(&t); //could be sub other way around
+            ret.b.mul_ui_mut(2);
+            ret.b.fdiv_q_mut(&co1);
+            ret.b.sub_mut(&y.b);
+
+            temp.mul_ui(&ret.a, 2);
+            ret.b.fdiv_r_mut(&temp);
+
+            ret.c.mul(&ret.b, &ret.b);
+            ret.c.sub_mut(&self.D);
+            ret.c.fdiv_q_mut(&ret.a);
+            ret.c.fdiv_q_ui_mut(2);
+
+            if ret.a.sgn() < 0 {
+                ret.a.neg_mut();
+                ret.c.neg_mut();
+            }
 
-    fn id() -> ClassElem {
-        with_ctx!(|ctx: &mut ClassCtx| {
-            let (a,) = mut_tuple_elems!(ctx.op_ctx, 0);
+        }
 
-            // Binary Quadratic Forms, Definition 5.4
-            // The identity is the Principal Form of Discriminant d.
-            let mut ret = ClassElem::default();
-            ret.a.set_ui(1);
-            ret.b.set_ui(1);
-            a.sub(&ret.b, &CLASS_GROUP_DISCRIMINANT);
-            ret.c.fdiv_q_ui(&a, 4);
-            ret
-        })
+        self.reduce_mut(&mut ret);
+        ret
     }
 
-    fn inv(x: &ClassElem) -> ClassElem {
+    /// Composes `x` and `y`.
+    ///
+    /// # Panics
+    ///
+    /// If either belongs to another group; `try_op` returns the error instead.
+    pub fn op(&mut self, x: &ClassElem<B>, y: &ClassElem<B>) -> ClassElem<B> {
         let mut ret = ClassElem::default();
-        ret.a.set(&x.a);
-        ret.b.neg(&x.b);
-        ret.c.set(&x.c);
+        self.op_into(&mut ret, x, y);
         ret
     }
 
-    pub fn pow(a: &ClassElem, n: &Integer) -> ClassElem {
-        let (mut val, mut a, mut n) = {
-            if *n < Integer::from(0) {
-                (Self::id(), Self::inv(&a), Integer::from(-n))
-            } else {
-                (Self::id(), a.clone(), n.clone())
-            }
-        };
-        loop {
-            if n == Integer::from(0) {
-                return val;
-            }
+    /// Composes `x` and `y`, or fails if either belongs to another group.
+    pub fn try_op(
+        &mut self,
+        x: &ClassElem<B>,
+        y: &ClassElem<B>,
+    ) -> Result<ClassElem<B>, GroupMismatch> {
+        self.op_prepare(x, y)?;
+        let mut ret = ClassElem::default();
+        self.op_finish(&mut ret);
+        Ok(ret)
+    }
 
-            if n.is_odd() {
-                val = Self::op(&val, &a);
-            }
+    /// Sets `out` to `x * y`, reusing the limbs of `out`.
+    ///
+    /// # Panics
+    ///
+    /// If `x` or `y` belongs to another group.
+    pub fn op_into(&mut self, out: &mut ClassElem<B>, x: &ClassElem<B>, y: &ClassElem<B>) {
+        if let Err(e) = self.op_prepare(x, y) {
+            panic!("{}", e);
+        }
+        self.op_finish(out);
+    }
 
-            Self::square(&mut a);
-            n >>= 1;
+    /// Sets `x` to `x * y` in place.
+    ///
+    /// # Panics
+    ///
+    /// If `x` or `y` belongs to another group.
+    pub fn op_assign(&mut self, x: &mut ClassElem<B>, y: &ClassElem<B>) {
+        if let Err(e) = self.op_prepare(x, y) {
+            panic!("{}", e);
         }
+        self.op_finish(x);
     }
 
-    /// The generator element
-    pub fn unknown_order_elem() -> ClassElem {
+    // Composition is split in two so that the output may be one of the inputs: this half reads
+    // `x` and `y` and leaves the values the result is built from in the context, and
+    // `op_finish` writes the result.
+    fn op_prepare(&mut self, x: &ClassElem<B>, y: &ClassElem<B>) -> Result<(), GroupMismatch> {
+        check_group(self, x)?;
+        check_group(self, y)?;
+
+        let (g, h, j, w, r, s, t, u, a, b, l, m, mut mu, mut v, mut lambda, mut sigma, k) = mut_tuple_elems!(
+            self.op_ctx, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16
+        );
+
+        // Binary Quadratic Forms, 6.1.1
+        g.add(&x.b, &y.b);
+        g.fdiv_q_ui_mut(2);
+        h.sub(&y.b, &x.b);
+        h.fdiv_q_ui_mut(2);
+        w.gcd(&x.a, &y.a);
+        w.gcd_mut(&g);
+        j.set(&w);
+        r.set_ui(0);
+        s.fdiv_q(&x.a, &w);
+        t.fdiv_q(&y.a, &w);
+        u.fdiv_q(&g, &w);
+        a.mul(&t, &u);
+        b.mul(&h, &u);
+        m.mul(&s, &x.c);
+        b.add_mut(&m);
+        m.mul(&s, &t);
+        self.lin_cong_ctx
+            .solve_linear_congruence(&mut mu, &mut v, &a, &b, &m)
+            .unwrap();
+
+        a.mul(&t, &v);
+        m.mul(&t, &mu);
+        b.sub(&h, &m);
+        m.set(&s);
+        self.lin_cong_ctx
+            .solve_linear_congruence(&mut lambda, &mut sigma, &a, &b, &m)
+            .unwrap();
+
+        a.mul(&v, &lambda);
+        k.add(&mu, &a);
+        l.mul(&k, &t);
+        l.sub_mut(&h);
+        l.fdiv_q_mut(&s);
+        m.mul(&t, &u);
+        m.mul_mut(&k);
+        a.mul(&h, &u);
+        m.sub_mut(&a);
+        a.mul(&x.c, &s);
+        m.sub_mut(&a);
+        a.mul(&s, &t);
+        m.fdiv_q_mut(&a);
+        Ok(())
+    }
+
+    fn op_finish(&mut self, ret: &mut ClassElem<B>) {
+        ret.group = Some(self.group);
+        let (j, r, s, t, u, a, l, m, k) =
+            mut_tuple_elems!(self.op_ctx, 2, 4, 5, 6, 7, 8, 10, 11, 16);
+
+        ret.a.mul(&s, &t);
+        a.mul(&r, &u);
+        ret.a.sub_mut(&a);
+
+        ret.b.mul(&j, &u);
+        a.mul(&m, &r);
+        ret.b.add_mut(&a);
+        a.mul(&k, &t);
+        ret.b.sub_mut(&a);
+        a.mul(&l, &s);
+        ret.b.sub_mut(&a);
+
+        ret.c.mul(&k, &l);
+        a.mul(&j, &m);
+        ret.c.sub_mut(&a);
+
+        self.reduce_mut(ret);
+    }
+
+    /// The identity element, the principal form of the discriminant.
+    pub fn id(&mut self) -> ClassElem<B> {
+        let (a,) = mut_tuple_elems!(self.op_ctx, 0);
+
         // Binary Quadratic Forms, Definition 5.4
-        let mut ret = ClassElem::default();
-        ret.a.set_ui(2);
-        ret.b.set_ui(1);
-        ret.c.set_ui(1);
-        ret.c.sub_mut(&CLASS_GROUP_DISCRIMINANT);
-        ret.c.fdiv_q_ui_mut(8);
+        // The identity is the Principal Form of Discriminant d.
+        let mut ret = ClassElem::<B>::default();
+        ret.a.set_ui(1);
+        ret.b.set_ui(if self.D.odd() { 1 } else { 0 });
+        a.sub(&ret.b, &self.D);
+        ret.c.fdiv_q_ui(&a, 4);
+        ret.group = Some(self.group);
+        ret
+    }
 
-        Self::reduce(&mut ret);
-        ClassElem {
-            a: ret.a,
-            b: ret.b,
-            c: ret.c,
+    /// The inverse of `x`, see `ClassElem::invert`.
+    pub fn inv(&mut self, x: &ClassElem<B>) -> ClassElem<B> {
+        let mut ret = x.clone();
+        ret.b.neg_mut();
+        if !ret.is_reduced() {
+            self.reduce_mut(&mut ret);
+        }
+        ret
+    }
+
+    /// Raises `a` to the power `n`. The exponent may use any big-number backend.
+    ///
+    /// # Panics
+    ///
+    /// If `a` belongs to another group.
+    pub fn pow<E: BigNum>(&mut self, a: &ClassElem<B>, n: &E) -> ClassElem<B> {
+        let (mut val, mut a) = if n.is_neg() {
+            (self.id(), self.inv(a))
+        } else {
+            (self.id(), a.clone())
+        };
+        let mut n_abs = E::default();
+        n_abs.abs(n);
+
+        let bits = n_abs.bit_length();
+        for i in 0..bits {
+            if n_abs.tstbit(i) {
+                self.op_assign(&mut val, &a);
+            }
+            if i + 1 < bits {
+                self.square(&mut a);
+            }
         }
+        val
     }
 
-    /// The generator element
-    pub fn unknown_order_elem_disc(disc: &Mpz) -> ClassElem {
-        // Binary Quadratic Forms, Definition 5.4
-        let mut ret = ClassElem::default();
+    /// The generator element, see `ClassGroupOf::unknown_order_elem_disc`.
+    pub fn unknown_order_elem(&mut self) -> ClassElem<B> {
+        let mut ret = ClassElem::<B>::default();
         ret.a.set_ui(2);
         ret.b.set_ui(1);
         ret.c.set_ui(1);
-        ret.c.sub_mut(disc);
+        ret.c.sub_mut(&self.D);
         ret.c.fdiv_q_ui_mut(8);
 
-        Self::reduce(&mut ret);
-        ClassElem {
-            a: ret.a,
-            b: ret.b,
-            c: ret.c,
-        }
+        self.reduce(&mut ret);
+        ret.group = Some(self.group);
+        ret
     }
 
-    fn validate(a: &Mpz, b: &Mpz, c: &Mpz) -> bool {
-        ClassGroup::discriminant(a, b, c) == *CLASS_GROUP_DISCRIMINANT
+    pub(crate) fn validate(&mut self, a: &B, b: &B, c: &B) -> bool {
+        let (scratch,) = mut_tuple_elems!(self.op_ctx, 0);
+
+        let mut d = B::default();
+        d.mul(&b, &b);
+        scratch.mul(&a, &c);
+        scratch.mul_ui_mut(4);
+        d.sub_mut(&scratch);
+        d == self.D
     }
 
-    fn elem_is_normal(scratch: &mut Mpz, a: &Mpz, b: &Mpz, _c: &Mpz) -> bool {
+    fn elem_is_normal(scratch: &mut B, a: &B, b: &B, _c: &B) -> bool {
         scratch.neg(&a);
         *scratch < *b && b <= a
     }
-
-    pub fn elem(abc: (Mpz, Mpz, Mpz)) -> ClassElem {
-        let mut el = ClassElem {
-            a: abc.0,
-            b: abc.1,
-            c: abc.2,
-        };
-        ClassGroup::reduce(&mut el);
-
-        // Ideally, this should return an error and the
-        // return type of ElemFrom should be Result<Self::Elem, Self:err>,
-        // but this would require a lot of ugly "unwraps" in the accumulator
-        // library. Besides, users should not need to create new class group
-        // elements, so an invalid ElemFrom here should signal a severe internal error.
-        assert!(ClassGroup::validate(&el.a, &el.b, &el.c));
-
-        el
-    }
 }
 
 //  Caveat: tests that use "ground truth" use outputs from
@@ -743,6 +1042,7 @@ impl ClassGroup {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::group::ClassGroupStructure;
     use std::collections::hash_map::DefaultHasher;
     use std::hash::{Hash, Hasher};
     use std::str::FromStr;
@@ -755,24 +1055,24 @@ mod tests {
 
     // Makes a class elem tuple but does not reduce.
     fn construct_raw_elem_from_strings(a: &str, b: &str, c: &str) -> ClassElem {
-        ClassElem {
-            a: Mpz::from_str(a).unwrap(),
-            b: Mpz::from_str(b).unwrap(),
-            c: Mpz::from_str(c).unwrap(),
-        }
+        ClassElem::new(
+            DefaultBigNum::from_str(a).unwrap(),
+            DefaultBigNum::from_str(b).unwrap(),
+            DefaultBigNum::from_str(c).unwrap(),
+        )
     }
 
     #[should_panic]
     #[test]
     fn test_bad_elem() {
-        let _ = ClassGroup::elem((Mpz::from(1), Mpz::from(2), Mpz::from(3)));
+        let _ = ClassGroup::elem((DefaultBigNum::from(1), DefaultBigNum::from(2), DefaultBigNum::from(3)));
     }
 
     #[test]
     fn test_elem_from() {
-        let a1 = Mpz::from_str("16").unwrap();
-        let b1 = Mpz::from_str("105").unwrap();
-        let c1 = Mpz::from_str(
+        let a1 = DefaultBigNum::from_str("16").unwrap();
+        let b1 = DefaultBigNum::from_str("105").unwrap();
+        let c1 = DefaultBigNum::from_str(
       "47837607866886756167333839869251273774207619337757918597995294777816250058331116325341018110\
       672047217112377476473502060121352842575308793237621563947157630098485131517401073775191194319\
       531549483898334742144138601661120476425524333273122132151927833887323969998955713328783526854\
@@ -783,9 +1083,9 @@ mod tests {
     )
     .unwrap();
 
-        let a2 = Mpz::from_str("16").unwrap();
-        let b2 = Mpz::from_str("9").unwrap();
-        let c2 = Mpz::from_str(
+        let a2 = DefaultBigNum::from_str("16").unwrap();
+        let b2 = DefaultBigNum::from_str("9").unwrap();
+        let c2 = DefaultBigNum::from_str(
       "47837607866886756167333839869251273774207619337757918597995294777816250058331116325341018110\
       672047217112377476473502060121352842575308793237621563947157630098485131517401073775191194319\
       531549483898334742144138601661120476425524333273122132151927833887323969998955713328783526854\
@@ -955,9 +1255,9 @@ mod tests {
 
     #[test]
     fn test_normalize_basic() {
-        let mut unnorm_a = Mpz::from_str("16").unwrap();
-        let mut unnorm_b = Mpz::from_str("105").unwrap();
-        let mut unnorm_c = Mpz::from_str(
+        let mut unnorm_a = DefaultBigNum::from_str("16").unwrap();
+        let mut unnorm_b = DefaultBigNum::from_str("105").unwrap();
+        let mut unnorm_c = DefaultBigNum::from_str(
       "4783760786688675616733383986925127377420761933775791859799529477781625005833111632534101811\
        0672047217112377476473502060121352842575308793237621563947157630098485131517401073775191194\
        3195315494838983347421441386016611204764255243332731221321519278338873239699989557133287835\
@@ -968,9 +1268,9 @@ mod tests {
     )
     .unwrap();
 
-        let norm_a = Mpz::from_str("16").unwrap();
-        let norm_b = Mpz::from_str("9").unwrap();
-        let norm_c = Mpz::from_str(
+        let norm_a = DefaultBigNum::from_str("16").unwrap();
+        let norm_b = DefaultBigNum::from_str("9").unwrap();
+        let norm_c = DefaultBigNum::from_str(
       "4783760786688675616733383986925127377420761933775791859799529477781625005833111632534101811\
        06720472171123774764735020601213528425753087932376215639471576300984851315174010737751911943\
        19531549483898334742144138601661120476425524333273122132151927833887323969998955713328783526\
@@ -981,7 +1281,7 @@ mod tests {
     )
     .unwrap();
 
-        ClassGroup::normalize(&mut unnorm_a, &mut unnorm_b, &mut unnorm_c);
+        ClassCtx::<DefaultBigNum>::default().normalize(&mut unnorm_a, &mut unnorm_b, &mut unnorm_c);
         assert_eq!((norm_a, norm_b, norm_c), (unnorm_a, unnorm_b, unnorm_c));
     }
 
@@ -1121,6 +1421,147 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_op_in_place() {
+        let g = ClassGroup::unknown_order_elem();
+        let h = ClassGroup::op(&g, &g);
+        let gh = ClassGroup::op(&g, &h);
+
+        let mut out = ClassGroup::id();
+        ClassGroup::op_into(&mut out, &g, &h);
+        assert_eq!(out, gh);
+        ClassGroup::op_into(&mut out, &g, &g);
+        assert_eq!(out, h);
+
+        let mut x = g.clone();
+        ClassGroup::op_assign(&mut x, &h);
+        assert_eq!(x, gh);
+        let y = x.clone();
+        ClassGroup::op_assign(&mut x, &y);
+        assert_eq!(x, ClassGroup::op(&gh, &gh));
+    }
+
+    #[test]
+    fn test_explicit_ctx() {
+        fn assert_send<T: Send>() {}
+        assert_send::<ClassCtx<DefaultBigNum>>();
+
+        let g = ClassGroup::unknown_order_elem();
+        let g3 = ClassGroup::pow(&g, &DefaultBigNum::from(3));
+        let mut ctx = ClassCtx::<DefaultBigNum>::default();
+        assert_eq!(ctx.group(), ClassGroup::current_group());
+        assert_eq!(ctx.unknown_order_elem(), g);
+        assert_eq!(ctx.pow(&g, &DefaultBigNum::from(3)), g3);
+
+        // A context moved to another thread computes the same values.
+        let (mut ctx, x) = std::thread::spawn(move || {
+            let mut x = ctx.op(&g, &g);
+            let g = ctx.unknown_order_elem();
+            ctx.op_assign(&mut x, &g);
+            (ctx, x)
+        })
+        .join()
+        .unwrap();
+        assert_eq!(x, g3);
+        let mut y = ctx.id();
+        ctx.square(&mut y);
+        assert_eq!(y, ClassGroup::id());
+        let x_inv = ctx.inv(&x);
+        assert_eq!(ctx.op(&x_inv, &x), y);
+
+        // Contexts of different groups are used side by side.
+        let d = DefaultBigNum::from_str("-999983").unwrap();
+        let mut small = ClassCtx::from_discriminant(&d);
+        assert_eq!(small.discriminant(), &d);
+        let h = small.unknown_order_elem();
+        assert_eq!(
+            ctx.try_op(&x, &h),
+            Err(GroupMismatch {
+                expected: ctx.group(),
+                found: small.group(),
+            })
+        );
+        let order = ClassGroupStructure::compute(&d).unwrap().element_order(&h);
+        let mut h_order = small.pow(&h, &DefaultBigNum::from(order));
+        assert!(h_order.is_identity());
+        small.op_assign(&mut h_order, &h);
+        assert_eq!(h_order, h);
+    }
+
+    #[test]
+    fn test_nested_ctx() {
+        let d = DefaultBigNum::from_str("-999983").unwrap();
+        let g = ClassGroup::unknown_order_elem();
+        let g3 = ClassGroup::pow(&g, &DefaultBigNum::from(3));
+        let h = ClassCtx::from_discriminant(&d).unknown_order_elem();
+        let h2 = ClassCtx::from_discriminant(&d).op(&h, &h);
+
+        // Explicit contexts work inside the thread-local one and inside each other.
+        let (x, y) = with_class_ctx(|outer: &mut ClassCtx<DefaultBigNum>| {
+            let mut small = ClassCtx::from_discriminant(&d);
+            let mut big = ClassCtx::<DefaultBigNum>::default();
+            let y = small.op(&h, &h);
+            let x = big.pow(&outer.unknown_order_elem(), &DefaultBigNum::from(3));
+            (x, y)
+        });
+        assert_eq!(x, g3);
+        assert_eq!(y, h2);
+
+        // The same holds with the thread switched to another group.
+        let x = ClassGroup::with_discriminant(&d, || {
+            let mut big = ClassCtx::<DefaultBigNum>::default();
+            let g = big.unknown_order_elem();
+            let x = big.pow(&g, &DefaultBigNum::from(3));
+            assert_eq!(ClassGroup::op(&h, &h), h2);
+            x
+        });
+        assert_eq!(x, g3);
+    }
+
+    #[test]
+    fn test_cross_group_op() {
+        let g = ClassGroup::unknown_order_elem();
+        let h = ClassGroup::unknown_order_elem_disc(&DefaultBigNum::from_str("-23").unwrap());
+        let mismatch = GroupMismatch {
+            expected: ClassGroup::current_group(),
+            found: h.group().unwrap(),
+        };
+        assert_eq!(ClassGroup::try_op(&g, &h), Err(mismatch));
+        assert_eq!(ClassGroup::try_op(&h, &g), Err(mismatch));
+        assert_eq!(ClassGroup::try_op(&g, &g), Ok(ClassGroup::op(&g, &g)));
+
+        // Untagged forms of the group's discriminant are accepted, and the result joins the
+        // group. Those of another discriminant are not.
+        let raw = ClassElem::new(g.a.clone(), g.b.clone(), g.c.clone());
+        assert_eq!(raw.group(), None);
+        assert_eq!(ClassGroup::try_op(&raw, &g).unwrap().group(), g.group());
+        let foreign = ClassElem::new(h.a.clone(), h.b.clone(), h.c.clone());
+        assert_eq!(foreign.group(), None);
+        assert_eq!(ClassGroup::try_op(&g, &foreign), Err(mismatch));
+        assert_eq!(ClassGroup::try_op(&foreign, &foreign), Err(mismatch));
+        let mut x = foreign.clone();
+        assert!(std::panic::catch_unwind(move || ClassGroup::square(&mut x)).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "used in group")]
+    fn test_cross_group_op_panics_on_untagged_forms() {
+        let h = ClassElem::new(
+            DefaultBigNum::from(2),
+            DefaultBigNum::from(1),
+            DefaultBigNum::from(3),
+        );
+        ClassGroup::op(&ClassGroup::id(), &h);
+    }
+
+    #[test]
+    #[should_panic(expected = "used in group")]
+    fn test_cross_group_op_panics() {
+        let d = DefaultBigNum::from_str("-23").unwrap();
+        let h = ClassGroup::unknown_order_elem_disc(&d);
+        ClassGroup::op(&ClassGroup::id(), &h);
+    }
+
     #[test]
     fn test_id_basic() {
         let g = ClassGroup::unknown_order_elem();
@@ -1165,7 +1606,7 @@ mod tests {
 
         for i in 1..=1000 {
             g = ClassGroup::op(&g, &g_anchor);
-            assert_eq!(&g, &ClassGroup::pow(&g_anchor, &Integer::from(i)));
+            assert_eq!(&g, &ClassGroup::pow(&g_anchor, &DefaultBigNum::from(i as u64)));
         }
     }
 
@@ -1205,6 +1646,50 @@ mod tests {
         }
     }
 
+    // Top bits, bit length and low bits of each coefficient, comparable across backends.
+    fn fingerprint<B: BigNum>(x: &ClassElem<B>) -> Vec<(i64, i64, i64)> {
+        [&x.a, &x.b, &x.c]
+            .iter()
+            .map(|n| {
+                let (top, exp) = n.get_si_2exp();
+                (top, exp, n.get_si())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_backends_agree() {
+        fn pow_fingerprint<B: BigNum>() -> Vec<(i64, i64, i64)> {
+            let g = ClassGroupOf::<B>::unknown_order_elem();
+            let mut g2 = ClassGroupOf::<B>::op(&g, &g);
+            ClassGroupOf::<B>::square(&mut g2);
+            let x = ClassGroupOf::<B>::pow(&g2, &B::from(123_456_789));
+            fingerprint(&x)
+        }
+
+        let expected = pow_fingerprint::<DefaultBigNum>();
+        #[cfg(feature = "gmp")]
+        assert_eq!(expected, pow_fingerprint::<rug::Integer>());
+        #[cfg(feature = "num-bigint")]
+        assert_eq!(expected, pow_fingerprint::<num_bigint::BigInt>());
+    }
+
+    #[cfg(feature = "gmp")]
+    #[test]
+    fn test_hash_to_elem() {
+        let x = ClassGroup::hash_to_elem(b"martian cyborg gerbil attack");
+        let y = ClassGroup::hash_to_elem(b"martian cyborg gerbil attacl");
+        assert_eq!(x, ClassGroup::hash_to_elem(b"martian cyborg gerbil attack"));
+        assert_ne!(x, y);
+        assert_ne!(x, ClassGroup::id());
+        assert!(ClassGroup::validate(&x.a, &x.b, &x.c));
+        assert!(ClassGroup::validate(&y.a, &y.b, &y.c));
+        assert_eq!(x, ClassGroup::elem((x.a.clone(), x.b.clone(), x.c.clone())));
+
+        let z = ClassGroupOf::<rug::Integer>::hash_to_elem(b"martian cyborg gerbil attack");
+        assert_eq!(fingerprint(&x), fingerprint(&z));
+    }
+
     fn split_into_three_pieces(line: &str, c: char) -> [&str; 3] {
         let mut iter = line.split(c);
         let fst = iter.next().expect("bad test file");
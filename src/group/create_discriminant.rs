@@ -8,13 +8,128 @@
 //Handle Precomputes
 include!(concat!(env!("OUT_DIR"), "/constants.rs"));
 
-use crate::num::Mpz;
+use super::ResidueClass;
+use crate::hash::primality::big::{is_prob_prime, passes_miller_rabin, passes_strong_lucas};
+use crate::num::{BigNum, Mpz};
 use bacteria::Transcript;
+use bit_vec::BitVec;
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Number of candidates `n + M * i` covered by one sieve block.
+const SIEVE_SIZE: usize = 1 << 16;
+
+/// How a seed is expanded into the first candidate of the search.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Expansion {
+    /// A Merlin transcript under the label, with the seed and length appended. The candidate is
+    /// read from whole bytes of output, so it can be up to 7 bits longer than `length` when that
+    /// is not a multiple of 8.
+    Merlin,
+    /// SHA-256 of the seed followed by a 2-byte big-endian block counter, as in Chia's VDF. The
+    /// candidate is cut down to `length` bits and its top bit set. A non-empty label goes in front
+    /// of the seed, prefixed by its length as a little-endian `u64`.
+    Sha256,
+}
+
+/// How a discriminant is derived from a seed: the expansion of the seed, the residue class of
+/// the discriminant and the domain label. The presets are `classygroup`, which is what
+/// `create_discriminant` and the other free functions use, and `chia`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiscriminantParams {
+    expansion: Expansion,
+    residue: ResidueClass,
+    label: &'static [u8],
+}
+
+impl DiscriminantParams {
+    /// Merlin expansion under the label `Classygroup.create_discriminant`, with D ≡ 1 mod 8.
+    pub fn classygroup() -> Self {
+        DiscriminantParams {
+            expansion: Expansion::Merlin,
+            residue: ResidueClass::OneModEight,
+            label: b"Classygroup.create_discriminant",
+        }
+    }
+
+    /// Chia's derivation: SHA-256 expansion without a label, with D ≡ 1 mod 8.
+    pub fn chia() -> Self {
+        DiscriminantParams {
+            expansion: Expansion::Sha256,
+            residue: ResidueClass::OneModEight,
+            label: b"",
+        }
+    }
+
+    pub fn expansion(mut self, expansion: Expansion) -> Self {
+        self.expansion = expansion;
+        self
+    }
+
+    /// Residue class of the discriminant. With `OneModFour`, |D| is 3 or 7 mod 8.
+    pub fn residue(mut self, residue: ResidueClass) -> Self {
+        self.residue = residue;
+        self
+    }
+
+    pub fn label(mut self, label: &'static [u8]) -> Self {
+        self.label = label;
+        self
+    }
+
+    pub fn residue_class(&self) -> ResidueClass {
+        self.residue
+    }
+
+    /// `create_discriminant` under these parameters.
+    pub fn create(&self, seed: &[u8], length: u64) -> Mpz {
+        self.create_with_proof(seed, length).0
+    }
+
+    /// `create_discriminant_with_proof` under these parameters.
+    pub fn create_with_proof(&self, seed: &[u8], length: u64) -> (Mpz, DiscriminantProof) {
+        search(self, seed, length)
+    }
+
+    /// `create_discriminant_parallel` under these parameters.
+    pub fn create_parallel(&self, seed: &[u8], length: u64, threads: usize) -> Mpz {
+        search_parallel(self, seed, length, threads).0
+    }
+
+    /// `verify_discriminant` under these parameters.
+    pub fn verify(&self, seed: &[u8], length: u64, d: &Mpz, proof: &DiscriminantProof) -> bool {
+        verify(self, seed, length, d, proof)
+    }
+}
+
+impl Default for DiscriminantParams {
+    fn default() -> Self {
+        DiscriminantParams::classygroup()
+    }
+}
+
+/// Where `create_discriminant` found its prime, and why it skipped every candidate before it.
+///
+/// The candidates start at a value derived from the seed and step by `M`, in blocks of `2^16`. A
+/// prime factor below `2^16` rules out most of them, and a verifier finds those again by sieving.
+/// Each remaining one is listed here with a base to which it fails the Miller-Rabin test, which
+/// proves it composite.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiscriminantProof {
+    /// Index of the sieve block holding the prime.
+    pub block: u64,
+    /// Position `i` of the prime within its block.
+    pub offset: u32,
+    /// Miller-Rabin witnesses for the candidates that survived the sieve before the prime, in
+    /// order. Almost all are 2.
+    pub witnesses: Vec<u32>,
+}
 
 /// Create a discriminant from a seed (a byte string) and a bit length (a
 /// `u16`).  The discriminant is guaranteed to be a negative prime number that
 /// fits in `length` bits, except with negligible probability (less than
-/// 2^(-100)).  It is also guaranteed to equal 7 modulo 8.
+/// 2^(-100)).  Its absolute value is also guaranteed to equal 7 modulo 8.
 ///
 /// This function uses Shake128 as an extensible output function to expand the seed.  
 /// Therefore, different seeds will result in completely different discriminants with
@@ -25,13 +140,214 @@ use bacteria::Transcript;
 /// This function is guaranteed not to panic for any inputs whatsoever, unless
 /// memory allocation fails and the allocator in use panics in that case.
 pub fn create_discriminant(seed: &[u8], length: u64) -> Mpz {
-    //1. Create a Merlin transcript
-    let mut transcript = Transcript::new(b"Classygroup.create_discriminant");
-    //2. Commit our seed
-    transcript.append_message(b"seed", seed);
-    //3. Commit seed length
-    transcript.append_u64(b"length", length);
+    DiscriminantParams::default().create(seed, length)
+}
+
+/// Like `create_discriminant`, but also returns a proof that lets `verify_discriminant` confirm
+/// the result without repeating the search.
+pub fn create_discriminant_with_proof(seed: &[u8], length: u64) -> (Mpz, DiscriminantProof) {
+    DiscriminantParams::default().create_with_proof(seed, length)
+}
+
+fn search(params: &DiscriminantParams, seed: &[u8], length: u64) -> (Mpz, DiscriminantProof) {
+    let mut n = first_candidate(params, seed, length);
+    let mut witnesses = Vec::new();
+
+    // This generates the smallest prime ≥ n that is of the form n + m*x.
+    let mut block = 0;
+    loop {
+        let sieve = sieve_block(&n);
+
+        for (i, x) in sieve.iter().enumerate() {
+            if !x {
+                //-(n + m*i)
+                let q = u64::from(M) * i as u64;
+                //n = n + q;
+                n.add_ui_mut(q);
+
+                //test if we found our target
+                match composite_witness(&n) {
+                    None => {
+                        //set sign to negative
+                        n.neg_mut();
+                        let proof = DiscriminantProof {
+                            block,
+                            offset: i as u32,
+                            witnesses,
+                        };
+                        return (n, proof);
+                    }
+                    Some(witness) => witnesses.push(witness),
+                }
+
+                //n = n - q;
+                n.sub_ui_mut(q);
+            }
+        }
+
+        // M is set to a number with many prime factors so the results are
+        // more uniform https://eprint.iacr.org/2011/401.pdf
+        //n = n + (u64::from(M) * (1 << 16)) as u64;
+        n.add_ui_mut(u64::from(M) * SIEVE_SIZE as u64);
+        block += 1;
+    }
+}
+
+/// Like `create_discriminant`, but tests the candidates of each sieve block on `threads` worker
+/// threads. The result is the same prime, since a candidate only wins once every candidate before
+/// it is known to be composite. `std::thread::available_parallelism` is a sensible thread count.
+pub fn create_discriminant_parallel(seed: &[u8], length: u64, threads: usize) -> Mpz {
+    DiscriminantParams::default().create_parallel(seed, length, threads)
+}
+
+/// The search of `create_discriminant_with_proof`, spread over `threads` threads.
+fn search_parallel(
+    params: &DiscriminantParams,
+    seed: &[u8],
+    length: u64,
+    threads: usize,
+) -> (Mpz, DiscriminantProof) {
+    let threads = threads.max(1);
+    let mut n = first_candidate(params, seed, length);
+    let mut witnesses = Vec::new();
+
+    let mut block = 0;
+    loop {
+        let sieve = sieve_block(&n);
+        let survivors: Vec<usize> = (0..SIEVE_SIZE).filter(|&i| !sieve[i]).collect();
+        let (first_prime, block_witnesses) = test_survivors(&n, &survivors, threads);
+        witnesses.extend(block_witnesses);
+
+        if let Some(j) = first_prime {
+            let i = survivors[j];
+            n.add_ui_mut(u64::from(M) * i as u64);
+            n.neg_mut();
+            let proof = DiscriminantProof {
+                block,
+                offset: i as u32,
+                witnesses,
+            };
+            return (n, proof);
+        }
+
+        n.add_ui_mut(u64::from(M) * SIEVE_SIZE as u64);
+        block += 1;
+    }
+}
+
+/// Tests the candidates `n + M * i` for `i` in `survivors`. Returns the index into `survivors` of
+/// the first probable prime, if any, and the witnesses of the candidates before it.
+///
+/// Worker `t` takes the indices `t, t + threads, ...`, so all of them work near the front of the
+/// block. A worker stops once it passes the earliest prime found so far. Any index below the
+/// final one is still tested by its worker, since the bound only ever decreases.
+fn test_survivors(n: &Mpz, survivors: &[usize], threads: usize) -> (Option<usize>, Vec<u32>) {
+    let first_prime = AtomicUsize::new(usize::MAX);
+    let mut results: Vec<(usize, u32)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|t| {
+                let first_prime = &first_prime;
+                scope.spawn(move || {
+                    let mut found = Vec::new();
+                    let mut candidate = Mpz::default();
+                    for j in (t..survivors.len()).step_by(threads) {
+                        if j > first_prime.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        candidate.set(n);
+                        candidate.add_ui_mut(u64::from(M) * survivors[j] as u64);
+                        match composite_witness(&candidate) {
+                            Some(witness) => found.push((j, witness)),
+                            None => {
+                                first_prime.fetch_min(j, Ordering::Relaxed);
+                                break;
+                            }
+                        }
+                    }
+                    found
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("discriminant search thread panicked"))
+            .collect()
+    });
+
+    let first_prime = first_prime.into_inner();
+    results.retain(|&(j, _)| j < first_prime);
+    results.sort_unstable();
+    let witnesses = results.into_iter().map(|(_, witness)| witness).collect();
+    let first_prime = if first_prime == usize::MAX {
+        None
+    } else {
+        Some(first_prime)
+    };
+    (first_prime, witnesses)
+}
+
+/// Checks that `d` is `create_discriminant(seed, length)`, using the proof returned with it.
+///
+/// This costs one BPSW test for `d` plus one Miller-Rabin round per witness, against a BPSW test
+/// per witness for the search itself, and a sieve per block. The proof has to supply a witness
+/// for every surviving candidate of every block it skips, so a large `block` fails as soon as the
+/// witnesses run out instead of sieving block after block.
+pub fn verify_discriminant(seed: &[u8], length: u64, d: &Mpz, proof: &DiscriminantProof) -> bool {
+    DiscriminantParams::default().verify(seed, length, d, proof)
+}
+
+fn verify(
+    params: &DiscriminantParams,
+    seed: &[u8],
+    length: u64,
+    d: &Mpz,
+    proof: &DiscriminantProof,
+) -> bool {
+    let offset = proof.offset as usize;
+    if offset >= SIEVE_SIZE {
+        return false;
+    }
+    let mut n = first_candidate(params, seed, length);
+    let mut witnesses = proof.witnesses.iter();
+    let mut candidate = Mpz::default();
+    let mut block = 0;
+    loop {
+        let sieve = sieve_block(&n);
+        let end = if block == proof.block {
+            offset
+        } else {
+            SIEVE_SIZE
+        };
+        for i in (0..end).filter(|&i| !sieve[i]) {
+            candidate.set(&n);
+            candidate.add_ui_mut(u64::from(M) * i as u64);
+            match witnesses.next() {
+                Some(&w) if w >= 2 && candidate.cmp_si(i64::from(w)) > 0 => {
+                    if passes_miller_rabin(&candidate, u64::from(w)) {
+                        return false;
+                    }
+                }
+                _ => return false,
+            }
+        }
+        if block == proof.block {
+            if sieve[offset] || witnesses.next().is_some() {
+                return false;
+            }
+            candidate.set(&n);
+            candidate.add_ui_mut(u64::from(M) * offset as u64);
+            let mut abs_d = Mpz::default();
+            abs_d.neg(d);
+            return candidate == abs_d && is_prob_prime(&candidate);
+        }
+        n.add_ui_mut(u64::from(M) * SIEVE_SIZE as u64);
+        block += 1;
+    }
+}
 
+/// The first candidate for `seed` and `length`: a number of about `length` bits expanded from
+/// the seed, moved into one of the residue classes modulo `M` that avoid its prime factors.
+fn first_candidate(params: &DiscriminantParams, seed: &[u8], length: u64) -> Mpz {
     // The number of “extra” bits (that don’t evenly fit in a byte)
     let extra = (length % 8) as u8;
 
@@ -46,20 +362,29 @@ pub fn create_discriminant(seed: &[u8], length: u64) -> Mpz {
         }
     };
 
-    //println!("random_bytes_len2: {:?}", random_bytes_len);
-
     //get our random bytes sequence derived from seed
-    let mut random_bytes = vec![0u8; random_bytes_len as usize];
-    transcript.challenge_bytes(b"random_bytes", &mut random_bytes);
+    let random_bytes = match params.expansion {
+        Expansion::Merlin => merlin_bytes(params.label, seed, length, random_bytes_len as usize),
+        Expansion::Sha256 => sha256_bytes(params.label, seed, random_bytes_len as usize),
+    };
 
-    // The number of random bytes needed (the number of bytes that hold `length`
-    // bits, plus 2).
+    // The last two bytes pick the residue class.
     let (n_tmp, last_2) = random_bytes.split_at(random_bytes_len as usize - 2);
     let numerator = (usize::from(last_2[0]) << 8) + usize::from(last_2[1]);
 
-    //println!("random_bytes_len: {:?}", n_tmp);
     let mut n: Mpz = Mpz::from_bytes(n_tmp);
-    //println!("random_bytes_len: {:?}", n.bit_length());
+    if params.expansion == Expansion::Sha256 && length > 0 {
+        // Drop the bits beyond `length` and set the top one.
+        if extra != 0 {
+            let bytes = n.clone();
+            n.tdiv_q_2exp(&bytes, u64::from(8 - extra));
+        }
+        if !n.tstbit(length as usize - 1) {
+            let mut top = vec![0u8; n_tmp.len()];
+            top[0] = 1 << ((length - 1) % 8);
+            n.add_mut(&Mpz::from_bytes(&top));
+        }
+    }
 
     // n -= n.clone() % M;
     //let rem = n.clone() % Mpz::from(M as u64);
@@ -67,59 +392,93 @@ pub fn create_discriminant(seed: &[u8], length: u64) -> Mpz {
     rem.modulo(&n, &Mpz::from(M as u64));
     //n = n - rem;
     n.sub_mut(&rem);
-    //println!("n plus: {:?}", RESIDUES[numerator % RESIDUES.len()]);
-    let residue = RESIDUES[numerator % RESIDUES.len()];
+    let residues: &[u32] = match params.residue {
+        ResidueClass::OneModEight => &RESIDUES,
+        ResidueClass::OneModFour => &RESIDUES_3_MOD_4,
+    };
+    let residue = residues[numerator % residues.len()];
     let residue = Mpz::from(residue as u64);
     //n = n + residue;
     n.add_mut(&residue);
 
     debug_assert!(n >= Mpz::zero());
+    n
+}
 
-    // This generates the smallest prime ≥ n that is of the form n + m*x.
-    loop {
-        // Speed up prime-finding by quickly ruling out numbers
-        // that are known to be composite.
-        let mut sieve = ::bit_vec::BitVec::from_elem(1 << 16, false);
-
-        //Optimize for gains
-        for &(p, q) in SIEVE_INFO.iter() {
-            // The reference implementation changes the sign of `n` before taking its
-            // remainder. Instead, we leave `n` as positive, but use ceiling
-            // division instead of floor division.  This is mathematically
-            // equivalent and potentially faster.
-            let mut i: usize = (n.crem_u16(p) as usize * q as usize) % p as usize;
-            while i < sieve.len() {
-                sieve.set(i, true);
-                i += p as usize;
-            }
-        }
+/// `len` bytes from a Merlin transcript labelled `label` that commits to the seed and length.
+fn merlin_bytes(label: &'static [u8], seed: &[u8], length: u64, len: usize) -> Vec<u8> {
+    //1. Create a Merlin transcript
+    let mut transcript = Transcript::new(label);
+    //2. Commit our seed
+    transcript.append_message(b"seed", seed);
+    //3. Commit seed length
+    transcript.append_u64(b"length", length);
 
-        for (i, x) in sieve.iter().enumerate() {
-            let i = i as u32;
+    let mut random_bytes = vec![0u8; len];
+    transcript.challenge_bytes(b"random_bytes", &mut random_bytes);
+    random_bytes
+}
 
-            if !x {
-                //-(n + m*i)
-                let q = u64::from(M) * u64::from(i);
-                //n = n + q;
-                n.add_ui_mut(q);
+/// `len` bytes from SHA-256 of `label || seed || counter`, for counters 0, 1, ... as 2-byte
+/// big-endian integers, with the label length-prefixed and omitted when empty.
+fn sha256_bytes(label: &[u8], seed: &[u8], len: usize) -> Vec<u8> {
+    let mut input = Vec::with_capacity(label.len() + seed.len() + 10);
+    if !label.is_empty() {
+        input.extend_from_slice(&(label.len() as u64).to_le_bytes());
+        input.extend_from_slice(label);
+    }
+    input.extend_from_slice(seed);
+    let prefix_len = input.len();
 
-                //test if we found our target
-                if n.is_prime(2) {
-                    //set sign to negative
-                    n.neg_mut();
-                    return n;
-                }
+    let mut out = Vec::with_capacity(len + 32);
+    let mut counter = 0u16;
+    while out.len() < len {
+        input.truncate(prefix_len);
+        input.extend_from_slice(&counter.to_be_bytes());
+        out.extend_from_slice(&Sha256::digest(&input));
+        counter = counter.wrapping_add(1);
+    }
+    out.truncate(len);
+    out
+}
 
-                //n = n - q;
-                n.sub_ui_mut(q);
-            }
+/// Marks the offsets `i` in `0..2^16` for which `n + M * i` has an odd prime factor between 17
+/// and `2^16`.
+fn sieve_block(n: &Mpz) -> BitVec {
+    // Speed up prime-finding by quickly ruling out numbers
+    // that are known to be composite.
+    let mut sieve = BitVec::from_elem(SIEVE_SIZE, false);
+
+    //Optimize for gains
+    for &(p, q) in SIEVE_INFO.iter() {
+        // The reference implementation changes the sign of `n` before taking its
+        // remainder. Instead, we leave `n` as positive, but use ceiling
+        // division instead of floor division.  This is mathematically
+        // equivalent and potentially faster.
+        let mut i: usize = (n.crem_u16(p) as usize * q as usize) % p as usize;
+        while i < sieve.len() {
+            sieve.set(i, true);
+            i += p as usize;
         }
+    }
+    sieve
+}
 
-        // M is set to a number with many prime factors so the results are
-        // more uniform https://eprint.iacr.org/2011/401.pdf
-        //n = n + (u64::from(M) * (1 << 16)) as u64;
-        n.add_ui_mut((u64::from(M) * (1 << 16)));
+/// `None` if `n` is a probable prime, otherwise a base to which it fails the Miller-Rabin test.
+/// Candidates that survive the sieve have no prime factor below `2^16`, so this agrees with
+/// `is_prob_prime` on them.
+fn composite_witness(n: &Mpz) -> Option<u32> {
+    if !passes_miller_rabin(n, 2) {
+        return Some(2);
+    }
+    if passes_strong_lucas(n) {
+        return None;
     }
+    // A strong pseudoprime to base 2, which most other bases expose.
+    let witness = (3..)
+        .find(|&a| !passes_miller_rabin(n, u64::from(a)))
+        .expect("every odd composite has a Miller-Rabin witness below it");
+    Some(witness)
 }
 
 #[cfg(test)]
@@ -129,6 +488,148 @@ mod test {
     // use crate::biggie::BigNum;
     use std::str::FromStr;
 
+    #[test]
+    fn test_known_discriminants() {
+        let known: [(&[u8], u64, &str); 3] = [
+            (b"\xaa", 512, "-5738485026353662061739407900958511339981517447670618596993567761340274681912048859094110649641278579199662565667997867234524279601602490124599821169942031"),
+            (b"seed", 1024, "-65115322633189288348166486834077537254437071631518547008608579119140745061194289683258245591613124899920149857150451827702391466084291294993936139867673213942820609393467380604492752450062020935972475752783300570565443711045366474473508080530775048466724913960799319107574841768799381311718676587333656638959"),
+            (b"", 64, "-7568054375662201807"),
+        ];
+        for &(seed, length, d) in known.iter() {
+            let d = Mpz::from_str(d).unwrap();
+            let (found, proof) = create_discriminant_with_proof(seed, length);
+            assert_eq!(found, d);
+            assert_eq!(create_discriminant(seed, length), d);
+            assert!(verify_discriminant(seed, length, &d, &proof));
+        }
+    }
+
+    #[test]
+    fn test_chia_discriminants() {
+        // From Chia's reference `create_discriminant`.
+        let chia = DiscriminantParams::chia();
+        assert_eq!(
+            chia.create(b"\xaa", 40),
+            Mpz::from_str("-685537176559").unwrap()
+        );
+        let known: [(DiscriminantParams, &[u8], u64, &str); 4] = [
+            (chia, b"\xaa", 512, "-8359660134568383023885564670366702136187694669176086302246349534295787805650587215790753848727015584770487951477204945010237361460391734983260451049583023"),
+            (chia, b"\xaa", 1025, "-224169434887781928593261263450334841334633672263828370289523487675633650197648547899277054861626953573864830472290658845365917364621623656384281084401606466915008146852477686852356197589184234450117354672148010199899514135573630879964846709364077204811723428279429717207658032818629372533320497003547049413703"),
+            (chia.residue(ResidueClass::OneModFour), b"seed1", 512, "-8342497880508285173767083116236932637735205855988949976224146087216080869332761977024566807448711336098983813044933252609510039179680561133895923166092227"),
+            (chia.label(b"my tag"), b"seed", 512, "-11942567901177194484894384606961438208522238641777315788120137618334557271336630089221737711510403422145727488714089307053578464674813378451230334446906607"),
+        ];
+        for &(params, seed, length, d) in known.iter() {
+            let d = Mpz::from_str(d).unwrap();
+            let (found, proof) = params.create_with_proof(seed, length);
+            assert_eq!(found, d);
+            assert_eq!(found.bit_length(), length as usize);
+            assert!(params.verify(seed, length, &d, &proof));
+            assert!(!DiscriminantParams::default().verify(seed, length, &d, &proof));
+            assert_eq!(params.create_parallel(seed, length, 3), d);
+        }
+        // Only the 1 mod 4 table allows D ≡ 5 mod 8.
+        assert_eq!(known[2].0.residue_class(), ResidueClass::OneModFour);
+        assert_eq!(Mpz::from_str(known[2].3).unwrap().fdiv_ui(8), 5);
+    }
+
+    #[test]
+    fn test_params_builder() {
+        assert_eq!(
+            DiscriminantParams::default(),
+            DiscriminantParams::classygroup()
+        );
+        let custom = DiscriminantParams::chia()
+            .expansion(Expansion::Merlin)
+            .label(b"Classygroup.create_discriminant");
+        assert_eq!(custom, DiscriminantParams::classygroup());
+        assert_eq!(
+            custom.create(b"\xaa", 512),
+            create_discriminant(b"\xaa", 512)
+        );
+        let relabelled = custom.label(b"other");
+        assert_ne!(
+            relabelled.create(b"\xaa", 512),
+            create_discriminant(b"\xaa", 512)
+        );
+    }
+
+    #[test]
+    fn test_parallel_search_matches_sequential() {
+        for &(seed, length) in [(&b"\xaa"[..], 512), (b"seed", 1024), (b"", 64), (b"", 24)].iter() {
+            let sequential = create_discriminant_with_proof(seed, length);
+            for &threads in [0, 1, 3, 8].iter() {
+                assert_eq!(
+                    search_parallel(&DiscriminantParams::default(), seed, length, threads),
+                    sequential
+                );
+            }
+            assert_eq!(create_discriminant_parallel(seed, length, 2), sequential.0);
+        }
+    }
+
+    #[test]
+    fn test_verify_discriminant_rejects_bad_proofs() {
+        let (d, proof) = create_discriminant_with_proof(b"\xaa", 512);
+        assert_eq!(proof.block, 0);
+        assert!(!proof.witnesses.is_empty());
+        assert!(!verify_discriminant(b"\xab", 512, &d, &proof));
+        assert!(!verify_discriminant(b"\xaa", 513, &d, &proof));
+
+        let mut wrong_d = d.clone();
+        wrong_d.neg_mut();
+        assert!(!verify_discriminant(b"\xaa", 512, &wrong_d, &proof));
+
+        let check = |f: &dyn Fn(&mut DiscriminantProof)| {
+            let mut bad = proof.clone();
+            f(&mut bad);
+            verify_discriminant(b"\xaa", 512, &d, &bad)
+        };
+        assert!(check(&|_| {}));
+        assert!(!check(&|p| p.offset += 1));
+        assert!(!check(&|p| p.offset = 1 << 16));
+        assert!(!check(&|p| p.block = 1));
+        assert!(!check(&|p| p.block = u64::MAX));
+        assert!(!check(&|p| {
+            p.witnesses.pop();
+        }));
+        assert!(!check(&|p| p.witnesses.push(2)));
+        // Every number passes to base 1 and fails to base 0.
+        assert!(!check(&|p| p.witnesses[0] = 1));
+    }
+
+    #[test]
+    fn test_verify_discriminant_skips_prime_with_proof() {
+        // Claiming a later prime needs a witness for the real one, which does not exist.
+        let (d, proof) = create_discriminant_with_proof(b"seed", 1024);
+        let mut n = d.clone();
+        n.neg_mut();
+        let mut offset = proof.offset + 1;
+        let d_later = loop {
+            let mut candidate = n.clone();
+            candidate.add_ui_mut(u64::from(M) * u64::from(offset - proof.offset));
+            if is_prob_prime(&candidate) {
+                candidate.neg_mut();
+                break candidate;
+            }
+            offset += 1;
+        };
+        let mut later = proof.clone();
+        later.offset = offset;
+        // Each candidate in between that survives the sieve needs a witness of its own too.
+        for i in proof.offset + 1..offset {
+            let mut candidate = n.clone();
+            candidate.add_ui_mut(u64::from(M) * u64::from(i - proof.offset));
+            if !sieve_block(&candidate)[0] {
+                later.witnesses.push(composite_witness(&candidate).unwrap());
+            }
+        }
+        for &w in [0, 1, 2, 3].iter() {
+            later.witnesses.insert(proof.witnesses.len(), w);
+            assert!(!verify_discriminant(b"seed", 1024, &d_later, &later));
+            later.witnesses.remove(proof.witnesses.len());
+        }
+    }
+
     // #[test]
     // fn check_rem() {
     //     let negh = BigUint::from(100u32);
@@ -0,0 +1,378 @@
+//! Discrete logarithms in toy-size class groups, for tests that play the attacker.
+
+use super::{ClassElem, ClassGroupOf};
+use crate::num::BigNum;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// Starting points `rho` tries before giving up on a target.
+const RHO_ATTEMPTS: u64 = 16;
+
+/// Why a discrete logarithm was not found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiscreteLogError {
+    /// D is zero or positive.
+    NotNegative,
+    /// |D| has more bits than the solver accepts.
+    TooLarge { bits: usize, max_bits: usize },
+    /// The order passed to `rho` does not annihilate the base.
+    WrongOrder,
+    /// The target is not a power of the base.
+    NotInSubgroup,
+    /// The bound on h(D) that `bsgs` searches up to does not fit in a `u64`.
+    BoundTooLarge,
+}
+
+impl fmt::Display for DiscreteLogError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiscreteLogError::NotNegative => write!(f, "discriminant is not negative"),
+            DiscreteLogError::TooLarge { bits, max_bits } => write!(
+                f,
+                "discriminant has {} bits, at most {} are accepted",
+                bits, max_bits
+            ),
+            DiscreteLogError::WrongOrder => write!(f, "the base does not have the given order"),
+            DiscreteLogError::NotInSubgroup => write!(f, "the target is not a power of the base"),
+            DiscreteLogError::BoundTooLarge => write!(f, "the class number bound exceeds u64"),
+        }
+    }
+}
+
+impl std::error::Error for DiscreteLogError {}
+
+/// Generic discrete logarithm algorithms for class groups, refusing discriminants above
+/// `max_bits` bits. Both algorithms take time around sqrt(h(D)), which is about |D|^(1/4), so
+/// the limit is what keeps a call from running for years.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiscreteLog {
+    /// Largest bit length of |D| that is accepted.
+    pub max_bits: usize,
+}
+
+impl DiscreteLog {
+    pub fn new(max_bits: usize) -> Self {
+        DiscreteLog { max_bits }
+    }
+
+    /// The least `n >= 0` with `g^n = x` in the class group of discriminant `disc`, by
+    /// baby-step giant-step.
+    ///
+    /// The search covers exponents up to sqrt(|D|) (ln|D| + 2), which bounds h(D) for
+    /// fundamental D, so `NotInSubgroup` means `x` is not a power of `g`. The baby steps `g^j`
+    /// are kept in a hash map, which takes memory in the square root of that bound.
+    pub fn bsgs<B: BigNum>(
+        &self,
+        disc: &B,
+        g: &ClassElem<B>,
+        x: &ClassElem<B>,
+    ) -> Result<u64, DiscreteLogError> {
+        self.check(disc)?;
+        let bound = class_number_bound(disc).ok_or(DiscreteLogError::BoundTooLarge)?;
+        let m = (bound as f64).sqrt().ceil() as u64;
+
+        ClassGroupOf::<B>::with_discriminant(disc, || {
+            let id = ClassGroupOf::<B>::id();
+            let mut baby = HashMap::new();
+            let mut step = id.clone();
+            for j in 0..m {
+                // Stop early if g has order j, then x must be one of the baby steps.
+                if j > 0 && step == id {
+                    return baby.get(x).copied().ok_or(DiscreteLogError::NotInSubgroup);
+                }
+                baby.entry(step.clone()).or_insert(j);
+                step = ClassGroupOf::op(&step, g);
+            }
+
+            let giant = ClassGroupOf::pow(&ClassGroupOf::inv(g), &B::from(m));
+            let mut y = x.clone();
+            for i in 0..=m {
+                if let Some(&j) = baby.get(&y) {
+                    return Ok(i * m + j);
+                }
+                y = ClassGroupOf::op(&y, &giant);
+            }
+            Err(DiscreteLogError::NotInSubgroup)
+        })
+    }
+
+    /// The `n` in `[0, order)` with `g^n = x` in the class group of discriminant `disc`, where
+    /// `order` is the order of `g`, by Pollard's rho.
+    ///
+    /// The walk multiplies by `x`, squares or multiplies by `g` depending on the hash of the
+    /// current element, and Floyd's cycle finding detects the collision, so memory use is
+    /// constant. A collision gives a linear congruence mod `order` whose solutions are checked
+    /// one by one. If none works, the walk restarts elsewhere, and after a few starting points
+    /// `x` is taken not to be a power of `g`.
+    pub fn rho<B: BigNum>(
+        &self,
+        disc: &B,
+        g: &ClassElem<B>,
+        x: &ClassElem<B>,
+        order: u64,
+    ) -> Result<u64, DiscreteLogError> {
+        self.check(disc)?;
+
+        ClassGroupOf::<B>::with_discriminant(disc, || {
+            let id = ClassGroupOf::<B>::id();
+            if order == 0 || ClassGroupOf::pow(g, &B::from(order)) != id {
+                return Err(DiscreteLogError::WrongOrder);
+            }
+            if order == 1 {
+                return if *x == id {
+                    Ok(0)
+                } else {
+                    Err(DiscreteLogError::NotInSubgroup)
+                };
+            }
+
+            for attempt in 0..RHO_ATTEMPTS {
+                // Each point of the walk is g^a x^b.
+                let start = {
+                    let a = (attempt + 1) % order;
+                    let ga = ClassGroupOf::pow(g, &B::from(a));
+                    (ClassGroupOf::op(&ga, x), a, 1)
+                };
+                let mut tortoise = start.clone();
+                let mut hare = start;
+                loop {
+                    rho_step(&mut tortoise, g, x, order);
+                    rho_step(&mut hare, g, x, order);
+                    rho_step(&mut hare, g, x, order);
+                    if tortoise.0 == hare.0 {
+                        break;
+                    }
+                }
+
+                // g^a1 x^b1 = g^a2 x^b2, so (b1 - b2) n = a2 - a1 mod order.
+                let r = (tortoise.2 + order - hare.2) % order;
+                let s = (hare.1 + order - tortoise.1) % order;
+                let d = gcd(r, order);
+                if s % d != 0 || d > 1 << 16 {
+                    continue;
+                }
+                let modulus = order / d;
+                let n0 = mul_mod(s / d, inverse_mod(r / d, modulus), modulus);
+                for k in 0..d {
+                    let n = n0 + k * modulus;
+                    if ClassGroupOf::pow(g, &B::from(n)) == *x {
+                        return Ok(n);
+                    }
+                }
+            }
+            Err(DiscreteLogError::NotInSubgroup)
+        })
+    }
+
+    fn check<B: BigNum>(&self, disc: &B) -> Result<(), DiscreteLogError> {
+        if disc.sgn() >= 0 {
+            return Err(DiscreteLogError::NotNegative);
+        }
+        let bits = disc.bit_length();
+        if bits > self.max_bits {
+            return Err(DiscreteLogError::TooLarge {
+                bits,
+                max_bits: self.max_bits,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Default for DiscreteLog {
+    /// Accepts discriminants of up to 64 bits, where h(D) is around 2^32.
+    fn default() -> Self {
+        DiscreteLog::new(64)
+    }
+}
+
+// An upper bound on h(D) for fundamental D: h(D) = w sqrt(|D|) L(1, χ) / 2π with w <= 6, and
+// L(1, χ) < ln|D| + 2. `None` if it does not fit in a `u64`, which happens from about 114 bits.
+fn class_number_bound<B: BigNum>(disc: &B) -> Option<u64> {
+    let mut root = B::default();
+    root.abs(disc);
+    root.root_mut(2);
+    if root.bit_length() > 62 {
+        return None;
+    }
+    let ln = disc.bit_length() as f64 * std::f64::consts::LN_2;
+    let bound = ((root.get_si() + 1) as f64 * (ln + 2.0)).ceil();
+    if bound >= u64::MAX as f64 {
+        return None;
+    }
+    Some(bound as u64)
+}
+
+fn rho_step<B: BigNum>(
+    point: &mut (ClassElem<B>, u64, u64),
+    g: &ClassElem<B>,
+    x: &ClassElem<B>,
+    order: u64,
+) {
+    let mut hasher = DefaultHasher::new();
+    point.0.hash(&mut hasher);
+    match hasher.finish() % 3 {
+        0 => {
+            point.0 = ClassGroupOf::op(&point.0, x);
+            point.2 = (point.2 + 1) % order;
+        }
+        1 => {
+            ClassGroupOf::square(&mut point.0);
+            point.1 = mul_mod(point.1, 2, order);
+            point.2 = mul_mod(point.2, 2, order);
+        }
+        _ => {
+            point.0 = ClassGroupOf::op(&point.0, g);
+            point.1 = (point.1 + 1) % order;
+        }
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    ((a as u128 * b as u128) % m as u128) as u64
+}
+
+// The inverse of `a` mod `m`, for `a` coprime to `m`.
+fn inverse_mod(a: u64, m: u64) -> u64 {
+    let (mut r0, mut r1) = (m as i128, (a % m) as i128);
+    let (mut t0, mut t1) = (0_i128, 1_i128);
+    while r1 != 0 {
+        let q = r0 / r1;
+        let r = r0 - q * r1;
+        r0 = r1;
+        r1 = r;
+        let t = t0 - q * t1;
+        t0 = t1;
+        t1 = t;
+    }
+    t0.rem_euclid(m as i128) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::group::discriminant::DISCRIMINANT2048_DECIMAL;
+    use crate::group::{ClassGroup, ClassGroupStructure};
+    use crate::num::DefaultBigNum;
+    use std::str::FromStr;
+
+    fn disc(d: i64) -> DefaultBigNum {
+        let mut disc = DefaultBigNum::default();
+        disc.set_si(d);
+        disc
+    }
+
+    #[test]
+    fn test_recovers_exponents() {
+        // h(-999983) = 1171 is prime, so any element but the identity generates the group.
+        let d = disc(-999_983);
+        let group = ClassGroupStructure::compute(&d).unwrap();
+        let g = &group.generators()[0];
+        let order = group.element_order(g);
+        assert_eq!(order, 1171);
+
+        let solver = DiscreteLog::default();
+        for &k in [0, 1, 2, order / 3, order - 1].iter() {
+            let x =
+                ClassGroup::with_discriminant(&d, || ClassGroup::pow(g, &DefaultBigNum::from(k)));
+            assert_eq!(solver.bsgs(&d, g, &x), Ok(k));
+            assert_eq!(solver.rho(&d, g, &x, order), Ok(k));
+        }
+        // Exponents past the order are reduced.
+        let x = ClassGroup::with_discriminant(&d, || {
+            ClassGroup::pow(g, &DefaultBigNum::from(order + 5))
+        });
+        assert_eq!(solver.bsgs(&d, g, &x), Ok(5));
+        assert_eq!(solver.rho(&d, g, &x, order), Ok(5));
+    }
+
+    #[test]
+    fn test_detects_targets_outside_subgroup() {
+        // The group of -3299 is C3 x C9, so no element generates it.
+        let d = disc(-3299);
+        let group = ClassGroupStructure::compute(&d).unwrap();
+        let g = group
+            .generators()
+            .iter()
+            .find(|g| group.element_order(g) == 9)
+            .unwrap();
+
+        let solver = DiscreteLog::default();
+        let mut outside = 0;
+        for x in group.generators() {
+            match solver.bsgs(&d, g, x) {
+                Ok(n) => {
+                    let power = ClassGroup::with_discriminant(&d, || {
+                        ClassGroup::pow(g, &DefaultBigNum::from(n))
+                    });
+                    assert_eq!(power, *x);
+                    assert_eq!(solver.rho(&d, g, x, 9), Ok(n));
+                }
+                Err(e) => {
+                    assert_eq!(e, DiscreteLogError::NotInSubgroup);
+                    assert_eq!(
+                        solver.rho(&d, g, x, 9),
+                        Err(DiscreteLogError::NotInSubgroup)
+                    );
+                    outside += 1;
+                }
+            }
+        }
+        assert!(outside > 0);
+    }
+
+    #[test]
+    fn test_rejects_large_discriminants() {
+        let g = ClassGroup::unknown_order_elem();
+        let big = DefaultBigNum::from_str(DISCRIMINANT2048_DECIMAL).unwrap();
+        assert_eq!(
+            DiscreteLog::default().bsgs(&big, &g, &g),
+            Err(DiscreteLogError::TooLarge {
+                bits: 2048,
+                max_bits: 64
+            })
+        );
+        assert_eq!(
+            DiscreteLog::new(4).rho(&disc(-23), &g, &g, 3),
+            Err(DiscreteLogError::TooLarge {
+                bits: 5,
+                max_bits: 4
+            })
+        );
+        assert_eq!(
+            DiscreteLog::default().bsgs(&disc(23), &g, &g),
+            Err(DiscreteLogError::NotNegative)
+        );
+
+        // Past about 114 bits the search bound no longer fits in a u64.
+        assert_eq!(
+            DiscreteLog::new(4096).bsgs(&big, &g, &g),
+            Err(DiscreteLogError::BoundTooLarge)
+        );
+        assert_eq!(class_number_bound(&big), None);
+        assert_eq!(class_number_bound(&disc(-999_983)), Some(15_863));
+    }
+
+    #[test]
+    fn test_rho_checks_order() {
+        let d = disc(-23);
+        let group = ClassGroupStructure::compute(&d).unwrap();
+        let g = &group.generators()[0];
+        assert_eq!(
+            DiscreteLog::default().rho(&d, g, g, 2),
+            Err(DiscreteLogError::WrongOrder)
+        );
+        assert_eq!(DiscreteLog::default().rho(&d, g, g, 3), Ok(1));
+    }
+}
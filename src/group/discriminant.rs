@@ -1,12 +1,15 @@
 //! The fixed class group discriminant.
+#[cfg(feature = "gmp")]
 use crate::num::Mpz;
+#[cfg(feature = "gmp")]
 use std::str::FromStr;
 
 // 2048-bit prime, negated, congruent to 3 mod 4.  Generated using OpenSSL.
 // According to "A Survey of IQ Cryptography" (Buchmann & Hamdy) Table 1, IQ-MPQS for computing
 // discrete logarithms in class groups with a 2048-bit discriminant is comparable in complexity to
 // GNFS for factoring a 4096-bit integer.
-const DISCRIMINANT2048_DECIMAL: &str =
+// `SecurityLevel` has fixed discriminants for other sizes.
+pub(crate) const DISCRIMINANT2048_DECIMAL: &str =
   "-30616069034807523947093657516320815215492876376165067902716988657802400037331914448218251590830\
   1102189519215849430413184776658192481976276720778009261808832630304841711366872161223643645001916\
   6969493423497224870506311710491233557329479816457723381368788734079933165653042145718668727765268\
@@ -15,6 +18,7 @@ const DISCRIMINANT2048_DECIMAL: &str =
   9453371727344087286361426404588335160385998280988603297435639020911295652025967761702701701471162\
   3966286152805654229445219531956098223";
 
+#[cfg(feature = "gmp")]
 lazy_static! {
     pub static ref CLASS_GROUP_DISCRIMINANT: Mpz = Mpz::from_str(DISCRIMINANT2048_DECIMAL).unwrap();
 }
@@ -1,55 +1,279 @@
 //! Defines the ClassElem struct and associated traits.
-use crate::num::Mpz;
+use super::classy::with_class_ctx;
+use super::{discriminant_fingerprint, ClassCtx, ClassGroupOf, GroupId};
+use crate::num::{BigNum, DefaultBigNum};
+use std::any::{Any, TypeId};
+use std::borrow::{Borrow, Cow};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fmt;
 use std::hash::{Hash, Hasher};
+use std::ops::{Mul, MulAssign, Neg};
 
 #[allow(clippy::stutter)]
 #[derive(Debug)]
-pub struct ClassElem {
-    pub a: Mpz,
-    pub b: Mpz,
-    pub c: Mpz,
+pub struct ClassElem<B: BigNum = DefaultBigNum> {
+    pub a: B,
+    pub b: B,
+    pub c: B,
+    // The group the element was produced in, `None` for forms built by hand.
+    pub(crate) group: Option<GroupId>,
 }
 
-impl ClassElem {
-    fn to_bytes(&self) -> Vec<u8> {
-        let mut buf = Vec::new();
-        // buf.extend_from_slice();
-        // buf.extend_from_slice();
+/// Flag byte of an encoding that carries a discriminant fingerprint.
+const WITH_FINGERPRINT: u8 = 1;
+
+/// How many contexts for groups other than its own a thread keeps for the operators.
+const MAX_OTHER_CONTEXTS: usize = 8;
+
+thread_local! {
+    // Contexts the operators use for elements outside the thread's group, by backend and group.
+    static OTHER_CONTEXTS: RefCell<HashMap<(TypeId, GroupId), Box<dyn Any>>> = Default::default();
+}
+
+impl<B: BigNum> ClassElem<B> {
+    /// The form (a, b, c), as built by hand. It carries no group, and the group operations take
+    /// it to belong to that of its discriminant.
+    pub fn new(a: B, b: B, c: B) -> Self {
+        ClassElem {
+            a,
+            b,
+            c,
+            group: None,
+        }
+    }
+
+    /// The group the element was produced in, or `None` for a form built by hand.
+    pub fn group(&self) -> Option<GroupId> {
+        self.group
+    }
+
+    /// The discriminant b^2 - 4ac of the form.
+    pub fn discriminant(&self) -> B {
+        let (mut d, mut ac) = (B::default(), B::default());
+        d.mul(&self.b, &self.b);
+        ac.mul(&self.a, &self.c);
+        ac.mul_ui_mut(4);
+        d.sub_mut(&ac);
+        d
+    }
+
+    /// Whether -a < b <= a.
+    pub fn is_normalized(&self) -> bool {
+        let mut neg_a = B::default();
+        neg_a.neg(&self.a);
+        neg_a < self.b && self.b <= self.a
+    }
+
+    /// Whether the form is normalized and a <= c, with b >= 0 if a = c. Every class holds
+    /// exactly one reduced form, which is how the group operations return their results.
+    pub fn is_reduced(&self) -> bool {
+        self.is_normalized()
+            && match self.a.cmp(&self.c) {
+                Ordering::Less => true,
+                Ordering::Equal => !self.b.is_neg(),
+                Ordering::Greater => false,
+            }
+    }
+
+    /// Whether the form is in the class of the principal form, the identity of its group.
+    pub fn is_identity(&self) -> bool {
+        self.reduced().a.is_one()
+    }
+
+    /// Whether the form's class is its own inverse. A reduced form is ambiguous exactly when
+    /// b = 0, a = b or a = c.
+    pub fn is_ambiguous(&self) -> bool {
+        let x = self.reduced();
+        x.b.is_zero() || x.a == x.b || x.a == x.c
+    }
+
+    /// Reduces a positive definite form in place, to the reduced form of its class. Reduction
+    /// does not depend on the group, so this works on elements of any.
+    pub fn reduce(&mut self) {
+        ClassGroupOf::reduce_mut(self);
+    }
+
+    /// Normalizes a positive definite form in place, which keeps it in its class.
+    pub fn normalize(&mut self) {
+        ClassGroupOf::normalize_mut(self);
+    }
+
+    /// Replaces the form by the reduced form of its inverse (a, -b, c).
+    pub fn invert(&mut self) {
+        self.b.neg_mut();
+        if !self.is_reduced() {
+            self.reduce();
+        }
+    }
+
+    /// `self^n`, computed in the group of `self`, see `ClassGroupOf::pow`. A form built by hand
+    /// is raised in the calling thread's group.
+    pub fn pow<E: BigNum>(&self, n: &E) -> Self {
+        in_group_of(self, None, |ctx, x| ctx.pow(x, n))
+    }
+
+    fn reduced(&self) -> Cow<'_, Self> {
+        if self.is_reduced() {
+            return Cow::Borrowed(self);
+        }
+        let mut x = self.clone();
+        x.reduce();
+        Cow::Owned(x)
+    }
+
+    /// Encodes the form as a flag byte of 0 followed by a, b and c, each as a sign byte (1 for
+    /// negative), its length as a 4-byte big-endian integer and its big-endian magnitude.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![0];
+        self.write_coefficients(&mut buf);
         buf
     }
+
+    /// Like `to_bytes`, but with a flag byte of 1 followed by the `discriminant_fingerprint` of
+    /// the form's discriminant, so that decoding it in another group fails.
+    pub fn to_bytes_with_fingerprint(&self) -> Vec<u8> {
+        let mut buf = vec![WITH_FINGERPRINT];
+        buf.extend_from_slice(&discriminant_fingerprint(&self.discriminant()));
+        self.write_coefficients(&mut buf);
+        buf
+    }
+
+    fn write_coefficients(&self, buf: &mut Vec<u8>) {
+        for x in [&self.a, &self.b, &self.c].iter() {
+            let magnitude = x.to_be_bytes();
+            buf.push(x.is_neg() as u8);
+            buf.extend_from_slice(&(magnitude.len() as u32).to_be_bytes());
+            buf.extend_from_slice(&magnitude);
+        }
+    }
 }
 
-impl Default for ClassElem {
-    fn default() -> Self {
-        ClassElem {
-            a: Mpz::default(),
-            b: Mpz::default(),
-            c: Mpz::default(),
+/// Why bytes did not decode to an element of the current group.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The bytes are not an encoding produced by `ClassElem::to_bytes` or
+    /// `ClassElem::to_bytes_with_fingerprint`.
+    Malformed,
+    /// The embedded fingerprint is not that of the group's discriminant.
+    WrongFingerprint,
+    /// The form's discriminant is not the group's.
+    WrongDiscriminant,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::Malformed => write!(f, "malformed element encoding"),
+            DecodeError::WrongFingerprint => {
+                write!(f, "element encoding is fingerprinted for another group")
+            }
+            DecodeError::WrongDiscriminant => {
+                write!(f, "element does not have the group's discriminant")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl<B: BigNum> ClassGroupOf<B> {
+    /// Decodes the output of `ClassElem::to_bytes` or `ClassElem::to_bytes_with_fingerprint` as
+    /// an element of the calling thread's group, checking the fingerprint if there is one and
+    /// the discriminant in any case. The form is not checked to be reduced; see
+    /// `validate_element`.
+    pub fn elem_from_bytes(bytes: &[u8]) -> Result<ClassElem<B>, DecodeError> {
+        let disc = Self::current_discriminant();
+        let (&flag, mut rest) = bytes.split_first().ok_or(DecodeError::Malformed)?;
+        let fingerprint = match flag {
+            0 => None,
+            WITH_FINGERPRINT if rest.len() >= 8 => {
+                let (fingerprint, tail) = rest.split_at(8);
+                rest = tail;
+                Some(fingerprint)
+            }
+            _ => return Err(DecodeError::Malformed),
+        };
+        if let Some(fingerprint) = fingerprint {
+            if fingerprint != discriminant_fingerprint(&disc) {
+                return Err(DecodeError::WrongFingerprint);
+            }
+        }
+
+        let mut coefficients = Vec::with_capacity(3);
+        for _ in 0..3 {
+            if rest.len() < 5 || rest[0] > 1 {
+                return Err(DecodeError::Malformed);
+            }
+            let len = u32::from_be_bytes(rest[1..5].try_into().unwrap()) as usize;
+            let magnitude = rest.get(5..5 + len).ok_or(DecodeError::Malformed)?;
+            // Exactly one encoding per integer: no leading zeros and no negative zero.
+            if magnitude.first() == Some(&0) || (rest[0] == 1 && len == 0) {
+                return Err(DecodeError::Malformed);
+            }
+            let mut x = B::from_be_bytes(magnitude);
+            if rest[0] == 1 {
+                x.neg_mut();
+            }
+            coefficients.push(x);
+            rest = &rest[5 + len..];
+        }
+        if !rest.is_empty() {
+            return Err(DecodeError::Malformed);
+        }
+
+        let c = coefficients.pop().unwrap();
+        let b = coefficients.pop().unwrap();
+        let a = coefficients.pop().unwrap();
+        let mut elem = ClassElem::new(a, b, c);
+        if elem.discriminant() != disc {
+            return Err(DecodeError::WrongDiscriminant);
         }
+        elem.group = Some(Self::current_group());
+        Ok(elem)
+    }
+}
+
+impl<B: BigNum> Default for ClassElem<B> {
+    fn default() -> Self {
+        ClassElem::new(B::default(), B::default(), B::default())
     }
 }
 
-impl Clone for ClassElem {
+impl<B: BigNum> Clone for ClassElem<B> {
     fn clone(&self) -> Self {
         let mut ret = ClassElem::default();
         ret.a = self.a.clone();
         ret.b = self.b.clone();
         ret.c = self.c.clone();
+        ret.group = self.group;
         ret
     }
 }
 
-impl PartialEq for ClassElem {
-    fn eq(&self, other: &ClassElem) -> bool {
-        // ClassElems only ever exist in reduced form, unless manually
-        // instantiated with ClassElem {...}
-        self.a == other.a && self.b == other.b && self.c == other.c
+impl<B: BigNum> PartialEq for ClassElem<B> {
+    fn eq(&self, other: &ClassElem<B>) -> bool {
+        // ClassElems only ever exist in reduced form, unless built by hand. Elements of
+        // different groups are never equal. A form built by hand counts as an element of the
+        // group of its discriminant, so that equality stays transitive.
+        if self.a != other.a || self.b != other.b || self.c != other.c {
+            return false;
+        }
+        match (self.group, other.group) {
+            (Some(x), Some(y)) => x == y,
+            (None, None) => true,
+            // Equal coefficients, so the hand-built form has the discriminant of `self`.
+            (Some(group), None) | (None, Some(group)) => GroupId::of(&self.discriminant()) == group,
+        }
     }
 }
 
-impl Hash for ClassElem {
+impl<B: BigNum> Hash for ClassElem<B> {
     // Assumes `ClassElem` is reduced and normalized, which will be the case unless a struct is
-    // instantiated manually in this module.
+    // instantiated manually in this module. The group is left out, as equal elements have equal
+    // coefficients whatever their groups.
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.a.hash(state);
         self.b.hash(state);
@@ -57,6 +281,338 @@ impl Hash for ClassElem {
     }
 }
 
-impl Eq for ClassElem {}
-unsafe impl Send for ClassElem {}
-unsafe impl Sync for ClassElem {}
+impl<B: BigNum> Eq for ClassElem<B> {}
+
+// Runs `logic` on a context for the group of `x`, or of `y` if `x` was built by hand. That is
+// the calling thread's context if it has the group or both forms were built by hand, and
+// otherwise one kept for the group by `OTHER_CONTEXTS`, created from the tagged operand's
+// discriminant the first time the group is seen. `x` is handed back to `logic`, which may then
+// take it mutably.
+fn in_group_of<B: BigNum, X: Borrow<ClassElem<B>>, R>(
+    x: X,
+    y: Option<&ClassElem<B>>,
+    logic: impl FnOnce(&mut ClassCtx<B>, X) -> R,
+) -> R {
+    let discriminant = |x: &X| match y {
+        Some(y) if x.borrow().group.is_none() => y.discriminant(),
+        _ => x.borrow().discriminant(),
+    };
+    let group = match x.borrow().group.or_else(|| y.and_then(|y| y.group)) {
+        Some(group) if group != ClassGroupOf::<B>::current_group() => group,
+        _ => return with_class_ctx(|ctx: &mut ClassCtx<B>| logic(ctx, x)),
+    };
+    OTHER_CONTEXTS.with(|refcell| {
+        let mut contexts = refcell.borrow_mut();
+        let key = (TypeId::of::<B>(), group);
+        if !contexts.contains_key(&key) {
+            if contexts.len() >= MAX_OTHER_CONTEXTS {
+                let evicted = *contexts.keys().next().unwrap();
+                contexts.remove(&evicted);
+            }
+            let mut ctx = ClassCtx::<B>::from_discriminant(&discriminant(&x));
+            // A tag that does not match the coefficients; the operation reports the mismatch.
+            if ctx.group() != group {
+                return logic(&mut ctx, x);
+            }
+            contexts.insert(key, Box::new(ctx));
+        }
+        let ctx = contexts
+            .get_mut(&key)
+            .and_then(|ctx| ctx.downcast_mut::<ClassCtx<B>>())
+            .expect("class context registered under the wrong backend");
+        logic(ctx, x)
+    })
+}
+
+// The operators compose in the group of their operands, whatever the calling thread's group.
+// Like `ClassCtx::op`, they panic if the operands belong to different groups.
+
+impl<'b, B: BigNum> Mul<&'b ClassElem<B>> for &ClassElem<B> {
+    type Output = ClassElem<B>;
+
+    fn mul(self, rhs: &'b ClassElem<B>) -> ClassElem<B> {
+        in_group_of(self, Some(rhs), |ctx, x| ctx.op(x, rhs))
+    }
+}
+
+impl<'b, B: BigNum> Mul<&'b ClassElem<B>> for ClassElem<B> {
+    type Output = ClassElem<B>;
+
+    fn mul(mut self, rhs: &'b ClassElem<B>) -> ClassElem<B> {
+        self *= rhs;
+        self
+    }
+}
+
+impl<B: BigNum> Mul for ClassElem<B> {
+    type Output = ClassElem<B>;
+
+    fn mul(self, rhs: ClassElem<B>) -> ClassElem<B> {
+        self * &rhs
+    }
+}
+
+impl<'b, B: BigNum> MulAssign<&'b ClassElem<B>> for ClassElem<B> {
+    fn mul_assign(&mut self, rhs: &'b ClassElem<B>) {
+        in_group_of(self, Some(rhs), |ctx, x| ctx.op_assign(x, rhs));
+    }
+}
+
+impl<B: BigNum> MulAssign for ClassElem<B> {
+    fn mul_assign(&mut self, rhs: ClassElem<B>) {
+        *self *= &rhs;
+    }
+}
+
+impl<B: BigNum> Neg for &ClassElem<B> {
+    type Output = ClassElem<B>;
+
+    fn neg(self) -> ClassElem<B> {
+        -self.clone()
+    }
+}
+
+impl<B: BigNum> Neg for ClassElem<B> {
+    type Output = ClassElem<B>;
+
+    fn neg(mut self) -> ClassElem<B> {
+        self.invert();
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::group::{ClassGroup, ClassGroupStructure};
+
+    fn form(a: i64, b: i64, c: i64) -> ClassElem {
+        let mut x = ClassElem::<DefaultBigNum>::default();
+        x.a.set_si(a);
+        x.b.set_si(b);
+        x.c.set_si(c);
+        x
+    }
+
+    #[test]
+    fn test_inspection() {
+        let d = form(2, 1, 3).discriminant();
+        assert_eq!(d.get_si(), -23);
+        assert_eq!(form(3, -1, 2).discriminant(), d);
+
+        assert!(form(2, 1, 3).is_reduced());
+        assert!(form(2, -1, 3).is_reduced());
+        // c < a.
+        assert!(form(3, -1, 2).is_normalized());
+        assert!(!form(3, -1, 2).is_reduced());
+        // b = -a is not normalized, and a = c needs b >= 0.
+        assert!(!form(2, -2, 11).is_normalized());
+        assert!(!form(5, -4, 5).is_reduced());
+        assert!(form(5, 4, 5).is_reduced());
+
+        assert!(form(1, 1, 6).is_identity());
+        assert!(!form(2, 1, 3).is_identity());
+        // (2, 5, 4) has discriminant -7, whose only class is the principal one.
+        assert!(form(2, 5, 4).is_identity());
+        assert!(!form(2, 5, 4).is_reduced());
+
+        // The reduced forms of discriminant -84 are all ambiguous; (2, 1, 3) has order 3.
+        for &(a, b, c) in [(1, 0, 21), (2, 2, 11), (3, 0, 7), (5, 4, 5)].iter() {
+            assert!(form(a, b, c).is_ambiguous());
+        }
+        assert!(form(2, -2, 11).is_ambiguous());
+        assert!(!form(2, 1, 3).is_ambiguous());
+        assert!(form(1, 1, 6).is_ambiguous());
+    }
+
+    #[test]
+    fn test_reduce_and_normalize() {
+        let mut x = form(2, 5, 4);
+        x.normalize();
+        assert_eq!(x, form(2, 1, 1));
+        assert!(x.is_normalized());
+        assert!(!x.is_reduced());
+
+        x.reduce();
+        assert_eq!(x, form(1, 1, 2));
+        assert!(x.is_reduced());
+
+        let mut y = form(2, -2, 11);
+        y.reduce();
+        assert_eq!(y, form(2, 2, 11));
+        assert_eq!(y.discriminant().get_si(), -84);
+    }
+
+    #[test]
+    fn test_operators() {
+        let g = ClassGroup::unknown_order_elem();
+        let mut g2 = g.clone();
+        ClassGroup::square(&mut g2);
+
+        assert_eq!(&g * &g, g2);
+        assert_eq!(g.clone() * &g, g2);
+        assert_eq!(g.clone() * g.clone(), g2);
+        let mut x = g.clone();
+        x *= &g;
+        assert_eq!(x, g2);
+        x *= g.clone();
+        assert_eq!(x, g.pow(&DefaultBigNum::from(3)));
+
+        let mut minus_one = DefaultBigNum::default();
+        minus_one.set_si(-1);
+        assert_eq!(-&g, g.pow(&minus_one));
+        assert!((&g * &-&g).is_identity());
+        assert_eq!(-(-g.clone()), g);
+
+        // Negating b alone would give (2, -2, 11), which is not normalized.
+        let mut z = form(2, 2, 11);
+        ClassGroup::with_discriminant(&z.discriminant(), || {
+            assert_eq!(-&z, z);
+            z *= form(2, 2, 11);
+            assert!(z.is_identity());
+        });
+    }
+
+    #[test]
+    fn test_operators_in_other_groups() {
+        let d = "-999983".parse::<DefaultBigNum>().unwrap();
+        let mut ctx = ClassCtx::from_discriminant(&d);
+        let h = ctx.unknown_order_elem();
+        let h2 = ctx.op(&h, &h);
+        let h3 = ctx.op(&h2, &h);
+
+        // No `with_discriminant`: the operands' group is used, not the thread's.
+        assert_eq!(&h * &h, h2);
+        let mut x = h.clone();
+        x *= &h2;
+        assert_eq!(x, h3);
+        assert_eq!(x.group(), Some(ctx.group()));
+        assert_eq!(h.pow(&DefaultBigNum::from(3)), h3);
+        assert!((&h * &-&h).is_identity());
+        let order = ClassGroupStructure::compute(&d).unwrap().element_order(&h);
+        assert!(h.pow(&DefaultBigNum::from(order)).is_identity());
+
+        // A form built by hand takes the group of the other operand.
+        let raw = ClassElem::new(h.a.clone(), h.b.clone(), h.c.clone());
+        assert_eq!(raw.group(), None);
+        assert_eq!(&raw * &h, h2);
+        assert_eq!(&h * &raw, h2);
+
+        // The group's context is kept for later operators, for a bounded number of groups.
+        let key = (TypeId::of::<DefaultBigNum>(), ctx.group());
+        assert!(OTHER_CONTEXTS.with(|contexts| contexts.borrow().contains_key(&key)));
+        for &d in [23, 31, 47, 71, 79, 103, 127, 151, 167, 191].iter() {
+            let mut d = DefaultBigNum::from(d);
+            d.neg_mut();
+            let g = ClassCtx::from_discriminant(&d).unknown_order_elem();
+            assert_eq!(&g * &g, ClassCtx::from_discriminant(&d).op(&g, &g));
+        }
+        let cached = OTHER_CONTEXTS.with(|contexts| contexts.borrow().len());
+        assert_eq!(cached, MAX_OTHER_CONTEXTS);
+    }
+
+    #[test]
+    #[should_panic(expected = "used in group")]
+    fn test_operators_across_groups() {
+        let d = "-999983".parse::<DefaultBigNum>().unwrap();
+        let h = ClassCtx::from_discriminant(&d).unknown_order_elem();
+        let _ = &ClassGroup::unknown_order_elem() * &h;
+    }
+
+    #[test]
+    fn test_group_tags() {
+        let g = ClassGroup::unknown_order_elem();
+        assert_eq!(g.group(), Some(ClassGroup::current_group()));
+        assert_eq!(form(2, 1, 3).group(), None);
+
+        let d = form(2, 1, 3).discriminant();
+        let h =
+            ClassGroup::with_discriminant(&d, || ClassGroup::op(&form(2, 1, 3), &ClassGroup::id()));
+        assert_eq!(h.group(), Some(GroupId::of(&d)));
+        assert_ne!(h.group(), g.group());
+        // A form built by hand compares as an element of the group of its discriminant.
+        assert_eq!(h, form(2, 1, 3));
+    }
+
+    #[test]
+    fn test_eq_is_transitive() {
+        // The same coefficients tagged with the group of their discriminant, left untagged, and
+        // tagged with another group, as composing a foreign hand-built form there gives.
+        let d = form(2, 1, 3).discriminant();
+        let mut x = form(2, 1, 3);
+        x.group = Some(GroupId::of(&d));
+        let y = form(2, 1, 3);
+        let mut z = form(2, 1, 3);
+        z.group = Some(ClassGroup::current_group());
+
+        assert_eq!(x, y);
+        assert_ne!(y, z);
+        assert_ne!(x, z);
+        assert_eq!(y, y.clone());
+        assert_eq!(z, z.clone());
+        assert_ne!(form(2, -1, 3), x);
+
+        // A form whose discriminant no group has used is equal to no tagged element.
+        let w = form(3, 1, 1_000_000);
+        let mut tagged = w.clone();
+        tagged.group = z.group;
+        assert_ne!(w, tagged);
+        assert_eq!(w, form(3, 1, 1_000_000));
+    }
+
+    #[test]
+    fn test_serialization() {
+        let g = ClassGroup::unknown_order_elem();
+        for bytes in [g.to_bytes(), g.to_bytes_with_fingerprint()].iter() {
+            let x = ClassGroup::elem_from_bytes(bytes).unwrap();
+            assert_eq!(x, g);
+            assert_eq!(x.group(), g.group());
+        }
+
+        // The discriminant catches a plain encoding from another group, the fingerprint catches
+        // a fingerprinted one before the form is even parsed.
+        let h = form(2, -1, 3);
+        assert_eq!(
+            ClassGroup::elem_from_bytes(&h.to_bytes()),
+            Err(DecodeError::WrongDiscriminant)
+        );
+        assert_eq!(
+            ClassGroup::elem_from_bytes(&h.to_bytes_with_fingerprint()),
+            Err(DecodeError::WrongFingerprint)
+        );
+        ClassGroup::with_discriminant(&h.discriminant(), || {
+            assert_eq!(
+                ClassGroup::elem_from_bytes(&h.to_bytes_with_fingerprint()),
+                Ok(h.clone())
+            );
+        });
+
+        let bytes = h.to_bytes();
+        assert_eq!(
+            bytes,
+            [0, 0, 0, 0, 0, 1, 2, 1, 0, 0, 0, 1, 1, 0, 0, 0, 0, 1, 3]
+        );
+        let d = h.discriminant();
+        ClassGroup::with_discriminant(&d, || {
+            let decode = |bytes: &[u8]| ClassGroup::elem_from_bytes(bytes);
+            assert_eq!(decode(&[]), Err(DecodeError::Malformed));
+            assert_eq!(decode(&[2]), Err(DecodeError::Malformed));
+            assert_eq!(
+                decode(&bytes[..bytes.len() - 1]),
+                Err(DecodeError::Malformed)
+            );
+            assert_eq!(
+                decode(&[&bytes[..], &[0]].concat()),
+                Err(DecodeError::Malformed)
+            );
+            // 2 with a leading zero byte.
+            let mut padded = vec![0, 0, 0, 0, 0, 2, 0, 2];
+            padded.extend_from_slice(&bytes[7..]);
+            assert_eq!(decode(&padded), Err(DecodeError::Malformed));
+            // A sign byte other than 0 or 1.
+            let mut bad_sign = bytes.clone();
+            bad_sign[7] = 2;
+            assert_eq!(decode(&bad_sign), Err(DecodeError::Malformed));
+        });
+    }
+}
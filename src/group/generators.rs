@@ -0,0 +1,178 @@
+//! Generators of a class group derived from a seed, for constructions that need many elements
+//! with no known relations between them.
+
+use super::classy::with_class_ctx;
+use super::{ClassCtx, ClassElem, ClassGroupOf, GroupId};
+use crate::hash::{hash_to_prime_with, HashToPrimeParams};
+use crate::num::BigNum;
+use rug::integer::Order;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Domain tag for the primes drawn by `generators`.
+const GENERATORS_DOMAIN: &[u8] = b"Classygroup.generators";
+
+/// Sequences kept by the cache of `generators`. Past this, an arbitrary one is dropped for each
+/// new backend, group and seed.
+const MAX_CACHED_SEQUENCES: usize = 64;
+
+// Generators derived so far, by backend, group and seed. Each entry is a `Vec<ClassElem<B>>`.
+type Cache = HashMap<(TypeId, GroupId, Vec<u8>), Box<dyn Any + Send>>;
+
+lazy_static! {
+    static ref CACHE: Mutex<Cache> = Mutex::new(HashMap::new());
+}
+
+impl<B: BigNum> ClassCtx<B> {
+    /// The first `n` generators derived from `seed` in this group, see
+    /// `ClassGroupOf::generators`.
+    pub fn generators(&mut self, seed: &[u8], n: usize) -> Vec<ClassElem<B>> {
+        let key = (TypeId::of::<B>(), self.group, seed.to_vec());
+        let mut generators = {
+            let cache = CACHE
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            match cache
+                .get(&key)
+                .and_then(|x| x.downcast_ref::<Vec<ClassElem<B>>>())
+            {
+                Some(cached) if cached.len() >= n => return cached[..n].to_vec(),
+                Some(cached) => cached.clone(),
+                None => Vec::new(),
+            }
+        };
+
+        // Derived without holding the lock; another thread deriving the same generators gets
+        // the same values.
+        for i in generators.len()..n {
+            generators.push(self.derive_generator(seed, i as u64));
+        }
+
+        let mut cache = CACHE
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let longer = cache
+            .get(&key)
+            .and_then(|x| x.downcast_ref::<Vec<ClassElem<B>>>())
+            .map_or(true, |cached| cached.len() < n);
+        if longer {
+            if cache.len() >= MAX_CACHED_SEQUENCES && !cache.contains_key(&key) {
+                let evicted = cache.keys().next().cloned();
+                if let Some(evicted) = evicted {
+                    cache.remove(&evicted);
+                }
+            }
+            cache.insert(key, Box::new(generators.clone()));
+        }
+        generators
+    }
+
+    fn derive_generator(&mut self, seed: &[u8], i: u64) -> ClassElem<B> {
+        let params = HashToPrimeParams::new(256, GENERATORS_DOMAIN);
+        let mut input = seed.to_vec();
+        input.extend_from_slice(&i.to_le_bytes());
+        input.extend_from_slice(&[0; 8]);
+        let counter = seed.len() + 8;
+        for k in 0_u64.. {
+            input[counter..].copy_from_slice(&k.to_le_bytes());
+            let prime = hash_to_prime_with(&params, &input);
+            let p = B::from_be_bytes(&prime.to_digits::<u8>(Order::Msf));
+            if let Ok(generator) = self.prime_form(&p) {
+                return generator;
+            }
+        }
+        unreachable!("ran out of counter values")
+    }
+}
+
+impl<B: BigNum> ClassGroupOf<B> {
+    /// The first `n` of a sequence of elements of the calling thread's group derived from
+    /// `seed`, for Pedersen-style and vector commitments that need generators with no known
+    /// relations between them.
+    ///
+    /// Generator `i` is `prime_form(p)` for the first prime
+    /// `p = hash_to_prime_with(params, seed || i || k)`, over `k = 0, 1, ...`, that splits. Here
+    /// `params` asks for 256-bit primes under the domain tag `Classygroup.generators`, and `i`
+    /// and `k` are little-endian `u64`s. The sequence depends only on the seed and the
+    /// discriminant, so every backend and platform derives the same one, and `generators(seed,
+    /// n)` is a prefix of `generators(seed, m)` for `n < m`.
+    ///
+    /// Each generator costs a hash to a prime and a modular square root. They are cached per
+    /// group and seed, so repeated calls only derive the ones not seen before. The cache is
+    /// process-wide and holds up to 64 sequences, each as long as the longest prefix requested;
+    /// beyond that an arbitrary sequence is dropped and is derived again if asked for.
+    pub fn generators(seed: &[u8], n: usize) -> Vec<ClassElem<B>> {
+        with_class_ctx(|ctx: &mut ClassCtx<B>| ctx.generators(seed, n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::group::{ClassGroup, ClassGroupStructure};
+    use crate::num::DefaultBigNum;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_generators() {
+        let gens = ClassGroup::generators(b"pedersen", 8);
+        assert_eq!(gens.len(), 8);
+        assert_eq!(ClassGroup::generators(b"pedersen", 3), gens[..3].to_vec());
+        assert_eq!(ClassGroup::generators(b"pedersen", 10)[..8], gens[..]);
+        assert!(ClassGroup::generators(b"pedersen", 0).is_empty());
+
+        for (i, g) in gens.iter().enumerate() {
+            assert!(ClassGroup::validate(&g.a, &g.b, &g.c));
+            assert!(g.is_reduced());
+            assert!(!ClassGroup::has_small_order(g, 100));
+            assert!(gens[..i].iter().all(|h| h != g && *h != -g));
+        }
+        assert_ne!(ClassGroup::generators(b"vector", 1)[0], gens[0]);
+
+        // Generator 0 from its documented derivation.
+        let params = HashToPrimeParams::new(256, GENERATORS_DOMAIN);
+        let mut input = b"pedersen".to_vec();
+        input.extend_from_slice(&[0; 16]);
+        let g0 = (0_u8..)
+            .find_map(|k| {
+                input[16] = k;
+                let p = hash_to_prime_with(&params, &input);
+                let p = DefaultBigNum::from_str(&p.to_string()).unwrap();
+                ClassGroup::prime_form(&p).ok()
+            })
+            .unwrap();
+        assert_eq!(g0, gens[0]);
+    }
+
+    #[test]
+    fn test_generators_across_backends_and_groups() {
+        let gens = ClassGroup::generators(b"seed", 4);
+        let other = ClassGroupOf::<rug::Integer>::generators(b"seed", 4);
+        for (x, y) in gens.iter().zip(other.iter()) {
+            assert_eq!(x.to_bytes(), y.to_bytes());
+        }
+
+        // Cached per group: the same seed gives other generators in another group.
+        let d = DefaultBigNum::from_str("-999983").unwrap();
+        let structure = ClassGroupStructure::compute(&d).unwrap();
+        let small = ClassCtx::from_discriminant(&d).generators(b"seed", 4);
+        for g in small.iter() {
+            assert_eq!(g.discriminant(), d);
+            assert!(structure.verify_order(g));
+        }
+        assert_eq!(ClassGroup::generators(b"seed", 4), gens);
+    }
+
+    #[test]
+    fn test_generator_cache_is_bounded() {
+        let d = DefaultBigNum::from_str("-999983").unwrap();
+        let mut ctx = ClassCtx::from_discriminant(&d);
+        let first = ctx.generators(b"bounded 0", 2);
+        for i in 1..2 * MAX_CACHED_SEQUENCES {
+            ctx.generators(format!("bounded {}", i).as_bytes(), 1);
+        }
+        assert!(CACHE.lock().unwrap().len() <= MAX_CACHED_SEQUENCES);
+        assert_eq!(ctx.generators(b"bounded 0", 2), first);
+    }
+}
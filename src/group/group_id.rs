@@ -0,0 +1,72 @@
+//! Identifiers that tie class group elements to their discriminant.
+
+use crate::num::BigNum;
+use mohan::hash::blake256;
+use std::fmt;
+
+/// An identifier of a discriminant, which elements carry to name their group.
+///
+/// It is the `discriminant_fingerprint` of the discriminant, so it needs no shared state and is
+/// the same under every backend and in every process. Distinct negative discriminants share an
+/// identifier with probability about 2^-64; the fingerprint leaves out the sign.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GroupId(u64);
+
+impl GroupId {
+    /// The identifier of the group of discriminant `disc`.
+    pub fn of<B: BigNum>(disc: &B) -> GroupId {
+        GroupId(u64::from_be_bytes(discriminant_fingerprint(disc)))
+    }
+}
+
+impl fmt::Display for GroupId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:016x}", self.0)
+    }
+}
+
+/// An element was used in a group it does not belong to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GroupMismatch {
+    /// The group of the operation.
+    pub expected: GroupId,
+    /// The group of the element.
+    pub found: GroupId,
+}
+
+impl fmt::Display for GroupMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "element of group {} used in group {}",
+            self.found, self.expected
+        )
+    }
+}
+
+impl std::error::Error for GroupMismatch {}
+
+/// The first 8 bytes of BLAKE2b-256 over the big-endian bytes of `|disc|`. It identifies a
+/// discriminant across processes and platforms, for serialized elements to name their group.
+pub fn discriminant_fingerprint<B: BigNum>(disc: &B) -> [u8; 8] {
+    let digest = blake256(&disc.to_be_bytes()).to_bytes();
+    let mut fingerprint = [0; 8];
+    fingerprint.copy_from_slice(&digest[..8]);
+    fingerprint
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::num::DefaultBigNum;
+
+    #[test]
+    fn test_ids() {
+        let d1 = DefaultBigNum::from(23);
+        let d2 = DefaultBigNum::from(47);
+        assert_eq!(GroupId::of(&d1), GroupId::of(&d1.clone()));
+        assert_ne!(GroupId::of(&d1), GroupId::of(&d2));
+        assert_ne!(discriminant_fingerprint(&d1), discriminant_fingerprint(&d2));
+        assert_eq!(GroupId::of(&d1).to_string().len(), 17);
+    }
+}
@@ -1,33 +1,33 @@
 //! Reusable memory context for solving linear congruences.
 use crate::mut_tuple_elems;
-use crate::num::Mpz;
+use crate::num::BigNum;
 
-pub struct LinCongruenceCtx {
-    pub inner: (Mpz, Mpz, Mpz, Mpz, Mpz),
+pub struct LinCongruenceCtx<B: BigNum> {
+    pub inner: (B, B, B, B, B),
 }
 
-impl Default for LinCongruenceCtx {
+impl<B: BigNum> Default for LinCongruenceCtx<B> {
     fn default() -> Self {
         Self {
             inner: (
-                Mpz::default(),
-                Mpz::default(),
-                Mpz::default(),
-                Mpz::default(),
-                Mpz::default(),
+                B::default(),
+                B::default(),
+                B::default(),
+                B::default(),
+                B::default(),
             ),
         }
     }
 }
 
-impl LinCongruenceCtx {
+impl<B: BigNum> LinCongruenceCtx<B> {
     pub fn solve_linear_congruence(
         &mut self,
-        mu: &mut Mpz,
-        v: &mut Mpz,
-        a: &Mpz,
-        b: &Mpz,
-        m: &Mpz,
+        mu: &mut B,
+        v: &mut B,
+        a: &B,
+        b: &B,
+        m: &B,
     ) -> Option<()> {
         let (g, d, e, q, r) = mut_tuple_elems!(self, 0, 1, 2, 3, 4);
 
@@ -50,14 +50,15 @@ impl LinCongruenceCtx {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::num::DefaultBigNum;
 
     #[test]
     fn test_linear_congruence_solver() {
         fn test_congruence_problem_w_solution(x: i64, y: i64, z: i64, mu_i64: u64, v_i64: u64) {
-            let mut ctx: LinCongruenceCtx = LinCongruenceCtx::default();
-            let (mut a, mut b, mut c) = (Mpz::default(), Mpz::default(), Mpz::default());
-            let (mut mu, mut v) = (Mpz::default(), Mpz::default());
-            let (mut mu_truth, mut v_truth) = (Mpz::default(), Mpz::default());
+            let mut ctx: LinCongruenceCtx<DefaultBigNum> = LinCongruenceCtx::default();
+            let (mut a, mut b, mut c) = (DefaultBigNum::default(), DefaultBigNum::default(), DefaultBigNum::default());
+            let (mut mu, mut v) = (DefaultBigNum::default(), DefaultBigNum::default());
+            let (mut mu_truth, mut v_truth) = (DefaultBigNum::default(), DefaultBigNum::default());
 
             a.set_si(x);
             b.set_si(y);
@@ -80,9 +81,9 @@ mod tests {
     #[test]
     fn test_linear_congruence_solver_no_solution() {
         fn test_congruence_problem_no_solution(x: i64, y: i64, z: i64) {
-            let mut ctx: LinCongruenceCtx = LinCongruenceCtx::default();
-            let (mut a, mut b, mut c) = (Mpz::default(), Mpz::default(), Mpz::default());
-            let (mut mu, mut v) = (Mpz::default(), Mpz::default());
+            let mut ctx: LinCongruenceCtx<DefaultBigNum> = LinCongruenceCtx::default();
+            let (mut a, mut b, mut c) = (DefaultBigNum::default(), DefaultBigNum::default(), DefaultBigNum::default());
+            let (mut mu, mut v) = (DefaultBigNum::default(), DefaultBigNum::default());
 
             a.set_si(x);
             b.set_si(y);
@@ -3,27 +3,61 @@
 //! structures, or standalone if you have a custom application.
 //!
 
-use rug::Integer;
-use std::fmt::Debug;
-use std::hash::Hash;
-use std::marker::Sized;
+mod group_id;
+pub use group_id::{discriminant_fingerprint, GroupId, GroupMismatch};
 
 mod elem;
-pub use elem::ClassElem;
+pub use elem::{ClassElem, DecodeError};
 
 mod class_ctx;
-use class_ctx::ClassCtx;
+pub use class_ctx::ClassCtx;
 
 mod lin_congruence_ctx;
 
+#[cfg(feature = "gmp")]
 mod create_discriminant;
-pub use create_discriminant::create_discriminant;
+#[cfg(feature = "gmp")]
+pub use create_discriminant::{
+    create_discriminant, create_discriminant_parallel, create_discriminant_with_proof,
+    verify_discriminant, DiscriminantParams, DiscriminantProof, Expansion,
+};
+
+#[cfg(feature = "gmp")]
+mod validate_discriminant;
+#[cfg(feature = "gmp")]
+pub use validate_discriminant::{
+    validate_discriminant, DiscriminantError, DiscriminantPolicy, ResidueClass,
+};
+
+#[cfg(feature = "gmp")]
+mod security_level;
+#[cfg(feature = "gmp")]
+pub use security_level::{create_discriminant_for, SecurityLevel};
 
 mod discriminant;
+#[cfg(feature = "gmp")]
 pub use discriminant::CLASS_GROUP_DISCRIMINANT;
 
 mod classy;
-pub use classy::ClassGroup;
+pub use classy::{ClassGroup, ClassGroupOf, MAX_SMALL_ORDER_BOUND};
+
+mod class_number;
+pub use class_number::{ClassGroupStructure, ClassNumberError, MAX_STRUCTURE_BITS};
+
+mod discrete_log;
+pub use discrete_log::{DiscreteLog, DiscreteLogError};
+
+mod validate_element;
+pub use validate_element::ElementError;
+
+mod prime_form;
+pub use prime_form::PrimeFormError;
+
+#[cfg(feature = "gmp")]
+mod generators;
+
+mod non_maximal;
+pub use non_maximal::{NonMaximalOrder, OrderError};
 
 // pub fn multi_exp<G: Group>(alphas: &[G::Elem], x: &[Integer]) -> G::Elem {
 //     if alphas.len() == 1 {
@@ -0,0 +1,336 @@
+//! Class groups of non-maximal imaginary quadratic orders, of discriminant `Δ_q = q^2 Δ_K` for a
+//! fundamental discriminant `Δ_K` and a conductor `q`, and the maps between `Cl(Δ_q)` and
+//! `Cl(Δ_K)` that trapdoor constructions are built on.
+//!
+//! The reduction and composition of `ClassGroupOf` work unchanged for `Δ_q` as long as the forms
+//! are primitive, which every form produced by these maps and the group operations is.
+//!
+//! Reference: Hühnlein, Jacobson, Paulus and Takagi, "A cryptosystem based on non-maximal
+//! imaginary quadratic orders with fast decryption", EUROCRYPT 1998.
+
+use super::{ClassElem, GroupId};
+use crate::num::BigNum;
+use std::fmt;
+
+/// Largest coordinate tried when looking for a form of norm prime to the conductor.
+const MAX_REPRESENTATIVE_SEARCH: i64 = 16;
+
+/// Why an order could not be built or an element could not be mapped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderError {
+    /// `Δ_K` is zero or positive.
+    NotNegative,
+    /// `Δ_K` is not 0 or 1 mod 4.
+    NotDiscriminant,
+    /// The conductor is less than 1.
+    BadConductor,
+    /// The form's discriminant is not the one the map starts from.
+    WrongDiscriminant,
+    /// No form of small coordinates in the class has a norm prime to the conductor.
+    NotPrimeToConductor,
+}
+
+impl fmt::Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OrderError::NotNegative => write!(f, "discriminant is not negative"),
+            OrderError::NotDiscriminant => write!(f, "discriminant is not 0 or 1 mod 4"),
+            OrderError::BadConductor => write!(f, "conductor is less than 1"),
+            OrderError::WrongDiscriminant => {
+                write!(f, "element does not have the map's discriminant")
+            }
+            OrderError::NotPrimeToConductor => {
+                write!(f, "no representative of norm prime to the conductor found")
+            }
+        }
+    }
+}
+
+impl std::error::Error for OrderError {}
+
+/// The order of conductor `q` in the imaginary quadratic field of discriminant `Δ_K`.
+///
+/// `φ_q`, `project`, is the surjective homomorphism `Cl(Δ_q) -> Cl(Δ_K)` sending the class of an
+/// ideal `a` prime to `q` to that of `a O_K`. Its kernel has `q ∏ (1 - (Δ_K / p) / p)`
+/// elements over the primes `p | q`, divided by 3 or 2 for `Δ_K = -3, -4`. `lift` sends a class
+/// of `Cl(Δ_K)` to one of its preimages, so `project(lift(x)) = x`, but is not a homomorphism.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NonMaximalOrder<B: BigNum> {
+    fundamental: B,
+    conductor: B,
+    discriminant: B,
+}
+
+impl<B: BigNum> NonMaximalOrder<B> {
+    /// The order of conductor `conductor` in the field of discriminant `fundamental`. That
+    /// discriminant should be fundamental, which is not checked as it needs `|Δ_K|` factored.
+    pub fn new(fundamental: &B, conductor: &B) -> Result<Self, OrderError> {
+        if fundamental.sgn() >= 0 {
+            return Err(OrderError::NotNegative);
+        }
+        if fundamental.fdiv_ui(4) > 1 {
+            return Err(OrderError::NotDiscriminant);
+        }
+        if conductor.cmp_si(1) < 0 {
+            return Err(OrderError::BadConductor);
+        }
+        let mut discriminant = B::default();
+        discriminant.mul(conductor, conductor);
+        discriminant.mul_mut(fundamental);
+        Ok(NonMaximalOrder {
+            fundamental: fundamental.clone(),
+            conductor: conductor.clone(),
+            discriminant,
+        })
+    }
+
+    /// `Δ_K`, the discriminant of the maximal order.
+    pub fn fundamental_discriminant(&self) -> &B {
+        &self.fundamental
+    }
+
+    /// The conductor `q`.
+    pub fn conductor(&self) -> &B {
+        &self.conductor
+    }
+
+    /// `Δ_q = q^2 Δ_K`, the discriminant of the order.
+    pub fn discriminant(&self) -> &B {
+        &self.discriminant
+    }
+
+    /// `φ_q^-1`: the reduced form of `Cl(Δ_q)` of the ideal `a O_q`, where `a` is an ideal prime to
+    /// `q` in the class of `x`, an element of `Cl(Δ_K)`. For `x = (a, b, c)` with `a` prime to `q`
+    /// this is the form `(a, bq mod 2a, c')`.
+    pub fn lift(&self, x: &ClassElem<B>) -> Result<ClassElem<B>, OrderError> {
+        let x = self.prime_to_conductor(x, &self.fundamental)?;
+        let (mut b, mut two_a) = (B::default(), B::default());
+        b.mul(&x.b, &self.conductor);
+        two_a.mul_ui(&x.a, 2);
+        b.modulo_mut(&two_a);
+        Ok(reduced_form(x.a, b, &self.discriminant))
+    }
+
+    /// `φ_q`: the reduced form of `Cl(Δ_K)` in the class of `a O_K`, where `a` is an ideal prime to
+    /// `q` in the class of `x`, an element of `Cl(Δ_q)`. For `x = (a, b, c)` with `a` prime to `q`
+    /// and `μ q + λ a = 1` this is the form `(a, b μ + a λ (Δ_K mod 2) mod 2a, c')`.
+    pub fn project(&self, x: &ClassElem<B>) -> Result<ClassElem<B>, OrderError> {
+        let x = self.prime_to_conductor(x, &self.discriminant)?;
+        let (mut g, mut mu, mut lambda) = (B::default(), B::default(), B::default());
+        g.gcdext(&mut mu, &mut lambda, &self.conductor, &x.a);
+        debug_assert!(g.is_one());
+
+        let (mut b, mut two_a) = (B::default(), B::default());
+        b.mul(&x.b, &mu);
+        if self.fundamental.odd() {
+            lambda.mul_mut(&x.a);
+            b.add_mut(&lambda);
+        }
+        two_a.mul_ui(&x.a, 2);
+        b.modulo_mut(&two_a);
+        Ok(reduced_form(x.a, b, &self.fundamental))
+    }
+
+    // A form in the class of `x` whose first coefficient is prime to the conductor: `x` itself,
+    // or `x(αX + βY, γX + δY)` for the first coprime `(α, γ)` that represents such a number.
+    fn prime_to_conductor(&self, x: &ClassElem<B>, disc: &B) -> Result<ClassElem<B>, OrderError> {
+        if x.discriminant() != *disc {
+            return Err(OrderError::WrongDiscriminant);
+        }
+        let mut g = B::default();
+        g.gcd(&x.a, &self.conductor);
+        if g.is_one() {
+            return Ok(x.clone());
+        }
+
+        let (mut n, mut t) = (B::default(), B::default());
+        for size in 1..=MAX_REPRESENTATIVE_SEARCH {
+            for (alpha, gamma) in (-size..=size).flat_map(|i| vec![(size, i), (i, size)]) {
+                // With s α + t γ = 1, δ = s and β = -t give α δ - β γ = 1.
+                let (one, delta, minus_beta) = egcd(alpha, gamma);
+                if one != 1 {
+                    continue;
+                }
+                let beta = -minus_beta;
+                eval(&mut n, &mut t, x, alpha, gamma);
+                g.gcd(&n, &self.conductor);
+                if !g.is_one() {
+                    continue;
+                }
+
+                // b' = 2 a α β + b (α δ + β γ) + 2 c γ δ.
+                let mut b = B::default();
+                b.mul_si(&x.a, 2 * alpha * beta);
+                t.mul_si(&x.b, alpha * delta + beta * gamma);
+                b.add_mut(&t);
+                t.mul_si(&x.c, 2 * gamma * delta);
+                b.add_mut(&t);
+                return Ok(form(n, b, disc));
+            }
+        }
+        Err(OrderError::NotPrimeToConductor)
+    }
+}
+
+// The form `(a, b, (b^2 - disc) / 4a)`.
+fn form<B: BigNum>(a: B, b: B, disc: &B) -> ClassElem<B> {
+    let mut c = B::default();
+    c.mul(&b, &b);
+    c.sub_mut(disc);
+    c.divexact_mut(&a);
+    c.fdiv_q_ui_mut(4);
+    ClassElem::new(a, b, c)
+}
+
+// `form(a, b, disc)` reduced and tagged with the group of `disc`.
+fn reduced_form<B: BigNum>(a: B, b: B, disc: &B) -> ClassElem<B> {
+    let mut x = form(a, b, disc);
+    x.reduce();
+    x.group = Some(GroupId::of(disc));
+    x
+}
+
+// Sets `n = a α^2 + b α γ + c γ^2`, the number `x` represents at `(α, γ)`.
+fn eval<B: BigNum>(n: &mut B, t: &mut B, x: &ClassElem<B>, alpha: i64, gamma: i64) {
+    n.mul_si(&x.a, alpha * alpha);
+    t.mul_si(&x.b, alpha * gamma);
+    n.add_mut(t);
+    t.mul_si(&x.c, gamma * gamma);
+    n.add_mut(t);
+}
+
+// `(g, s, t)` with `g = gcd(x, y) = s x + t y`.
+fn egcd(x: i64, y: i64) -> (i64, i64, i64) {
+    let (mut r0, mut r1, mut s0, mut s1, mut t0, mut t1) = (x, y, 1, 0, 0, 1);
+    while r1 != 0 {
+        let q = r0.div_euclid(r1);
+        (r0, r1) = (r1, r0 - q * r1);
+        (s0, s1) = (s1, s0 - q * s1);
+        (t0, t1) = (t1, t0 - q * t1);
+    }
+    if r0 < 0 {
+        (-r0, -s0, -t0)
+    } else {
+        (r0, s0, t0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::group::ClassGroup;
+    use crate::num::DefaultBigNum;
+
+    fn big(x: i64) -> DefaultBigNum {
+        let mut r = DefaultBigNum::default();
+        r.set_si(x);
+        r
+    }
+
+    // Every reduced primitive form of discriminant `d`, by brute force.
+    fn reduced_forms(d: i64) -> Vec<ClassElem> {
+        let mut forms = Vec::new();
+        let mut a = 1;
+        while 3 * a * a <= -d {
+            for b in -a + 1..=a {
+                if (b * b - d) % (4 * a) != 0 {
+                    continue;
+                }
+                let c = (b * b - d) / (4 * a);
+                let x = ClassElem::new(big(a), big(b), big(c));
+                let mut g = big(a);
+                g.gcd_mut(&big(b));
+                g.gcd_mut(&big(c));
+                if x.is_reduced() && g.is_one() {
+                    forms.push(x);
+                }
+            }
+            a += 1;
+        }
+        forms
+    }
+
+    #[test]
+    fn test_projection_is_homomorphism() {
+        // (-23 / 5) = -1, so h(-575) = 3 * 5 * (1 + 1/5) = 18 and the kernel has 6 elements.
+        let order = NonMaximalOrder::new(&big(-23), &big(5)).unwrap();
+        assert_eq!(order.discriminant(), &big(-575));
+        let forms = reduced_forms(-575);
+        assert_eq!(forms.len(), 18);
+
+        let id_k = ClassGroup::with_discriminant(&big(-23), ClassGroup::id);
+        let projected: Vec<_> = forms.iter().map(|x| order.project(x).unwrap()).collect();
+        assert_eq!(projected.iter().filter(|y| **y == id_k).count(), 6);
+        for (x, px) in forms.iter().zip(projected.iter()) {
+            for (y, py) in forms.iter().zip(projected.iter()) {
+                let xy =
+                    ClassGroup::with_discriminant(order.discriminant(), || ClassGroup::op(x, y));
+                let pxpy = ClassGroup::with_discriminant(&big(-23), || ClassGroup::op(px, py));
+                assert_eq!(order.project(&xy).unwrap(), pxpy);
+            }
+        }
+
+        for z in reduced_forms(-23) {
+            let lifted = order.lift(&z).unwrap();
+            assert_eq!(lifted.discriminant(), big(-575));
+            assert_eq!(lifted.group(), Some(GroupId::of(&big(-575))));
+            assert_eq!(order.project(&lifted).unwrap(), z);
+        }
+    }
+
+    #[test]
+    fn test_kernel_of_conductor_dividing_discriminant() {
+        // With q | Δ_K the kernel is the subgroup of order q generated by (q^2, q, c), which
+        // needs another representative to project as q divides its norm.
+        let order = NonMaximalOrder::new(&big(-15), &big(5)).unwrap();
+        let f = ClassElem::new(big(25), big(5), big(4));
+        assert_eq!(f.discriminant(), big(-375));
+        let id_k = ClassGroup::with_discriminant(&big(-15), ClassGroup::id);
+        ClassGroup::with_discriminant(order.discriminant(), || {
+            let mut power = ClassGroup::id();
+            for k in 1..=5 {
+                power = ClassGroup::op(&power, &f);
+                assert_eq!(power.is_identity(), k == 5);
+                assert_eq!(order.project(&power).unwrap(), id_k);
+            }
+        });
+        assert_eq!(
+            order.project(&ClassElem::new(big(2), big(1), big(2))),
+            Err(OrderError::WrongDiscriminant)
+        );
+    }
+
+    #[test]
+    fn test_large_order() {
+        // A 2048-bit Δ_K and a prime conductor, with elements of Cl(Δ_q) built in Cl(Δ_q).
+        let fundamental = ClassGroup::current_discriminant();
+        let order = NonMaximalOrder::new(&fundamental, &big(1_000_003)).unwrap();
+        let (x, y, xy) = ClassGroup::with_discriminant(order.discriminant(), || {
+            let forms = ClassGroup::small_prime_forms(2);
+            let (x, y) = (forms[0].clone(), forms[1].clone());
+            let xy = ClassGroup::op(&ClassGroup::pow(&x, &big(40_000)), &y);
+            (x, y, xy)
+        });
+        let (px, py) = (order.project(&x).unwrap(), order.project(&y).unwrap());
+        let pxy = ClassGroup::op(&ClassGroup::pow(&px, &big(40_000)), &py);
+        assert_eq!(order.project(&xy).unwrap(), pxy);
+        assert_eq!(order.project(&order.lift(&pxy).unwrap()).unwrap(), pxy);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            NonMaximalOrder::new(&big(23), &big(5)),
+            Err(OrderError::NotNegative)
+        );
+        assert_eq!(
+            NonMaximalOrder::new(&big(-21), &big(5)),
+            Err(OrderError::NotDiscriminant)
+        );
+        assert_eq!(
+            NonMaximalOrder::new(&big(-23), &big(0)),
+            Err(OrderError::BadConductor)
+        );
+        assert_eq!(egcd(12, -18), (6, 2, 1));
+    }
+}
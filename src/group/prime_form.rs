@@ -0,0 +1,164 @@
+//! Prime forms, the forms whose first coefficient is a prime `p`. Their classes generate the
+//! class group, and the relations between them are what class group computations look for.
+
+use super::class_number::primes_up_to;
+use super::classy::with_class_ctx;
+use super::{ClassCtx, ClassElem, ClassGroupOf};
+use crate::num::BigNum;
+use std::fmt;
+
+/// Why there is no prime form of norm `p` to return.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrimeFormError {
+    /// `(D / p) = -1`, so no form of discriminant `D` represents `p`.
+    Inert,
+    /// `(D / p) = 0`, so `p` divides `D` and its prime form is ambiguous, of order at most 2.
+    Ramified,
+    /// `p` is not a prime.
+    NotPrime,
+}
+
+impl fmt::Display for PrimeFormError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PrimeFormError::Inert => write!(f, "prime is inert"),
+            PrimeFormError::Ramified => write!(f, "prime is ramified"),
+            PrimeFormError::NotPrime => write!(f, "norm is not a prime"),
+        }
+    }
+}
+
+impl std::error::Error for PrimeFormError {}
+
+impl<B: BigNum> ClassCtx<B> {
+    /// The reduced form in the class of the prime form `(p, b, (b^2 - D) / 4p)`, for a prime `p`
+    /// that splits, that is `(D / p) = 1`. `p` is checked with `BigNum::is_probable_prime`.
+    ///
+    /// `b` is the square root of `D` modulo `4p` with `0 < b < p`, or `b = 1` for `p = 2`. Its
+    /// inverse `(p, -b, c)` is the other form of norm `p`.
+    pub fn prime_form(&mut self, p: &B) -> Result<ClassElem<B>, PrimeFormError> {
+        if !p.is_probable_prime() {
+            return Err(PrimeFormError::NotPrime);
+        }
+        match self.D.kronecker(p) {
+            0 => return Err(PrimeFormError::Ramified),
+            -1 => return Err(PrimeFormError::Inert),
+            _ => {}
+        }
+
+        // A prime with (D / p) = 1 always has the root, so a pseudoprime is all that can fail.
+        let mut b = B::default();
+        if !b.sqrt_mod_4p(&self.D, p) {
+            return Err(PrimeFormError::NotPrime);
+        }
+
+        let mut c = B::default();
+        c.mul(&b, &b);
+        c.sub_mut(&self.D);
+        c.divexact_mut(p);
+        c.fdiv_q_ui_mut(4);
+
+        let mut form = ClassElem::new(p.clone(), b, c);
+        self.reduce_mut(&mut form);
+        form.group = Some(self.group);
+        Ok(form)
+    }
+
+    /// The prime forms of the `count` smallest primes that split, in increasing order of the
+    /// prime.
+    pub fn small_prime_forms(&mut self, count: usize) -> Vec<ClassElem<B>> {
+        let mut forms = Vec::with_capacity(count);
+        let (mut low, mut high) = (0, 64);
+        while forms.len() < count {
+            for p in primes_up_to(high).into_iter().filter(|&p| p > low) {
+                if let Ok(form) = self.prime_form(&B::from(p)) {
+                    forms.push(form);
+                    if forms.len() == count {
+                        break;
+                    }
+                }
+            }
+            low = high;
+            high *= 2;
+        }
+        forms
+    }
+}
+
+impl<B: BigNum> ClassGroupOf<B> {
+    /// `ClassCtx::prime_form` in the calling thread's group.
+    pub fn prime_form(p: &B) -> Result<ClassElem<B>, PrimeFormError> {
+        with_class_ctx(|ctx: &mut ClassCtx<B>| ctx.prime_form(p))
+    }
+
+    /// `ClassCtx::small_prime_forms` in the calling thread's group.
+    pub fn small_prime_forms(count: usize) -> Vec<ClassElem<B>> {
+        with_class_ctx(|ctx: &mut ClassCtx<B>| ctx.small_prime_forms(count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::group::{ClassGroup, ClassGroupStructure};
+    use crate::num::DefaultBigNum;
+
+    fn big(x: i64) -> DefaultBigNum {
+        let mut r = DefaultBigNum::default();
+        r.set_si(x);
+        r
+    }
+
+    #[test]
+    fn test_prime_forms() {
+        let d = big(-23);
+        let mut ctx = ClassCtx::from_discriminant(&d);
+        assert_eq!(
+            ctx.prime_form(&big(2)).unwrap(),
+            ClassElem::new(big(2), big(1), big(3))
+        );
+        // (3, 1, 2) reduces to (2, -1, 3), the inverse of (2, 1, 3).
+        assert_eq!(
+            ctx.prime_form(&big(3)).unwrap(),
+            ClassElem::new(big(2), big(-1), big(3))
+        );
+        assert_eq!(ctx.prime_form(&big(5)), Err(PrimeFormError::Inert));
+        assert_eq!(ctx.prime_form(&big(23)), Err(PrimeFormError::Ramified));
+        // (-23 / 9) = 1, but 9 has no non-residue to run Tonelli-Shanks with.
+        for &n in &[9, 15, 1, 0, -3] {
+            assert_eq!(ctx.prime_form(&big(n)), Err(PrimeFormError::NotPrime));
+        }
+
+        // -3299 = 5 mod 8, so 2 is inert.
+        let d = big(-3299);
+        let mut ctx = ClassCtx::from_discriminant(&d);
+        assert_eq!(ctx.prime_form(&big(2)), Err(PrimeFormError::Inert));
+        let forms = ctx.small_prime_forms(10);
+        assert_eq!(forms.len(), 10);
+        let structure = ClassGroupStructure::compute(&d).unwrap();
+        for x in forms.iter() {
+            assert!(x.is_reduced());
+            assert_eq!(x.discriminant(), d);
+            assert_eq!(x.group(), Some(ctx.group()));
+            assert!(structure.verify_order(x));
+        }
+    }
+
+    #[test]
+    fn test_small_prime_forms() {
+        // |D| is far larger than p^2, so the prime forms are already reduced and keep their norm.
+        let d = ClassGroup::current_discriminant();
+        let forms = ClassGroup::small_prime_forms(20);
+        let norms: Vec<u64> = forms.iter().map(|x| x.a.get_si() as u64).collect();
+        let split: Vec<u64> = primes_up_to(norms[19])
+            .into_iter()
+            .filter(|&p| d.kronecker(&DefaultBigNum::from(p)) == 1)
+            .collect();
+        assert_eq!(norms, split);
+
+        for x in forms.iter() {
+            assert!(ClassGroup::op(x, &-x).is_identity());
+        }
+        assert_eq!(ClassGroup::prime_form(&forms[3].a), Ok(forms[3].clone()));
+    }
+}
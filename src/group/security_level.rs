@@ -0,0 +1,174 @@
+//! Security levels and the discriminant sizes recommended for them.
+use super::{DiscriminantParams, Expansion};
+use crate::num::Mpz;
+use std::str::FromStr;
+
+/// Target security of a class group against the best known attacks, which are subexponential
+/// index-calculus methods for computing the class group structure.
+///
+/// The sizes follow published extrapolations of those attacks (Hamdy and Möller 2000; Biasse,
+/// Jacobson and Silvester 2010), with `Bits128Conservative` at the 3598 bits suggested by Dobson,
+/// Galbraith and Smith (2020). Estimates for class groups are less settled than for RSA, so
+/// long-lived parameters should prefer the conservative level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SecurityLevel {
+    /// 1024-bit discriminants, the size Chia's VDF uses.
+    Bits80,
+    /// 1665-bit discriminants.
+    Bits100,
+    /// 1827-bit discriminants.
+    Bits128,
+    /// 3598-bit discriminants.
+    Bits128Conservative,
+}
+
+impl SecurityLevel {
+    /// Every level, from weakest to strongest.
+    pub const ALL: [SecurityLevel; 4] = [
+        SecurityLevel::Bits80,
+        SecurityLevel::Bits100,
+        SecurityLevel::Bits128,
+        SecurityLevel::Bits128Conservative,
+    ];
+
+    /// Target security in bits.
+    pub fn security_bits(self) -> u32 {
+        match self {
+            SecurityLevel::Bits80 => 80,
+            SecurityLevel::Bits100 => 100,
+            SecurityLevel::Bits128 | SecurityLevel::Bits128Conservative => 128,
+        }
+    }
+
+    /// Bit length of |D| recommended for the level.
+    pub fn discriminant_bits(self) -> u64 {
+        match self {
+            SecurityLevel::Bits80 => 1024,
+            SecurityLevel::Bits100 => 1665,
+            SecurityLevel::Bits128 => 1827,
+            SecurityLevel::Bits128Conservative => 3598,
+        }
+    }
+
+    /// A fixed discriminant for the level: `create_discriminant_for(self, b"classygroup")`.
+    pub fn discriminant(self) -> &'static Mpz {
+        match self {
+            SecurityLevel::Bits80 => &DISCRIMINANT_80,
+            SecurityLevel::Bits100 => &DISCRIMINANT_100,
+            SecurityLevel::Bits128 => &DISCRIMINANT_128,
+            SecurityLevel::Bits128Conservative => &DISCRIMINANT_128_CONSERVATIVE,
+        }
+    }
+}
+
+/// Derives a discriminant of exactly `level.discriminant_bits()` bits from `seed`.
+///
+/// This is `create_discriminant` with the SHA-256 expansion in place of Merlin, under the same
+/// label. The Merlin expansion reads whole bytes, so for lengths such as 1665 it would return up
+/// to 7 more bits than asked for; the SHA-256 one truncates to the length and sets the top bit.
+pub fn create_discriminant_for(level: SecurityLevel, seed: &[u8]) -> Mpz {
+    level_params().create(seed, level.discriminant_bits())
+}
+
+fn level_params() -> DiscriminantParams {
+    DiscriminantParams::classygroup().expansion(Expansion::Sha256)
+}
+
+const DISCRIMINANT1024_DECIMAL: &str =
+  "-11124479259279655079202399578312770038385461414717418589578849853761270641268629009251436570346\
+  008569367938376196665689036474155107060158977734072145742429156660598630692420210007331894234789\
+  997280855003394781420173302794522104416049066738797072713623582295526417786570398672696257022113\
+  9517677545306816691999";
+
+const DISCRIMINANT1665_DECIMAL: &str =
+  "-10150955204486634060020012708835784506973739177770033979982888736095700987371290092640953284841\
+  394628952837044615548758651219029939540092678527205670294022280989297254725736671107405423810226\
+  395839211689431569843493337729294012533512466824666823549877314731976997551356912330956162417781\
+  154517296689595427580472171510871640747777966856158466447683967201445884275646571005435009472262\
+  193344405431442239900830664320548809443297382674093252263618904680856562910471466800078660470822\
+  59742747586238719704191";
+
+const DISCRIMINANT1827_DECIMAL: &str =
+  "-59342550607319464492234857367677821263578591425238255458286868164044791233660348032401631765209\
+  568455061707377528285177487011755642439639864999274490937913581265427633210422847221164687251396\
+  326489904169334073657018477104058291303285223161631661149577333902799724427145778562094398574264\
+  501754703306774982290969675724852865875752145671526870406237655152552272462966137772968514029604\
+  842770078698673158707282486119408829426567706967652268938090889987242381044099071214050002647169\
+  46750191974607287548514317116648690488048201036767179424779202889894359";
+
+const DISCRIMINANT3598_DECIMAL: &str =
+  "-78974799089581035498751891104667751836758369127401338798148009290436418423250530045312791832324\
+  834740100468826091546292240506688811355790961598801509896137465206565233277946122907803186390633\
+  469525359854969260492394934959614078814361989289630747716271049538144521676611884515629031910545\
+  421886979195194750878439387732823680914676623101533553229027373853010588052121116818483937737604\
+  273841593353725105568130293554342699340067827000060363472106545110537496203967961714423790707682\
+  745017805722770516312661452185284315656547715401447223307526573499392819956451421426283647017774\
+  272102228779585485486875679686329836520388430386992291179093779640832144158977427925669957122454\
+  029355837563314438519106948937992477092248434722863372358943706932030853285268056721895778688547\
+  861309949404841258041861754272122072101040932655268518073936769634305183266801326811685928286083\
+  519386975137364116698509066137817833133959605861288023031214794925390608819586942019364951991234\
+  729325586454783209497023540818705887342537200045731699521903416551120219036910154843159324753440\
+  7320529667709664766752328983";
+
+lazy_static! {
+    static ref DISCRIMINANT_80: Mpz = Mpz::from_str(DISCRIMINANT1024_DECIMAL).unwrap();
+    static ref DISCRIMINANT_100: Mpz = Mpz::from_str(DISCRIMINANT1665_DECIMAL).unwrap();
+    static ref DISCRIMINANT_128: Mpz = Mpz::from_str(DISCRIMINANT1827_DECIMAL).unwrap();
+    static ref DISCRIMINANT_128_CONSERVATIVE: Mpz =
+        Mpz::from_str(DISCRIMINANT3598_DECIMAL).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::group::{validate_discriminant, DiscriminantPolicy};
+
+    /// Seed of the fixed discriminants.
+    const FIXED_SEED: &[u8] = b"classygroup";
+
+    #[test]
+    fn test_fixed_discriminants_are_valid() {
+        for &level in SecurityLevel::ALL.iter() {
+            let d = level.discriminant();
+            assert_eq!(d.bit_length() as u64, level.discriminant_bits());
+            assert_eq!(
+                validate_discriminant(d, &DiscriminantPolicy::for_level(level)),
+                Ok(())
+            );
+        }
+    }
+
+    #[test]
+    fn test_fixed_discriminants_are_derived() {
+        for &level in SecurityLevel::ALL[..3].iter() {
+            assert_eq!(
+                create_discriminant_for(level, FIXED_SEED),
+                *level.discriminant()
+            );
+        }
+    }
+
+    // Takes over ten seconds.
+    #[test]
+    #[ignore]
+    fn test_conservative_discriminant_is_derived() {
+        let level = SecurityLevel::Bits128Conservative;
+        assert_eq!(
+            create_discriminant_for(level, FIXED_SEED),
+            *level.discriminant()
+        );
+    }
+
+    #[test]
+    fn test_create_discriminant_for_has_exact_length() {
+        let level = SecurityLevel::Bits100;
+        for seed in [&b"a"[..], b"b", b"c"].iter() {
+            let d = create_discriminant_for(level, seed);
+            assert_eq!(d.bit_length(), 1665);
+            assert_eq!(
+                validate_discriminant(&d, &DiscriminantPolicy::for_level(level)),
+                Ok(())
+            );
+        }
+    }
+}
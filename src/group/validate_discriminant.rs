@@ -0,0 +1,256 @@
+//! Checks for discriminants supplied from outside the crate.
+
+use super::{DiscriminantParams, SecurityLevel};
+use crate::hash::primality::big::is_prob_prime;
+use crate::num::{BigNum, Mpz};
+use std::fmt;
+
+/// The congruence a discriminant must satisfy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResidueClass {
+    /// D ≡ 1 mod 4, which every odd fundamental discriminant satisfies.
+    OneModFour,
+    /// D ≡ 1 mod 8, that is |D| ≡ 7 mod 8 as `create_discriminant` guarantees. Then 2 splits, so
+    /// the form (2, 1, (1 - D) / 8) exists.
+    OneModEight,
+}
+
+impl ResidueClass {
+    fn contains(self, d: &Mpz) -> bool {
+        match self {
+            ResidueClass::OneModFour => d.fdiv_ui(4) == 1,
+            ResidueClass::OneModEight => d.fdiv_ui(8) == 1,
+        }
+    }
+}
+
+impl fmt::Display for ResidueClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResidueClass::OneModFour => write!(f, "1 mod 4"),
+            ResidueClass::OneModEight => write!(f, "1 mod 8"),
+        }
+    }
+}
+
+/// What `validate_discriminant` requires of a discriminant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiscriminantPolicy<'a> {
+    /// Required congruence of D.
+    pub residue: ResidueClass,
+    /// Minimum bit length of |D|.
+    pub min_bits: usize,
+    /// If set, D must be the discriminant `params` derive from this `(seed, length)`.
+    pub derivation: Option<(&'a [u8], u64)>,
+    /// Derivation checked by `derivation`, `DiscriminantParams::classygroup` by default.
+    pub params: DiscriminantParams,
+}
+
+impl<'a> DiscriminantPolicy<'a> {
+    pub fn new(residue: ResidueClass, min_bits: usize) -> Self {
+        DiscriminantPolicy {
+            residue,
+            min_bits,
+            derivation: None,
+            params: DiscriminantParams::default(),
+        }
+    }
+
+    /// D ≡ 1 mod 4 and the discriminant size of `level`.
+    pub fn for_level(level: SecurityLevel) -> Self {
+        DiscriminantPolicy::new(ResidueClass::OneModFour, level.discriminant_bits() as usize)
+    }
+
+    /// The policy for a discriminant claimed to be `create_discriminant(seed, length)`. The result
+    /// of `create_discriminant` only fits in `length` bits, so no minimum is set; raise `min_bits`
+    /// to reject short lengths.
+    pub fn derived_from(seed: &'a [u8], length: u64) -> Self {
+        DiscriminantPolicy::derived_with(DiscriminantParams::default(), seed, length)
+    }
+
+    /// Like `derived_from`, for a discriminant derived with `params`.
+    pub fn derived_with(params: DiscriminantParams, seed: &'a [u8], length: u64) -> Self {
+        DiscriminantPolicy {
+            residue: params.residue_class(),
+            min_bits: 0,
+            derivation: Some((seed, length)),
+            params,
+        }
+    }
+}
+
+impl Default for DiscriminantPolicy<'_> {
+    /// D ≡ 1 mod 4 and at least 1024 bits, with no derivation check.
+    fn default() -> Self {
+        DiscriminantPolicy::new(ResidueClass::OneModFour, 1024)
+    }
+}
+
+/// The property of a discriminant that failed validation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiscriminantError {
+    /// D is zero or positive.
+    NotNegative,
+    /// D is not in the residue class required by the policy.
+    WrongResidue { expected: ResidueClass },
+    /// |D| has fewer bits than the policy requires.
+    TooShort { bits: usize, min_bits: usize },
+    /// |D| is not a probable prime.
+    NotPrime,
+    /// D is not the discriminant derived from the claimed seed and length.
+    NotDerived,
+}
+
+impl fmt::Display for DiscriminantError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiscriminantError::NotNegative => write!(f, "discriminant is not negative"),
+            DiscriminantError::WrongResidue { expected } => {
+                write!(f, "discriminant is not {}", expected)
+            }
+            DiscriminantError::TooShort { bits, min_bits } => write!(
+                f,
+                "discriminant has {} bits, at least {} are required",
+                bits, min_bits
+            ),
+            DiscriminantError::NotPrime => write!(f, "discriminant is not a negated prime"),
+            DiscriminantError::NotDerived => {
+                write!(f, "discriminant is not derived from the claimed seed")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DiscriminantError {}
+
+/// Checks that `d` is safe to build a class group from under `policy`.
+///
+/// The properties are checked in the order of the `DiscriminantError` variants, and the first one
+/// that fails is returned. Primality is a BPSW test; use `primality::certificate` where a proof is
+/// needed. The derivation check repeats the search for the discriminant, which is by far the most
+/// expensive part.
+pub fn validate_discriminant(
+    d: &Mpz,
+    policy: &DiscriminantPolicy,
+) -> Result<(), DiscriminantError> {
+    if d.sgn() >= 0 {
+        return Err(DiscriminantError::NotNegative);
+    }
+    if !policy.residue.contains(d) {
+        return Err(DiscriminantError::WrongResidue {
+            expected: policy.residue,
+        });
+    }
+    let bits = d.bit_length();
+    if bits < policy.min_bits {
+        return Err(DiscriminantError::TooShort {
+            bits,
+            min_bits: policy.min_bits,
+        });
+    }
+    let mut n = Mpz::default();
+    n.abs(d);
+    if !is_prob_prime(&n) {
+        return Err(DiscriminantError::NotPrime);
+    }
+    if let Some((seed, length)) = policy.derivation {
+        if policy.params.create(seed, length) != *d {
+            return Err(DiscriminantError::NotDerived);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_discriminant, CLASS_GROUP_DISCRIMINANT};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_accepts_valid_discriminants() {
+        let d = &*CLASS_GROUP_DISCRIMINANT;
+        assert_eq!(
+            validate_discriminant(d, &DiscriminantPolicy::default()),
+            Ok(())
+        );
+        assert_eq!(
+            validate_discriminant(d, &DiscriminantPolicy::new(ResidueClass::OneModEight, 2048)),
+            Ok(())
+        );
+
+        let d = create_discriminant(b"\xaa", 512);
+        let policy = DiscriminantPolicy::derived_from(b"\xaa", 512);
+        assert_eq!(validate_discriminant(&d, &policy), Ok(()));
+    }
+
+    #[test]
+    fn test_reports_failed_property() {
+        let policy = DiscriminantPolicy::new(ResidueClass::OneModFour, 8);
+        let check = |d: &str| validate_discriminant(&Mpz::from_str(d).unwrap(), &policy);
+
+        assert_eq!(check("0"), Err(DiscriminantError::NotNegative));
+        assert_eq!(check("1021"), Err(DiscriminantError::NotNegative));
+        assert_eq!(
+            check("-1020"),
+            Err(DiscriminantError::WrongResidue {
+                expected: ResidueClass::OneModFour
+            })
+        );
+        assert_eq!(
+            check("-23"),
+            Err(DiscriminantError::TooShort {
+                bits: 5,
+                min_bits: 8
+            })
+        );
+        // 1023 = 3 * 11 * 31.
+        assert_eq!(check("-1023"), Err(DiscriminantError::NotPrime));
+        assert_eq!(check("-1019"), Ok(()));
+
+        // -1019 is 5 mod 8.
+        let policy = DiscriminantPolicy::new(ResidueClass::OneModEight, 8);
+        assert_eq!(
+            validate_discriminant(&Mpz::from_str("-1019").unwrap(), &policy),
+            Err(DiscriminantError::WrongResidue {
+                expected: ResidueClass::OneModEight
+            })
+        );
+    }
+
+    #[test]
+    fn test_checks_derivation_with_params() {
+        let chia = DiscriminantParams::chia();
+        let d = chia.create(b"\xaa", 512);
+        assert_eq!(
+            validate_discriminant(&d, &DiscriminantPolicy::derived_with(chia, b"\xaa", 512)),
+            Ok(())
+        );
+        assert_eq!(
+            validate_discriminant(&d, &DiscriminantPolicy::derived_from(b"\xaa", 512)),
+            Err(DiscriminantError::NotDerived)
+        );
+    }
+
+    #[test]
+    fn test_checks_derivation() {
+        let d = create_discriminant(b"\xaa", 512);
+        assert_eq!(
+            validate_discriminant(&d, &DiscriminantPolicy::derived_from(b"\xab", 512)),
+            Err(DiscriminantError::NotDerived)
+        );
+        assert_eq!(
+            validate_discriminant(&d, &DiscriminantPolicy::derived_from(b"\xaa", 513)),
+            Err(DiscriminantError::NotDerived)
+        );
+        let mut policy = DiscriminantPolicy::derived_from(b"\xaa", 512);
+        policy.min_bits = 1024;
+        assert_eq!(
+            validate_discriminant(&d, &policy),
+            Err(DiscriminantError::TooShort {
+                bits: d.bit_length(),
+                min_bits: 1024
+            })
+        );
+    }
+}
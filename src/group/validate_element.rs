@@ -0,0 +1,166 @@
+//! Checks for group elements supplied from outside the crate, such as VDF outputs and proofs or
+//! accumulator values and witnesses, for the verifiers of those schemes to call.
+
+use super::{ClassElem, ClassGroupOf, MAX_SMALL_ORDER_BOUND};
+use crate::num::BigNum;
+use std::fmt;
+
+/// The property of an element that failed validation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ElementError {
+    /// The form's discriminant is not the group's.
+    WrongDiscriminant,
+    /// The form is not reduced, so it is not the encoding the group operations produce.
+    NotReduced,
+    /// The element's order is at most the bound.
+    SmallOrder { order: u64 },
+    /// The small order bound exceeds `MAX_SMALL_ORDER_BOUND`.
+    BoundTooLarge,
+}
+
+impl fmt::Display for ElementError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ElementError::WrongDiscriminant => {
+                write!(f, "element does not have the group's discriminant")
+            }
+            ElementError::NotReduced => write!(f, "element is not a reduced form"),
+            ElementError::SmallOrder { order } => write!(f, "element has order {}", order),
+            ElementError::BoundTooLarge => write!(f, "small order bound is too large"),
+        }
+    }
+}
+
+impl std::error::Error for ElementError {}
+
+impl<B: BigNum> ClassGroupOf<B> {
+    /// Checks that `x` is safe for a verifier to accept: a reduced form of the current group's
+    /// discriminant whose order is larger than `small_order_bound`.
+    ///
+    /// Security arguments based on the adaptive root or low order assumptions fail if the prover
+    /// may submit elements of known small order, such as the identity or an ambiguous form, so
+    /// a Wesolowski or Pietrzak verifier should run this on the claimed output and every proof
+    /// element, and an accumulator verifier on the accumulator and each witness. The bound is
+    /// checked against `MAX_SMALL_ORDER_BOUND` first, then the properties in the order of the
+    /// `ElementError` variants; see `small_order` for the cost of the last one.
+    ///
+    /// This crate has no VDF or accumulator verifiers of its own, so nothing here calls this;
+    /// verifiers built on the crate have to call it on their inputs themselves.
+    pub fn validate_element(x: &ClassElem<B>, small_order_bound: u64) -> Result<(), ElementError> {
+        if small_order_bound > MAX_SMALL_ORDER_BOUND {
+            return Err(ElementError::BoundTooLarge);
+        }
+        if !Self::validate(&x.a, &x.b, &x.c) {
+            return Err(ElementError::WrongDiscriminant);
+        }
+        if !x.is_reduced() {
+            return Err(ElementError::NotReduced);
+        }
+        match Self::small_order(x, small_order_bound) {
+            Some(order) => Err(ElementError::SmallOrder { order }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::group::{ClassGroup, ClassGroupStructure};
+    use crate::num::DefaultBigNum;
+
+    fn disc(d: i64) -> DefaultBigNum {
+        let mut disc = DefaultBigNum::default();
+        disc.set_si(d);
+        disc
+    }
+
+    #[test]
+    fn test_small_order() {
+        let g = ClassGroup::unknown_order_elem();
+        assert!(!ClassGroup::has_small_order(&g, 1000));
+        assert_eq!(ClassGroup::small_order(&ClassGroup::id(), 1000), Some(1));
+        assert!(!ClassGroup::has_small_order(&ClassGroup::id(), 0));
+        assert_eq!(
+            ClassGroup::small_order(&ClassGroup::id(), MAX_SMALL_ORDER_BOUND),
+            Some(1)
+        );
+
+        // The group of -3299 is C3 x C9.
+        let d = disc(-3299);
+        let group = ClassGroupStructure::compute(&d).unwrap();
+        for x in group.generators() {
+            let order = group.element_order(x);
+            ClassGroup::with_discriminant(&d, || {
+                assert_eq!(ClassGroup::small_order(x, 9), Some(order));
+                assert_eq!(ClassGroup::small_order(x, order), Some(order));
+                assert_eq!(ClassGroup::small_order(x, order - 1), None);
+            });
+        }
+
+        // Orders with several prime factors, from products of generators.
+        for &d in &[-1555, -4020, -9799] {
+            let d = disc(d);
+            let group = ClassGroupStructure::compute(&d).unwrap();
+            let gens = group.generators();
+            for x in gens.iter() {
+                for y in gens.iter() {
+                    let xy = ClassGroup::with_discriminant(&d, || ClassGroup::op(x, y));
+                    let order = group.element_order(&xy);
+                    ClassGroup::with_discriminant(&d, || {
+                        assert_eq!(ClassGroup::small_order(&xy, 1000), Some(order));
+                        assert_eq!(ClassGroup::small_order(&xy, order - 1), None);
+                    });
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_element() {
+        let g = ClassGroup::unknown_order_elem();
+        assert_eq!(ClassGroup::validate_element(&g, 100), Ok(()));
+        for &bound in &[MAX_SMALL_ORDER_BOUND + 1, 1 << 40, u64::MAX] {
+            assert_eq!(
+                ClassGroup::validate_element(&g, bound),
+                Err(ElementError::BoundTooLarge)
+            );
+        }
+        assert_eq!(
+            ClassGroup::validate_element(&ClassGroup::id(), 100),
+            Err(ElementError::SmallOrder { order: 1 })
+        );
+
+        // (a, b + 2a, a + b + c) is in the class of (a, b, c) but not normalized.
+        let mut x = g.clone();
+        x.b.add_mut(&g.a);
+        x.b.add_mut(&g.a);
+        x.c.add_mut(&g.a);
+        x.c.add_mut(&g.b);
+        assert_eq!(x.discriminant(), g.discriminant());
+        assert_eq!(
+            ClassGroup::validate_element(&x, 100),
+            Err(ElementError::NotReduced)
+        );
+
+        let mut y = g.clone();
+        y.c.add_ui_mut(1);
+        assert_eq!(
+            ClassGroup::validate_element(&y, 100),
+            Err(ElementError::WrongDiscriminant)
+        );
+
+        // (2, 2, 11) is ambiguous, of order 2 in the group of -84.
+        let mut z = ClassElem::<DefaultBigNum>::default();
+        z.a.set_ui(2);
+        z.b.set_ui(2);
+        z.c.set_ui(11);
+        ClassGroup::with_discriminant(&disc(-84), || {
+            assert_eq!(
+                ClassGroup::validate_element(&z, 100),
+                Err(ElementError::SmallOrder { order: 2 })
+            );
+            assert_eq!(ClassGroup::validate_element(&z, 1), Ok(()));
+        });
+    }
+}
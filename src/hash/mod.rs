@@ -1,5 +1,6 @@
 //! This module wraps `blake2b_rfc` into a convenient hashing interface (`GeneralHasher`) and
 //! exports the `hash_to_prime` function. `hash_to_prime` is optimized to produce 256-bit primes.
+use crate::num::Mpz;
 use crate::uint::u256;
 use mohan::hash::{blake256, H256};
 use rug::integer::Order;
@@ -7,26 +8,156 @@ use rug::Integer;
 use std::hash::Hash;
 pub mod primality;
 
+/// Parameters for `hash_to_prime_with`.
+#[derive(Clone, Copy, Debug)]
+pub struct HashToPrimeParams<'a> {
+    /// Size of the candidates in bits; the prime found is below `2^bits`.
+    pub bits: usize,
+    /// Domain separation tag. Empty means no tag, which is what `hash_to_prime` uses.
+    pub domain: &'a [u8],
+    /// Hash function producing 32 bytes per call. Wider candidates are built from several
+    /// calls, each with a block index appended to the input.
+    pub hash: fn(&[u8]) -> [u8; 32],
+}
+
+impl<'a> HashToPrimeParams<'a> {
+    pub fn new(bits: usize, domain: &'a [u8]) -> Self {
+        HashToPrimeParams {
+            bits,
+            domain,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for HashToPrimeParams<'_> {
+    fn default() -> Self {
+        HashToPrimeParams {
+            bits: 256,
+            domain: &[],
+            hash: blake2b_256,
+        }
+    }
+}
+
+/// Blake2b with a 256-bit output, the hash used by `hash_to_prime`.
+pub fn blake2b_256(data: &[u8]) -> [u8; 32] {
+    blake256(data).to_bytes()
+}
+
 /// Hashes t with an incrementing counter (with blake2b) until a prime is found.
 pub fn hash_to_prime(t: &[u8]) -> Integer {
+    hash_to_prime_with(&HashToPrimeParams::default(), t)
+}
+
+/// Hashes `t` under `params` with an incrementing counter until a prime is found.
+///
+/// Each candidate is the little-endian integer formed by the first `params.bits` bits of
+/// `hash(tag || t || counter)`, forced odd, where `tag` is the length-prefixed domain (omitted when
+/// the domain is empty) and `counter` is a little-endian `u64`. Candidates up to 256 bits are tested
+/// with the `U256` BPSW test, larger ones with GMP.
+///
+/// # Panics
+///
+/// If `params.bits < 2`.
+pub fn hash_to_prime_with(params: &HashToPrimeParams, t: &[u8]) -> Integer {
+    search_prime(params, t).0
+}
+
+/// Like `hash_to_prime`, but also returns the counter value (nonce) that produced the prime, so
+/// that verifiers can use `verify_hash_to_prime` or `verify_hash_to_prime_unchecked` instead of
+/// repeating the search.
+pub fn hash_to_prime_with_nonce(t: &[u8]) -> (Integer, u64) {
+    search_prime(&HashToPrimeParams::default(), t)
+}
+
+/// Checks that `p` is `hash_to_prime(t)` and was found at counter value `nonce`.
+///
+/// This costs `nonce + 1` hashes and primality tests, as uniqueness requires that every earlier
+/// counter value gave a composite. Those are usually rejected by trial division, with the
+/// base-2 Miller-Rabin round that starts BPSW for the remainder, so this is cheaper than the
+/// search but not constant time.
+pub fn verify_hash_to_prime(t: &[u8], nonce: u64, p: &Integer) -> bool {
+    verify_hash_to_prime_unchecked(t, nonce, p)
+        && (0..nonce).all(|counter| {
+            let params = HashToPrimeParams::default();
+            !is_prime_candidate(&params, &prime_candidate(&params, t, counter))
+        })
+}
+
+/// Checks that `p` is the prime candidate for `t` at counter value `nonce`, with one hash and one
+/// primality test.
+///
+/// Earlier counter values are not checked, so a prover may pick any prime from the sequence of
+/// `t` rather than the first one `hash_to_prime` returns. Use this only where that choice does
+/// not matter, and `verify_hash_to_prime` where `p` must be unique.
+pub fn verify_hash_to_prime_unchecked(t: &[u8], nonce: u64, p: &Integer) -> bool {
+    let params = HashToPrimeParams::default();
+    let candidate = prime_candidate(&params, t, nonce);
+    Integer::from_digits(&candidate, Order::Lsf) == *p && is_prime_candidate(&params, &candidate)
+}
+
+fn search_prime(params: &HashToPrimeParams, t: &[u8]) -> (Integer, u64) {
+    assert!(params.bits >= 2, "hash_to_prime needs at least 2 bits");
     let mut counter = 0_u64;
     loop {
-        let mut buf = Vec::new();
-        buf.extend_from_slice(t);
-        buf.extend_from_slice(&counter.to_le_bytes());
-
-        let hash = blake256(&buf);
-        let mut hash = hash.to_bytes();
-        // Make the candidate prime odd. This gives ~7% performance gain on a 2018 Macbook Pro.
-        hash[0] |= 1;
-        let candidate_prime = u256(hash);
-        if primality::is_prob_prime(&candidate_prime) {
-            return Integer::from(candidate_prime);
+        let candidate = prime_candidate(params, t, counter);
+        if is_prime_candidate(params, &candidate) {
+            return (Integer::from_digits(&candidate, Order::Lsf), counter);
         }
         counter += 1;
     }
 }
 
+/// Primality test for the little-endian candidate bytes built by `prime_candidate`.
+fn is_prime_candidate(params: &HashToPrimeParams, candidate: &[u8]) -> bool {
+    if params.bits <= 256 {
+        let mut digits = [0; 32];
+        digits[..candidate.len()].copy_from_slice(candidate);
+        let candidate_prime = u256(digits);
+        // 1 is the only odd candidate the BPSW test is not defined for.
+        candidate_prime != 1 && primality::is_prob_prime(&candidate_prime)
+    } else {
+        let mut be = candidate.to_vec();
+        be.reverse();
+        primality::big::is_prob_prime(&Mpz::from_bytes(&be))
+    }
+}
+
+/// The little-endian bytes of the candidate tried at `counter`.
+fn prime_candidate(params: &HashToPrimeParams, t: &[u8], counter: u64) -> Vec<u8> {
+    let mut buf = Vec::new();
+    if !params.domain.is_empty() {
+        buf.extend_from_slice(&(params.domain.len() as u64).to_le_bytes());
+        buf.extend_from_slice(params.domain);
+    }
+    buf.extend_from_slice(t);
+    buf.extend_from_slice(&counter.to_le_bytes());
+
+    let len = (params.bits + 7) / 8;
+    let mut bytes = if len <= 32 {
+        (params.hash)(&buf).to_vec()
+    } else {
+        let mut out = Vec::with_capacity(len + 32);
+        let mut block = 0_u32;
+        while out.len() < len {
+            let mut input = buf.clone();
+            input.extend_from_slice(&block.to_le_bytes());
+            out.extend_from_slice(&(params.hash)(&input));
+            block += 1;
+        }
+        out
+    };
+    bytes.truncate(len);
+    let top_bits = params.bits % 8;
+    if top_bits != 0 {
+        bytes[len - 1] &= (1 << top_bits) - 1;
+    }
+    // Make the candidate prime odd. This gives ~7% performance gain on a 2018 Macbook Pro.
+    bytes[0] |= 1;
+    bytes
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -52,4 +183,78 @@ mod tests {
         h_2.write_digits(&mut digits2, Order::Lsf);
         assert!(primality::is_prob_prime(&u256(digits2)));
     }
+
+    #[test]
+    fn test_hash_to_prime_with_default_params() {
+        let t = b"boom i got ur boyfriend";
+        assert_eq!(
+            hash_to_prime(t),
+            hash_to_prime_with(&HashToPrimeParams::default(), t)
+        );
+    }
+
+    #[test]
+    fn test_hash_to_prime_with_sizes() {
+        let t = b"martian cyborg gerbil attack";
+        for &bits in &[2, 17, 64, 128, 255, 256, 257, 512, 1000] {
+            let p = hash_to_prime_with(&HashToPrimeParams::new(bits, b"sizes"), t);
+            assert!(p.significant_bits() as usize <= bits);
+            assert_ne!(p.is_probably_prime(30), rug::integer::IsPrime::No);
+        }
+        let p = hash_to_prime_with(&HashToPrimeParams::new(1024, b"sizes"), t);
+        assert!(p.significant_bits() > 1000);
+    }
+
+    #[test]
+    fn test_hash_to_prime_nonce() {
+        let t = b"boom i got ur boyfriend";
+        let (p, nonce) = hash_to_prime_with_nonce(t);
+        assert_eq!(p, hash_to_prime(t));
+        assert!(verify_hash_to_prime(t, nonce, &p));
+
+        // Wrong input, wrong prime, and a later prime for the same input are all rejected.
+        assert!(!verify_hash_to_prime(b"boom i got ur boyfriene", nonce, &p));
+        assert!(!verify_hash_to_prime(t, nonce, &(p.clone() + 2)));
+        let params = HashToPrimeParams::default();
+        let later = (nonce + 1..)
+            .find(|&n| is_prime_candidate(&params, &prime_candidate(&params, t, n)))
+            .unwrap();
+        let later_p = Integer::from_digits(&prime_candidate(&params, t, later), Order::Lsf);
+        assert!(!verify_hash_to_prime(t, later, &later_p));
+
+        // The unchecked form only binds `p` to its nonce, so it accepts the later prime.
+        assert!(verify_hash_to_prime_unchecked(t, nonce, &p));
+        assert!(verify_hash_to_prime_unchecked(t, later, &later_p));
+        assert!(!verify_hash_to_prime_unchecked(t, nonce, &later_p));
+        if nonce > 0 {
+            let earlier = Integer::from_digits(&prime_candidate(&params, t, nonce - 1), Order::Lsf);
+            assert!(!verify_hash_to_prime(t, nonce - 1, &earlier));
+        }
+    }
+
+    #[test]
+    fn test_hash_to_prime_with_domain_and_hash() {
+        fn reversed_blake(data: &[u8]) -> [u8; 32] {
+            let mut h = blake2b_256(data);
+            h.reverse();
+            h
+        }
+
+        let t = b"boom i got ur boyfriend";
+        let plain = hash_to_prime_with(&HashToPrimeParams::new(128, b""), t);
+        let poe = hash_to_prime_with(&HashToPrimeParams::new(128, b"poe"), t);
+        let acc = hash_to_prime_with(&HashToPrimeParams::new(128, b"acc"), t);
+        assert_ne!(plain, poe);
+        assert_ne!(poe, acc);
+        assert_eq!(
+            poe,
+            hash_to_prime_with(&HashToPrimeParams::new(128, b"poe"), t)
+        );
+
+        let params = HashToPrimeParams {
+            hash: reversed_blake,
+            ..HashToPrimeParams::new(128, b"poe")
+        };
+        assert_ne!(poe, hash_to_prime_with(&params, t));
+    }
 }
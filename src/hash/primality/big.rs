@@ -0,0 +1,398 @@
+//! Baillie-PSW for integers of any size, over any `BigNum` backend. This is the same test as the
+//! U256 one in the parent module, without the width limit, and is what discriminant generation
+//! and wide `hash_to_prime_with` candidates use.
+use super::constants::{D_VALUES, SMALL_PRIMES};
+use crate::num::BigNum;
+
+/// The Lucas half of Baillie-PSW. All three variants accept every odd prime; they differ in which
+/// composites slip through, and each is paired with the same base-2 strong Fermat test, whose
+/// pseudoprimes are very unlikely to also be Lucas pseudoprimes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum LucasTest {
+    /// Strong Lucas test with Selfridge's parameters, as in the original BPSW. Its pseudoprimes
+    /// start 5459, 5777, 10877, ..., with 58 below 10^6; no composite passing BPSW with it is
+    /// known, and none exists below 2^64.
+    #[default]
+    Strong,
+    /// Extra strong Lucas test with Mo and Jones's parameters `Q = 1`, `P = 3, 4, 5, ...`. Its
+    /// pseudoprimes start 989, 3239, 5777, ..., with 42 below 10^6; none below 2^64 passes the
+    /// base-2 test as well.
+    ExtraStrong,
+    /// Strong Lucas test with Selfridge's method A* plus the `V_{n+1} = 2Q` and
+    /// `Q^((n+1)/2) = Q (Q/n)` checks, following Baillie, Fiori and Wagstaff (2021). It costs one
+    /// more squaring than `Strong`, rejects the pseudoprimes of both tests above, and no
+    /// composite below 10^6 passes it even on its own.
+    Enhanced,
+}
+
+/// Baillie-PSW with the strong Lucas test; see `is_prob_prime_with`.
+pub fn is_prob_prime<B: BigNum>(n: &B) -> bool {
+    is_prob_prime_with(n, LucasTest::Strong)
+}
+
+/// Baillie-PSW: trial division by small primes, a strong Fermat test to base 2 and the given Lucas
+/// test.
+pub fn is_prob_prime_with<B: BigNum>(n: &B, lucas: LucasTest) -> bool {
+    if n.cmp_si(2) < 0 {
+        return false;
+    }
+    for &p in SMALL_PRIMES.iter() {
+        if n.fdiv_ui(p) == 0 {
+            return n.cmp_si(p as i64) == 0;
+        }
+    }
+    passes_miller_rabin_base_2(n)
+        && match lucas {
+            LucasTest::Strong => passes_strong_lucas(n),
+            LucasTest::ExtraStrong => passes_extra_strong_lucas(n),
+            LucasTest::Enhanced => passes_enhanced_lucas(n),
+        }
+}
+
+/// Strong probable prime test to base 2 for odd `n > 2`.
+pub fn passes_miller_rabin_base_2<B: BigNum>(n: &B) -> bool {
+    passes_miller_rabin(n, 2)
+}
+
+/// Strong probable prime test to base `base` for odd `n > base`. Failing it proves `n` composite.
+pub fn passes_miller_rabin<B: BigNum>(n: &B, base: u64) -> bool {
+    let mut n_minus_1 = n.clone();
+    n_minus_1.sub_ui_mut(1);
+    let (d, s) = split_power_of_two(&n_minus_1);
+
+    let mut x = B::default();
+    x.powm(&B::from(base), &d, n);
+    if x.is_one() || x == n_minus_1 {
+        return true;
+    }
+    for _ in 1..s {
+        x.square_mut();
+        x.modulo_mut(n);
+        if x.is_one() {
+            return false;
+        }
+        if x == n_minus_1 {
+            return true;
+        }
+    }
+    false
+}
+
+/// Strong Lucas probable prime test for odd `n > 2`, with `P = 1` and `Q = (1 - D) / 4` where `D`
+/// is the first of `5, -7, 9, -11, ...` with Jacobi symbol `(D/n) = -1`. Writing
+/// `n + 1 = d * 2^s`, `n` passes if `U_d = 0` or `V_{d * 2^r} = 0 (mod n)` for some `0 <= r < s`.
+/// Perfect squares, for which no such `D` exists, are rejected up front.
+pub fn passes_strong_lucas<B: BigNum>(n: &B) -> bool {
+    let d = match choose_d(n) {
+        Some(d) => d,
+        None => return false,
+    };
+    let q = (1 - d) / 4;
+
+    let mut n_plus_1 = n.clone();
+    n_plus_1.add_ui_mut(1);
+    let (k, s) = split_power_of_two(&n_plus_1);
+    let (u, mut v, mut q_k) = lucas_sequences(&k, n, 1, d, q);
+    if u.is_zero() || v.is_zero() {
+        return true;
+    }
+    for _ in 1..s {
+        double_v(&mut v, &mut q_k, n);
+        if v.is_zero() {
+            return true;
+        }
+    }
+    false
+}
+
+/// Extra strong Lucas probable prime test for odd `n > 2`, with `Q = 1` and `P` the first of
+/// `3, 4, 5, ...` with `(D/n) = -1` for `D = P^2 - 4`. Writing `n + 1 = d * 2^s`, `n` passes if
+/// `U_d = 0` and `V_d = ±2`, or `V_{d * 2^r} = 0 (mod n)` for some `0 <= r < s - 1`.
+pub fn passes_extra_strong_lucas<B: BigNum>(n: &B) -> bool {
+    if n.is_perfect_square() {
+        return false;
+    }
+    let mut p = 3;
+    let d = loop {
+        let d = p * p - 4;
+        match from_i32::<B>(d).jacobi(n) {
+            -1 => break d,
+            // A common factor below `n` proves it composite; `n` dividing `D` tells us nothing.
+            0 if n.cmp_si(i64::from(d)) > 0 => return false,
+            _ => p += 1,
+        }
+    };
+
+    let mut n_plus_1 = n.clone();
+    n_plus_1.add_ui_mut(1);
+    let (k, s) = split_power_of_two(&n_plus_1);
+    let (u, mut v, _) = lucas_sequences(&k, n, p, d, 1);
+    let mut n_minus_2 = n.clone();
+    n_minus_2.sub_ui_mut(2);
+    if u.is_zero() && (v.cmp_si(2) == 0 || v == n_minus_2) {
+        return true;
+    }
+    let mut one = B::from(1);
+    for _ in 1..s {
+        if v.is_zero() {
+            return true;
+        }
+        double_v(&mut v, &mut one, n);
+    }
+    false
+}
+
+/// The strong Lucas test with two further congruences that hold for primes, from section 6 of
+/// Baillie and Wagstaff (1980) as revisited by Baillie, Fiori and Wagstaff (2021): `V_{n+1} = 2Q`
+/// and Euler's criterion `Q^((n+1)/2) = Q (Q/n) (mod n)`. Parameters follow Selfridge's method
+/// A*, which replaces `P = 1, Q = -1` by `P = Q = 5` since `Q = -1` makes the Euler check
+/// useless.
+pub fn passes_enhanced_lucas<B: BigNum>(n: &B) -> bool {
+    let d = match choose_d(n) {
+        Some(d) => d,
+        None => return false,
+    };
+    let (p, q) = if d == 5 { (5, 5) } else { (1, (1 - d) / 4) };
+
+    let mut n_plus_1 = n.clone();
+    n_plus_1.add_ui_mut(1);
+    let (k, s) = split_power_of_two(&n_plus_1);
+    let (u, mut v, mut q_k) = lucas_sequences(&k, n, p, d, q);
+    let mut strong = u.is_zero() || v.is_zero();
+    for _ in 1..s {
+        double_v(&mut v, &mut q_k, n);
+        strong |= v.is_zero();
+    }
+    if !strong {
+        return false;
+    }
+
+    // `v` and `q_k` are now `V_{(n+1)/2}` and `Q^((n+1)/2)`.
+    let q = from_i32::<B>(q);
+    let mut euler = B::default();
+    euler.mul_si(&q, i64::from(q.jacobi(n)));
+    euler.modulo_mut(n);
+    if q_k != euler {
+        return false;
+    }
+    double_v(&mut v, &mut q_k, n);
+    let mut two_q = B::default();
+    two_q.mul_ui(&q, 2);
+    two_q.modulo_mut(n);
+    v == two_q
+}
+
+/// Selfridge's method A. `None` if `n` is a perfect square, or if some `D` shares a factor with
+/// `n`, which proves it composite.
+fn choose_d<B: BigNum>(n: &B) -> Option<i32> {
+    if n.is_perfect_square() {
+        return None;
+    }
+    for &d in D_VALUES.iter() {
+        match from_i32::<B>(d).jacobi(n) {
+            -1 => return Some(d),
+            0 if n.cmp_si(i64::from(d.abs())) != 0 => return None,
+            _ => {}
+        }
+    }
+    panic!("n is not square but we still couldn't find a d value!")
+}
+
+/// Writes `x = d * 2^s` with `d` odd, for `x > 0`.
+fn split_power_of_two<B: BigNum>(x: &B) -> (B, u64) {
+    let mut s = 0;
+    while !x.tstbit(s) {
+        s += 1;
+    }
+    let mut d = B::default();
+    d.tdiv_q_2exp(x, s as u64);
+    (d, s as u64)
+}
+
+/// `(U_k, V_k, Q^k) mod n` for the Lucas sequences with parameters `P`, `Q` and `D = P^2 - 4Q`,
+/// by the usual left-to-right doubling ladder over the bits of `k`.
+fn lucas_sequences<B: BigNum>(k: &B, n: &B, p: i32, d: i32, q: i32) -> (B, B, B) {
+    let mut q0 = from_i32::<B>(q);
+    q0.modulo_mut(n);
+    let mut dd = from_i32::<B>(d);
+    dd.modulo_mut(n);
+
+    let (mut u, mut v, mut q_k) = (B::from(1), from_i32::<B>(p), q0.clone());
+    v.modulo_mut(n);
+    let mut t = B::default();
+    for bit in (0..k.bit_length() - 1).rev() {
+        // U_{2k} = U_k V_k
+        u.mul_mut(&v);
+        u.modulo_mut(n);
+        double_v(&mut v, &mut q_k, n);
+        if k.tstbit(bit) {
+            // U_{2k+1} = (P U_{2k} + V_{2k}) / 2, V_{2k+1} = (D U_{2k} + P V_{2k}) / 2
+            t.mul(&dd, &u);
+            t.add_mul(&v, &B::from(p as u64));
+            u.mul_ui_mut(p as u64);
+            u.add_mut(&v);
+            half_mod(&mut u, n);
+            v.set(&t);
+            half_mod(&mut v, n);
+            q_k.mul_mut(&q0);
+            q_k.modulo_mut(n);
+        }
+    }
+    (u, v, q_k)
+}
+
+/// `V_{2k} = V_k^2 - 2 Q^k` and `Q^{2k}`, in place and mod `n`.
+fn double_v<B: BigNum>(v: &mut B, q_k: &mut B, n: &B) {
+    v.square_mut();
+    v.submul(q_k, &B::from(2));
+    v.modulo_mut(n);
+    q_k.square_mut();
+    q_k.modulo_mut(n);
+}
+
+fn from_i32<B: BigNum>(x: i32) -> B {
+    let mut b = B::default();
+    b.set_si(i64::from(x));
+    b
+}
+
+/// `x = x / 2 mod n` for odd `n`, with `x` reduced into `[0, n)`.
+fn half_mod<B: BigNum>(x: &mut B, n: &B) {
+    x.modulo_mut(n);
+    if x.odd() {
+        x.add_mut(n);
+    }
+    x.fdiv_q_ui_mut(2);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::constants::*;
+    use super::*;
+    use crate::num::DefaultBigNum;
+
+    fn big(n: u64) -> DefaultBigNum {
+        DefaultBigNum::from(n)
+    }
+
+    #[test]
+    fn test_agrees_with_u256_on_small_inputs() {
+        for n in 0..20_000u64 {
+            let expected = n >= 2 && super::super::is_prob_prime(&crate::uint::u256(n));
+            assert_eq!(is_prob_prime(&big(n)), expected, "{}", n);
+        }
+    }
+
+    #[test]
+    fn test_pseudoprimes() {
+        for &n in STRONG_BASE_2_PSEUDOPRIMES.iter() {
+            assert!(passes_miller_rabin_base_2(&big(n)));
+            assert!(!passes_strong_lucas(&big(n)));
+            assert!(!is_prob_prime(&big(n)));
+        }
+        // 1373653 = 829 * 1657 is the smallest strong pseudoprime to both bases 2 and 3.
+        assert!(passes_miller_rabin(&big(1_373_653), 3));
+        assert!(!passes_miller_rabin(&big(1_373_653), 5));
+        assert!(passes_miller_rabin(&big(1_000_003), 5));
+        for &n in STRONG_LUCAS_PSEUDOPRIMES.iter() {
+            assert!(passes_strong_lucas(&big(n)));
+            assert!(!passes_miller_rabin_base_2(&big(n)));
+            assert!(!is_prob_prime(&big(n)));
+        }
+    }
+
+    #[test]
+    fn test_lucas_variants() {
+        // The counts in the `LucasTest` docs, against a sieve.
+        const LIMIT: usize = 1_000_000;
+        let mut composite = vec![false; LIMIT];
+        for i in 2..LIMIT {
+            if !composite[i] {
+                for j in (i * i..LIMIT).step_by(i) {
+                    composite[j] = true;
+                }
+            }
+        }
+
+        let (mut strong, mut extra_strong) = (vec![], vec![]);
+        for n in (3..LIMIT as u64).step_by(2) {
+            let prime = !composite[n as usize];
+            if passes_strong_lucas(&big(n)) && !prime {
+                strong.push(n);
+            }
+            if passes_extra_strong_lucas(&big(n)) && !prime {
+                extra_strong.push(n);
+            }
+            assert_eq!(passes_enhanced_lucas(&big(n)), prime, "{}", n);
+            for &test in &[
+                LucasTest::Strong,
+                LucasTest::ExtraStrong,
+                LucasTest::Enhanced,
+            ] {
+                assert_eq!(is_prob_prime_with(&big(n), test), prime, "{} {:?}", n, test);
+            }
+        }
+        assert_eq!(strong[..10], STRONG_LUCAS_PSEUDOPRIMES);
+        assert_eq!(extra_strong[..10], EXTRA_STRONG_LUCAS_PSEUDOPRIMES);
+        assert_eq!(strong.len(), 58);
+        assert_eq!(extra_strong.len(), 42);
+
+        for &p in LARGE_PRIMES.iter() {
+            assert!(passes_extra_strong_lucas(&big(p)));
+            assert!(passes_enhanced_lucas(&big(p)));
+        }
+    }
+
+    #[test]
+    fn test_large_primes_and_composites() {
+        for &p in LARGE_PRIMES.iter() {
+            assert!(is_prob_prime(&big(p)));
+            for &q in LARGE_PRIMES.iter() {
+                let mut pq = big(p);
+                pq.mul_mut(&big(q));
+                assert!(!is_prob_prime(&pq));
+            }
+        }
+        // 2^521 - 1 and 2^607 - 1 are Mersenne primes; 2^523 - 1 is not.
+        for &(e, prime) in &[(521, true), (523, false), (607, true)] {
+            let mut m = big(1);
+            for _ in 0..e {
+                m.mul_ui_mut(2);
+            }
+            m.sub_ui_mut(1);
+            assert_eq!(is_prob_prime(&m), prime, "2^{} - 1", e);
+        }
+    }
+
+    #[test]
+    fn test_agrees_with_gmp_on_random_corpus() {
+        use rug::integer::IsPrime;
+        use rug::rand::RandState;
+        use rug::Integer;
+
+        let gmp_is_prime = |n: &Integer| n.is_probably_prime(25) != IsPrime::No;
+        let random_odd =
+            |rng: &mut RandState, bits: u32| Integer::from(Integer::random_bits(bits, rng)) | 1;
+
+        let mut rng = RandState::new();
+        rng.seed(&Integer::from(0x5eed));
+        let mut primes = 0;
+        for i in 0..2000 {
+            let bits = 64 + (i % 8) * 128;
+            let n = random_odd(&mut rng, bits);
+            let expected = gmp_is_prime(&n);
+            assert_eq!(is_prob_prime(&n), expected, "{}", n);
+            primes += expected as usize;
+
+            // A product of two primes of the same size has no small factor to give it away.
+            if expected {
+                let mut q = random_odd(&mut rng, bits);
+                while !gmp_is_prime(&q) {
+                    q += 2;
+                }
+                assert!(!is_prob_prime(&Integer::from(&n * &q)));
+            }
+        }
+        assert!(primes > 10);
+    }
+}
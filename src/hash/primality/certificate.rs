@@ -0,0 +1,1299 @@
+//! Primality certificates: proofs that a number is prime which can be checked without trusting
+//! whoever produced them, e.g. for `|D|` of a discriminant from `create_discriminant` or for a
+//! `hash_to_prime` output. `prove` builds a certificate and `verify` checks one.
+//!
+//! A certificate is a list of steps, each proving one number prime on the assumption that the
+//! primes it refers to are proven by later steps or are below 2^64, where BPSW is known to be
+//! exact. There are two kinds of step:
+//!
+//! - Pocklington's theorem, used when the part of `n - 1` made of small or provable primes
+//!   exceeds `sqrt(n)`.
+//! - Atkin and Morain's elliptic curve test (ECPP) otherwise. The prover finds curves of known
+//!   order by complex multiplication, from the Hilbert class polynomials of small discriminants;
+//!   the verifier needs none of that and only does curve arithmetic.
+//!
+//! The text format has one step per line, with every number in decimal:
+//!
+//! ```text
+//! pocklington <n> <q_1> <a_1> <q_2> <a_2> ...
+//! ecpp <n> <a> <b> <m> <q> <x> <y>
+//! ```
+use super::big::is_prob_prime;
+use crate::num::{BigNum, DefaultBigNum};
+use rug::float::Constant;
+use rug::integer::Order;
+use rug::{Complex, Float, Integer};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+/// Trial division bound for the factors of `n - 1` and of curve orders.
+const TRIAL_DIVISION_BOUND: u64 = 1 << 16;
+/// Discriminants considered for complex multiplication, by absolute value and by class number.
+/// Smaller class numbers are tried first since their class polynomials are cheaper to use.
+const MAX_CM_DISCRIMINANT: i64 = 20_000;
+const MAX_CLASS_NUMBER: usize = 40;
+
+/// One step of a `Certificate`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Step<B: BigNum = DefaultBigNum> {
+    /// `n` is prime if `F^2 > n`, where `F` is the part of `n - 1` made of the listed primes `q`,
+    /// and each `q` comes with a witness `a` such that `a^(n-1) = 1 (mod n)` and
+    /// `gcd(a^((n-1)/q) - 1, n) = 1`.
+    Pocklington { n: B, factors: Vec<(B, B)> },
+    /// `n`, coprime to 6, is prime if `P = (x, y)` lies on `y^2 = x^3 + ax + b (mod n)`,
+    /// `m P = O` and `(m / q) P != O` for a prime `q > (n^(1/4) + 1)^2`.
+    EllipticCurve {
+        n: B,
+        a: B,
+        b: B,
+        m: B,
+        q: B,
+        x: B,
+        y: B,
+    },
+}
+
+impl<B: BigNum> Step<B> {
+    /// The number this step proves prime.
+    pub fn n(&self) -> &B {
+        match self {
+            Step::Pocklington { n, .. } | Step::EllipticCurve { n, .. } => n,
+        }
+    }
+
+    /// The primes this step relies on.
+    fn dependencies(&self) -> Vec<&B> {
+        match self {
+            Step::Pocklington { factors, .. } => factors.iter().map(|(q, _)| q).collect(),
+            Step::EllipticCurve { q, .. } => vec![q],
+        }
+    }
+}
+
+/// A proof of primality: `steps[0]` proves the number itself, unless it is below 2^64 and the
+/// certificate is empty.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Certificate<B: BigNum = DefaultBigNum> {
+    pub steps: Vec<Step<B>>,
+}
+
+/// Why `verify` rejected a certificate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CertificateError {
+    /// A line of the text format could not be parsed. Lines count from 1.
+    Parse { line: usize },
+    /// The certificate does not prove the number it was checked against.
+    NotProven,
+    /// A step relies on a prime that is neither below 2^64 nor proven by a later step.
+    Unproven { step: usize },
+    /// The conditions of a step do not hold.
+    InvalidStep { step: usize, reason: &'static str },
+}
+
+impl fmt::Display for CertificateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CertificateError::Parse { line } => write!(f, "malformed certificate at line {}", line),
+            CertificateError::NotProven => write!(f, "certificate does not prove the number"),
+            CertificateError::Unproven { step } => {
+                write!(f, "step {} relies on a prime that is not proven", step)
+            }
+            CertificateError::InvalidStep { step, reason } => {
+                write!(f, "step {} is invalid: {}", step, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CertificateError {}
+
+/// Limits on the search `prove_with` does before it gives up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProveParams {
+    /// Most steps tried, counting those dropped when backtracking.
+    pub max_attempts: usize,
+    /// Most steps in a certificate, which bounds the depth of the search.
+    pub max_depth: usize,
+}
+
+impl Default for ProveParams {
+    /// Far more than primes of a few thousand bits need: the default discriminant takes about
+    /// 120 attempts and a chain of as many steps.
+    fn default() -> Self {
+        ProveParams {
+            max_attempts: 20_000,
+            max_depth: 1_000,
+        }
+    }
+}
+
+/// Why `prove` could not produce a certificate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProveError {
+    /// The number is not a probable prime.
+    Composite,
+    /// No chain of steps was found. Not expected for primes of practical size, since every step
+    /// has many curves to choose from.
+    NotFound,
+    /// The search hit a limit of its `ProveParams` before finding a chain.
+    LimitReached,
+}
+
+impl fmt::Display for ProveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProveError::Composite => write!(f, "number is composite"),
+            ProveError::NotFound => write!(f, "no primality certificate found"),
+            ProveError::LimitReached => write!(f, "primality certificate search limit reached"),
+        }
+    }
+}
+
+impl std::error::Error for ProveError {}
+
+/// Checks that `certificate` proves `n` prime.
+pub fn verify<B: BigNum>(n: &B, certificate: &Certificate<B>) -> Result<(), CertificateError> {
+    let mut proven = HashSet::new();
+    for (i, step) in certificate.steps.iter().enumerate().rev() {
+        let dependencies = step.dependencies();
+        if dependencies
+            .iter()
+            .any(|q| !proven.contains(q) && !is_small_prime(*q))
+        {
+            return Err(CertificateError::Unproven { step: i });
+        }
+        verify_step(step).map_err(|reason| CertificateError::InvalidStep { step: i, reason })?;
+        proven.insert(step.n());
+    }
+    if proven.contains(n) || is_small_prime(n) {
+        Ok(())
+    } else {
+        Err(CertificateError::NotProven)
+    }
+}
+
+/// Builds a certificate for the prime `n`, within the default `ProveParams`.
+pub fn prove<B: BigNum>(n: &B) -> Result<Certificate<B>, ProveError> {
+    prove_with(&ProveParams::default(), n)
+}
+
+/// Builds a certificate for the prime `n`, giving up with `ProveError::LimitReached` once the
+/// search exceeds `params`.
+pub fn prove_with<B: BigNum>(params: &ProveParams, n: &B) -> Result<Certificate<B>, ProveError> {
+    if !is_prob_prime(n) {
+        return Err(ProveError::Composite);
+    }
+    let mut prover = Prover::new(params);
+    let mut certificate = Certificate::default();
+    if prover.prove(n, &mut certificate.steps) {
+        Ok(certificate)
+    } else if prover.limit_reached {
+        Err(ProveError::LimitReached)
+    } else {
+        Err(ProveError::NotFound)
+    }
+}
+
+/// Below 2^64 BPSW has no false positives, so it settles primality by itself.
+fn is_small_prime<B: BigNum>(n: &B) -> bool {
+    n.bit_length() <= 64 && is_prob_prime(n)
+}
+
+fn verify_step<B: BigNum>(step: &Step<B>) -> Result<(), &'static str> {
+    match step {
+        Step::Pocklington { n, factors } => verify_pocklington(n, factors),
+        Step::EllipticCurve { n, a, b, m, q, x, y } => {
+            verify_elliptic_curve(n, a, b, m, q, &Point::Affine(x.clone(), y.clone()))
+        }
+    }
+}
+
+fn verify_pocklington<B: BigNum>(n: &B, factors: &[(B, B)]) -> Result<(), &'static str> {
+    if n.cmp_si(3) < 0 {
+        return Err("n is too small");
+    }
+    let mut n_minus_1 = n.clone();
+    n_minus_1.sub_ui_mut(1);
+    let (mut rest, mut f) = (n_minus_1.clone(), B::from(1));
+    let (mut e, mut t) = (B::default(), B::default());
+    for (q, a) in factors {
+        if q.cmp_si(2) < 0 || !divides(q, &rest) {
+            return Err("factor does not divide n - 1");
+        }
+        while divides(q, &rest) {
+            rest.divexact_mut(q);
+            f.mul_mut(q);
+        }
+        t.powm(a, &n_minus_1, n);
+        if !t.is_one() {
+            return Err("witness fails the Fermat test");
+        }
+        e.divexact(&n_minus_1, q);
+        t.powm(a, &e, n);
+        t.sub_ui_mut(1);
+        t.gcd_mut(n);
+        if !t.is_one() {
+            return Err("witness shares a factor with n");
+        }
+    }
+    f.square_mut();
+    if f <= *n {
+        return Err("factored part of n - 1 is too small");
+    }
+    Ok(())
+}
+
+fn verify_elliptic_curve<B: BigNum>(
+    n: &B,
+    a: &B,
+    b: &B,
+    m: &B,
+    q: &B,
+    point: &Point<B>,
+) -> Result<(), &'static str> {
+    let r = n.fdiv_ui(6);
+    if n.cmp_si(5) < 0 || (r != 1 && r != 5) {
+        return Err("n is not coprime to 6");
+    }
+    let curve = Curve { a, b, n };
+    if !curve.is_nonsingular() {
+        return Err("curve is singular");
+    }
+    if !curve.contains(point) {
+        return Err("point is not on the curve");
+    }
+    if q.sgn() <= 0 || m.sgn() <= 0 || !divides(q, m) {
+        return Err("q does not divide m");
+    }
+    // Rejected before the scalar multiplications, whose cost grows with the size of m.
+    if !within_hasse_bound(m, n) {
+        return Err("m exceeds the Hasse bound");
+    }
+    if !exceeds_quartic_bound(q, n) {
+        return Err("q is too small");
+    }
+    let mut k = B::default();
+    k.divexact(m, q);
+    match curve.mul(point, &k) {
+        Some(Point::Affine(..)) => {}
+        Some(Point::Infinity) => return Err("(m / q) P is the identity"),
+        None => return Err("n has a non-trivial factor"),
+    }
+    match curve.mul(point, m) {
+        Some(Point::Infinity) => Ok(()),
+        Some(Point::Affine(..)) => Err("m P is not the identity"),
+        None => Err("n has a non-trivial factor"),
+    }
+}
+
+fn divides<B: BigNum>(d: &B, x: &B) -> bool {
+    let mut r = B::default();
+    r.modulo(x, d);
+    r.is_zero()
+}
+
+/// Whether `m <= n + 1 + 2 floor(sqrt(n)) + 1`, a slightly loose form of the Hasse bound
+/// `m <= n + 1 + 2 sqrt(n)` on the number of points of a curve over `Z/nZ` for prime `n`.
+fn within_hasse_bound<B: BigNum>(m: &B, n: &B) -> bool {
+    let mut bound = n.clone();
+    bound.root_mut(2);
+    bound.mul_ui_mut(2);
+    bound.add_mut(n);
+    bound.add_ui_mut(2);
+    *m <= bound
+}
+
+/// Whether `q > (n^(1/4) + 1)^2`, checked conservatively as `(floor(sqrt(q)) - 1)^4 > n`.
+fn exceeds_quartic_bound<B: BigNum>(q: &B, n: &B) -> bool {
+    let mut s = q.clone();
+    s.root_mut(2);
+    s.sub_ui_mut(1);
+    if s.sgn() <= 0 {
+        return false;
+    }
+    s.square_mut();
+    s.square_mut();
+    s > *n
+}
+
+fn from_i64<B: BigNum>(x: i64) -> B {
+    let mut b = B::default();
+    b.set_si(x);
+    b
+}
+
+/// `x^-1 mod n`, or `None` if `x` is not invertible.
+fn invert<B: BigNum>(x: &B, n: &B) -> Option<B> {
+    let (mut g, mut d, mut e) = (B::default(), B::default(), B::default());
+    g.gcdext(&mut d, &mut e, x, n);
+    if !g.is_one() {
+        return None;
+    }
+    d.modulo_mut(n);
+    Some(d)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Point<B: BigNum> {
+    Infinity,
+    Affine(B, B),
+}
+
+/// `y^2 = x^3 + ax + b` over `Z/nZ`. The arithmetic returns `None` when it would have to invert
+/// a non-unit, which proves `n` composite.
+struct Curve<'a, B: BigNum> {
+    a: &'a B,
+    b: &'a B,
+    n: &'a B,
+}
+
+impl<'a, B: BigNum> Curve<'a, B> {
+    fn is_nonsingular(&self) -> bool {
+        // 4a^3 + 27b^2
+        let (mut d, mut t) = (B::default(), B::default());
+        d.mul(self.a, self.a);
+        d.mul_mut(self.a);
+        d.mul_ui_mut(4);
+        t.mul(self.b, self.b);
+        t.mul_ui_mut(27);
+        d.add_mut(&t);
+        d.gcd_mut(self.n);
+        d.is_one()
+    }
+
+    fn rhs(&self, x: &B) -> B {
+        let mut r = B::default();
+        r.mul(x, x);
+        r.add_mut(self.a);
+        r.mul_mut(x);
+        r.add_mut(self.b);
+        r.modulo_mut(self.n);
+        r
+    }
+
+    fn contains(&self, p: &Point<B>) -> bool {
+        match p {
+            Point::Infinity => true,
+            Point::Affine(x, y) => {
+                let mut y2 = B::default();
+                y2.mul(y, y);
+                y2.modulo_mut(self.n);
+                y2 == self.rhs(x)
+            }
+        }
+    }
+
+    fn add(&self, p: &Point<B>, q: &Point<B>) -> Option<Point<B>> {
+        let n = self.n;
+        let ((x1, y1), (x2, y2)) = match (p, q) {
+            (Point::Infinity, _) => return Some(q.clone()),
+            (_, Point::Infinity) => return Some(p.clone()),
+            (Point::Affine(x1, y1), Point::Affine(x2, y2)) => ((x1, y1), (x2, y2)),
+        };
+        let (mut num, mut den) = (B::default(), B::default());
+        let mut t = B::default();
+        t.sub(x2, x1);
+        t.modulo_mut(n);
+        if t.is_zero() {
+            t.add(y1, y2);
+            t.modulo_mut(n);
+            if t.is_zero() {
+                return Some(Point::Infinity);
+            }
+            t.sub(y1, y2);
+            t.modulo_mut(n);
+            if !t.is_zero() {
+                // Equal x but y neither equal nor opposite only happens modulo a proper factor.
+                return None;
+            }
+            // Tangent: (3 x^2 + a) / 2y
+            num.mul(x1, x1);
+            num.mul_ui_mut(3);
+            num.add_mut(self.a);
+            den.mul_ui(y1, 2);
+        } else {
+            num.sub(y2, y1);
+            den.set(&t);
+        }
+        let mut lambda = invert(&den, n)?;
+        lambda.mul_mut(&num);
+        lambda.modulo_mut(n);
+
+        let (mut x3, mut y3) = (B::default(), B::default());
+        x3.mul(&lambda, &lambda);
+        x3.sub_mut(x1);
+        x3.sub_mut(x2);
+        x3.modulo_mut(n);
+        y3.sub(x1, &x3);
+        y3.mul_mut(&lambda);
+        y3.sub_mut(y1);
+        y3.modulo_mut(n);
+        Some(Point::Affine(x3, y3))
+    }
+
+    /// `k P` for `k >= 0`, by double-and-add.
+    fn mul(&self, p: &Point<B>, k: &B) -> Option<Point<B>> {
+        let mut r = Point::Infinity;
+        for bit in (0..k.bit_length()).rev() {
+            r = self.add(&r, &r)?;
+            if k.tstbit(bit) {
+                r = self.add(&r, p)?;
+            }
+        }
+        Some(r)
+    }
+}
+
+impl<B: BigNum> fmt::Display for Step<B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Step::Pocklington { n, factors } => {
+                write!(f, "pocklington {}", n)?;
+                for (q, a) in factors {
+                    write!(f, " {} {}", q, a)?;
+                }
+                Ok(())
+            }
+            Step::EllipticCurve { n, a, b, m, q, x, y } => {
+                write!(f, "ecpp {} {} {} {} {} {} {}", n, a, b, m, q, x, y)
+            }
+        }
+    }
+}
+
+impl<B: BigNum> fmt::Display for Certificate<B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for step in &self.steps {
+            writeln!(f, "{}", step)?;
+        }
+        Ok(())
+    }
+}
+
+impl<B: BigNum> FromStr for Certificate<B> {
+    type Err = CertificateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut steps = vec![];
+        for (i, line) in s.lines().enumerate() {
+            let mut words = line.split_whitespace();
+            let kind = match words.next() {
+                Some(kind) => kind,
+                None => continue,
+            };
+            let error = CertificateError::Parse { line: i + 1 };
+            let numbers = words
+                .map(parse_decimal)
+                .collect::<Option<Vec<B>>>()
+                .ok_or_else(|| error.clone())?;
+            let step = match (kind, numbers.len()) {
+                ("pocklington", len) if len >= 3 && len % 2 == 1 => {
+                    let mut numbers = numbers.into_iter();
+                    let n = numbers.next().unwrap();
+                    let mut factors = vec![];
+                    while let (Some(q), Some(a)) = (numbers.next(), numbers.next()) {
+                        factors.push((q, a));
+                    }
+                    Step::Pocklington { n, factors }
+                }
+                ("ecpp", 7) => {
+                    let mut numbers = numbers.into_iter();
+                    let mut next = || numbers.next().unwrap();
+                    Step::EllipticCurve {
+                        n: next(),
+                        a: next(),
+                        b: next(),
+                        m: next(),
+                        q: next(),
+                        x: next(),
+                        y: next(),
+                    }
+                }
+                _ => return Err(error),
+            };
+            steps.push(step);
+        }
+        Ok(Certificate { steps })
+    }
+}
+
+/// Parses an optionally negative decimal integer, rejecting anything else.
+fn parse_decimal<B: BigNum>(s: &str) -> Option<B> {
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    if digits.is_empty() || !digits.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    B::from_str(s).ok()
+}
+
+/// A reduced form `(a, b, c)` of a small discriminant.
+type Form = (i64, i64, i64);
+
+struct Prover<B: BigNum> {
+    primes: Vec<u64>,
+    /// Discriminants `D` with their reduced forms, in the order they are tried.
+    discriminants: Vec<(i64, Vec<Form>)>,
+    class_polynomials: HashMap<i64, Vec<B>>,
+    attempts_left: usize,
+    max_depth: usize,
+    /// Set once a limit stops the search, which then unwinds without trying anything else.
+    limit_reached: bool,
+}
+
+impl<B: BigNum> Prover<B> {
+    fn new(params: &ProveParams) -> Self {
+        Prover {
+            primes: primes_below(TRIAL_DIVISION_BOUND),
+            discriminants: cm_discriminants(MAX_CM_DISCRIMINANT, MAX_CLASS_NUMBER),
+            class_polynomials: HashMap::new(),
+            attempts_left: params.max_attempts,
+            max_depth: params.max_depth,
+            limit_reached: false,
+        }
+    }
+
+    /// Pushes `step` if the limits allow another one.
+    fn try_push(&mut self, step: Step<B>, steps: &mut Vec<Step<B>>) -> bool {
+        if self.attempts_left == 0 || steps.len() >= self.max_depth {
+            self.limit_reached = true;
+            return false;
+        }
+        self.attempts_left -= 1;
+        steps.push(step);
+        true
+    }
+
+    /// Appends steps proving the probable prime `n` to `steps`, backtracking over the choices
+    /// for later steps if some prime they lead to cannot be proven, until a limit is reached.
+    fn prove(&mut self, n: &B, steps: &mut Vec<Step<B>>) -> bool {
+        if n.bit_length() <= 64 {
+            return true;
+        }
+        let mark = steps.len();
+        if let Some((factors, cofactor)) = self.pocklington(n) {
+            let step = Step::Pocklington {
+                n: n.clone(),
+                factors,
+            };
+            if !self.try_push(step, steps) {
+                return false;
+            }
+            match cofactor {
+                None => return true,
+                Some(r) => {
+                    if self.prove(&r, steps) {
+                        return true;
+                    }
+                    steps.truncate(mark);
+                    if self.limit_reached {
+                        return false;
+                    }
+                }
+            }
+        }
+        for i in 0..self.discriminants.len() {
+            for (step, q) in self.elliptic_curve_steps(n, i) {
+                if !self.try_push(step, steps) {
+                    return false;
+                }
+                if self.prove(&q, steps) {
+                    return true;
+                }
+                steps.truncate(mark);
+                if self.limit_reached {
+                    return false;
+                }
+            }
+        }
+        false
+    }
+
+    /// Factors and witnesses for a Pocklington step, if `n - 1` is a product of small primes and
+    /// at most one large probable prime, which is returned as well and still needs a proof.
+    #[allow(clippy::type_complexity)]
+    fn pocklington(&self, n: &B) -> Option<(Vec<(B, B)>, Option<B>)> {
+        let mut n_minus_1 = n.clone();
+        n_minus_1.sub_ui_mut(1);
+        let (mut rest, mut f) = (n_minus_1.clone(), B::from(1));
+        let mut primes = vec![];
+        for &p in &self.primes {
+            if rest.fdiv_ui(p) == 0 {
+                primes.push(B::from(p));
+                while rest.fdiv_ui(p) == 0 {
+                    rest.fdiv_q_ui_mut(p);
+                    f.mul_ui_mut(p);
+                }
+            }
+        }
+        let mut f2 = f.clone();
+        f2.square_mut();
+        let cofactor = if f2 > *n {
+            None
+        } else if rest.cmp_si(1) > 0 && is_prob_prime(&rest) {
+            primes.push(rest.clone());
+            Some(rest)
+        } else {
+            return None;
+        };
+        let factors = primes
+            .into_iter()
+            .map(|q| pocklington_witness(n, &q).map(|a| (q, a)))
+            .collect::<Option<Vec<_>>>()?;
+        Some((factors, cofactor))
+    }
+
+    /// The ECPP steps for `n` from the curves with complex multiplication by the `i`th
+    /// discriminant, each with the prime it reduces `n` to. Usually none.
+    fn elliptic_curve_steps(&mut self, n: &B, i: usize) -> Vec<(Step<B>, B)> {
+        let d = self.discriminants[i].0;
+        let mut steps = vec![];
+        if from_i64::<B>(d).jacobi(n) != 1 {
+            return steps;
+        }
+        let u = match cornacchia(d, n) {
+            Some(u) => u,
+            None => return steps,
+        };
+        // Curves with CM by the order of discriminant `D` have `n + 1 - u` or `n + 1 + u` points.
+        let mut j = None;
+        for &sign in &[-1, 1] {
+            let mut m = n.clone();
+            m.add_ui_mut(1);
+            if sign < 0 {
+                m.sub_mut(&u);
+            } else {
+                m.add_mut(&u);
+            }
+            let q = self.remove_small_factors(&m);
+            if q >= *n || !exceeds_quartic_bound(&q, n) || !is_prob_prime(&q) {
+                continue;
+            }
+            if j.is_none() {
+                j = self.class_polynomial_root(i, n);
+            }
+            let j = match &j {
+                Some(j) => j,
+                None => return steps,
+            };
+            if let Some((a, b, x, y)) = curve_with_order(n, j, &m, &q) {
+                let step = Step::EllipticCurve {
+                    n: n.clone(),
+                    a,
+                    b,
+                    m,
+                    q: q.clone(),
+                    x,
+                    y,
+                };
+                steps.push((step, q));
+            }
+        }
+        steps
+    }
+
+    fn remove_small_factors(&self, m: &B) -> B {
+        let mut r = m.clone();
+        for &p in &self.primes {
+            while r.fdiv_ui(p) == 0 {
+                r.fdiv_q_ui_mut(p);
+            }
+        }
+        r
+    }
+
+    /// A root mod `n` of the Hilbert class polynomial of the `i`th discriminant: the
+    /// j-invariant of a curve with complex multiplication by that order.
+    fn class_polynomial_root(&mut self, i: usize, n: &B) -> Option<B> {
+        let (d, forms) = &self.discriminants[i];
+        let h = self
+            .class_polynomials
+            .entry(*d)
+            .or_insert_with(|| hilbert_class_polynomial(*d, forms));
+        let j = polynomial_root(h, n)?;
+        // j = 0 and j = 1728 have extra automorphisms and other twists; skip them.
+        if j.is_zero() || j.cmp_si(1728) == 0 {
+            return None;
+        }
+        Some(j)
+    }
+}
+
+fn pocklington_witness<B: BigNum>(n: &B, q: &B) -> Option<B> {
+    let mut n_minus_1 = n.clone();
+    n_minus_1.sub_ui_mut(1);
+    let mut e = B::default();
+    e.divexact(&n_minus_1, q);
+    let mut t = B::default();
+    for a in 2..1000 {
+        let a = B::from(a);
+        t.powm(&a, &n_minus_1, n);
+        if !t.is_one() {
+            return None;
+        }
+        t.powm(&a, &e, n);
+        t.sub_ui_mut(1);
+        t.gcd_mut(n);
+        if t.is_one() {
+            return Some(a);
+        }
+    }
+    None
+}
+
+/// `u` with `4n = u^2 + |D| v^2` for the prime `n`, by Cornacchia's algorithm, if it exists.
+fn cornacchia<B: BigNum>(d: i64, n: &B) -> Option<B> {
+    let mut d_mod_n = from_i64::<B>(d);
+    d_mod_n.modulo_mut(n);
+    let mut b = B::default();
+    if !b.sqrt_mod_prime(&d_mod_n, n) {
+        return None;
+    }
+    if b.odd() != (d % 2 != 0) {
+        let t = b.clone();
+        b.sub(n, &t);
+    }
+    let mut four_n = n.clone();
+    four_n.mul_ui_mut(4);
+    let mut a = n.clone();
+    a.mul_ui_mut(2);
+    let mut limit = four_n.clone();
+    limit.root_mut(2);
+    let mut r = B::default();
+    while b > limit {
+        r.modulo(&a, &b);
+        a.swap(&mut b);
+        b.swap(&mut r);
+    }
+    let mut c = four_n;
+    c.submul(&b, &b);
+    let abs_d = B::from(d.unsigned_abs());
+    if !divides(&abs_d, &c) {
+        return None;
+    }
+    c.divexact_mut(&abs_d);
+    if !c.is_perfect_square() {
+        return None;
+    }
+    Some(b)
+}
+
+/// A curve with j-invariant `j` and `m` points mod the prime `n`, with a point `P` such that
+/// `(m / q) P != O`, as `(a, b, x, y)`. Either the curve `y^2 = x^3 + 3kx + 2k` with
+/// `k = j / (1728 - j)` or its quadratic twist has the right order.
+fn curve_with_order<B: BigNum>(n: &B, j: &B, m: &B, q: &B) -> Option<(B, B, B, B)> {
+    let mut k = from_i64::<B>(1728);
+    k.sub_mut(j);
+    k.modulo_mut(n);
+    let mut k = invert(&k, n)?;
+    k.mul_mut(j);
+    let (mut a, mut b) = (B::default(), B::default());
+    a.mul_ui(&k, 3);
+    a.modulo_mut(n);
+    b.mul_ui(&k, 2);
+    b.modulo_mut(n);
+
+    let mut c = B::from(2);
+    while c.jacobi(n) != -1 {
+        c.add_ui_mut(1);
+    }
+    let mut cofactor = B::default();
+    cofactor.divexact(m, q);
+    for twist in 0..2 {
+        if twist == 1 {
+            // a c^2, b c^3
+            a.mul_mut(&c);
+            a.mul_mut(&c);
+            a.modulo_mut(n);
+            b.mul_mut(&c);
+            b.mul_mut(&c);
+            b.mul_mut(&c);
+            b.modulo_mut(n);
+        }
+        let curve = Curve { a: &a, b: &b, n };
+        let mut x = B::default();
+        for _ in 0..20 {
+            x.add_ui_mut(1);
+            let rhs = curve.rhs(&x);
+            let mut y = B::default();
+            if rhs.is_zero() || !y.sqrt_mod_prime(&rhs, n) {
+                continue;
+            }
+            let p = Point::Affine(x.clone(), y.clone());
+            if curve.mul(&p, m)? != Point::Infinity {
+                break;
+            }
+            if curve.mul(&p, &cofactor)? != Point::Infinity {
+                return Some((a, b, x, y));
+            }
+        }
+    }
+    None
+}
+
+fn primes_below(bound: u64) -> Vec<u64> {
+    let mut composite = vec![false; bound as usize];
+    let mut primes = vec![];
+    for i in 2..bound as usize {
+        if !composite[i] {
+            primes.push(i as u64);
+            for j in (i * i..bound as usize).step_by(i) {
+                composite[j] = true;
+            }
+        }
+    }
+    primes
+}
+
+fn gcd(mut a: i64, mut b: i64) -> i64 {
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    a.abs()
+}
+
+/// The discriminants `-max_d <= D < -4` with class number at most `max_h`, each with its
+/// primitive reduced forms, ordered by class number and then by `|D|`.
+fn cm_discriminants(max_d: i64, max_h: usize) -> Vec<(i64, Vec<Form>)> {
+    let mut forms: BTreeMap<i64, Vec<Form>> = BTreeMap::new();
+    let mut a = 1;
+    while 3 * a * a <= max_d {
+        for b in -a + 1..=a {
+            let mut c = a;
+            while 4 * a * c - b * b <= max_d {
+                // Reduced: |b| <= a <= c, with b >= 0 if a = c.
+                if !(b < 0 && a == c) && gcd(gcd(a, b), c) == 1 {
+                    forms.entry(b * b - 4 * a * c).or_default().push((a, b, c));
+                }
+                c += 1;
+            }
+        }
+        a += 1;
+    }
+    let mut discriminants: Vec<_> = forms
+        .into_iter()
+        .filter(|(d, forms)| *d < -4 && forms.len() <= max_h)
+        .collect();
+    discriminants.sort_by_key(|(d, forms)| (forms.len(), -d));
+    discriminants
+}
+
+/// The Hilbert class polynomial of `D`, monic with coefficients from the constant term up, as
+/// the product of `x - j(tau)` over the reduced forms, computed in floating point and rounded.
+fn hilbert_class_polynomial<B: BigNum>(d: i64, forms: &[Form]) -> Vec<B> {
+    let sqrt_d = (-d as f64).sqrt();
+    // |j(tau)| is about exp(pi sqrt|D| / a); the coefficients are bounded by the product.
+    let bits: f64 = forms
+        .iter()
+        .map(|&(a, _, _)| std::f64::consts::PI * sqrt_d / a as f64 / std::f64::consts::LN_2 + 12.0)
+        .sum();
+    let mut prec = bits as u32 + 64;
+    loop {
+        let mut poly = vec![Complex::with_val(prec, 1)];
+        for &(a, b, _) in forms {
+            let j = j_invariant(a, b, d, prec);
+            // poly *= (x - j)
+            poly.insert(0, Complex::with_val(prec, 0));
+            for k in 0..poly.len() - 1 {
+                let t = Complex::with_val(prec, &poly[k + 1] * &j);
+                poly[k] -= t;
+            }
+        }
+        let coefficients = poly
+            .iter()
+            .map(|c| {
+                let re = c.real().to_integer()?;
+                let error = Float::with_val(prec, c.real() - &re).abs();
+                if error < 0.25 && c.imag().clone().abs() < 0.25 {
+                    Some(integer_to_bignum(&re))
+                } else {
+                    None
+                }
+            })
+            .collect::<Option<Vec<B>>>();
+        match coefficients {
+            Some(coefficients) => return coefficients,
+            None => prec *= 2,
+        }
+    }
+}
+
+/// `j((-b + sqrt(D)) / 2a)`, from `j = (1 + 256 t)^3 / t` with `t = (eta(2 tau) / eta(tau))^24`.
+fn j_invariant(a: i64, b: i64, d: i64, prec: u32) -> Complex {
+    let pi = Float::with_val(prec, Constant::Pi);
+    let re = Float::with_val(prec, -b) / (2 * a) * &pi;
+    let im = Float::with_val(prec, -d).sqrt() / (2 * a) * &pi;
+    // q = exp(2 pi i tau)
+    let q = Complex::with_val(prec, (Float::with_val(prec, -2) * im, re * 2)).exp();
+    // log2 |1/q| = pi sqrt|D| / (a ln 2)
+    let log2_inv_q = std::f64::consts::PI * (-d as f64).sqrt() / a as f64 / std::f64::consts::LN_2;
+    let q2 = Complex::with_val(prec, q.clone().square());
+    let ratio = eta_product(&q2, 2.0 * log2_inv_q, prec) / eta_product(&q, log2_inv_q, prec);
+    let r2 = ratio.square();
+    let r4 = Complex::with_val(prec, r2.clone().square());
+    let r8 = Complex::with_val(prec, r4.clone().square());
+    let r16 = r8.clone().square();
+    let t = r16 * r8 * q;
+    let u = Complex::with_val(prec, &t * 256u32) + 1u32;
+    let u3 = Complex::with_val(prec, u.clone().square()) * u;
+    u3 / t
+}
+
+/// `sum_k (-1)^k q^(k(3k-1)/2)` over all integers `k`, which is `eta(tau) / q^(1/24)`.
+fn eta_product(q: &Complex, log2_inv_q: f64, prec: u32) -> Complex {
+    let mut sum = Complex::with_val(prec, 1);
+    // q^(k(3k-1)/2) and q^k; the next exponents are k(3k+1)/2 and (k+1)(3k+2)/2.
+    let mut minus = q.clone();
+    let mut qk = q.clone();
+    let mut k = 1i64;
+    while ((k * (3 * k - 1) / 2) as f64) * log2_inv_q < f64::from(prec) + 16.0 {
+        let plus = Complex::with_val(prec, &minus * &qk);
+        let pair = Complex::with_val(prec, &minus + &plus);
+        if k % 2 == 1 {
+            sum -= pair;
+        } else {
+            sum += pair;
+        }
+        let qk2 = Complex::with_val(prec, qk.clone().square());
+        minus = plus * qk2 * q;
+        qk *= q;
+        k += 1;
+    }
+    sum
+}
+
+fn integer_to_bignum<B: BigNum>(x: &Integer) -> B {
+    let mut b = B::from_be_bytes(&x.to_digits::<u8>(Order::Msf));
+    if *x < 0 {
+        b.neg_mut();
+    }
+    b
+}
+
+/// A root mod the prime `n` of the monic polynomial `f`, which must split into distinct linear
+/// factors mod `n`, as class polynomials do when `n` is a norm from their order. Each round of
+/// Cantor-Zassenhaus keeps the smaller proper factor, so the degree at least halves.
+fn polynomial_root<B: BigNum>(f: &[B], n: &B) -> Option<B> {
+    let mut g: Vec<B> = f
+        .iter()
+        .map(|c| {
+            let mut c = c.clone();
+            c.modulo_mut(n);
+            c
+        })
+        .collect();
+    poly_trim(&mut g);
+    let mut half = n.clone();
+    half.sub_ui_mut(1);
+    half.fdiv_q_ui_mut(2);
+    let mut delta = 0u64;
+    while g.len() > 2 {
+        // (x + delta)^((n-1)/2) is 1 at the roots r with r + delta a square, -1 at the others.
+        delta += 1;
+        if delta > 64 {
+            return None;
+        }
+        let t = poly_powmod(&[B::from(delta), B::from(1)], &half, &g, n);
+        for &shift in &[-1, 1] {
+            let mut t = t.clone();
+            if t.is_empty() {
+                t.push(B::default());
+            }
+            if shift < 0 {
+                t[0].sub_ui_mut(1);
+            } else {
+                t[0].add_ui_mut(1);
+            }
+            t[0].modulo_mut(n);
+            poly_trim(&mut t);
+            let h = poly_gcd(g.clone(), t, n);
+            if h.len() > 1 && 2 * (h.len() - 1) < g.len() {
+                g = h;
+                break;
+            }
+        }
+    }
+    if g.len() != 2 {
+        return None;
+    }
+    let mut root = B::default();
+    root.neg(&g[0]);
+    root.modulo_mut(n);
+    Some(root)
+}
+
+fn poly_trim<B: BigNum>(f: &mut Vec<B>) {
+    while matches!(f.last(), Some(c) if c.is_zero()) {
+        f.pop();
+    }
+}
+
+/// `a mod f` for monic `f` with coefficients in `[0, n)`. The coefficients of `a` may be
+/// unreduced; only each leading coefficient is reduced before it is used, and the rest at the end.
+fn poly_rem<B: BigNum>(a: &[B], f: &[B], n: &B) -> Vec<B> {
+    let d = f.len() - 1;
+    let mut r = a.to_vec();
+    while r.len() > d {
+        let mut lead = r.pop().unwrap();
+        lead.modulo_mut(n);
+        if !lead.is_zero() {
+            let shift = r.len() - d;
+            for (i, c) in f[..d].iter().enumerate() {
+                r[shift + i].submul(&lead, c);
+            }
+        }
+    }
+    for c in r.iter_mut() {
+        c.modulo_mut(n);
+    }
+    poly_trim(&mut r);
+    r
+}
+
+fn poly_mulmod<B: BigNum>(a: &[B], b: &[B], f: &[B], n: &B) -> Vec<B> {
+    if a.is_empty() || b.is_empty() {
+        return vec![];
+    }
+    let mut r = vec![B::default(); a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            r[i + j].add_mul(x, y);
+        }
+    }
+    poly_rem(&r, f, n)
+}
+
+fn poly_powmod<B: BigNum>(base: &[B], e: &B, f: &[B], n: &B) -> Vec<B> {
+    let base = poly_rem(base, f, n);
+    let mut r = vec![B::from(1)];
+    for bit in (0..e.bit_length()).rev() {
+        r = poly_mulmod(&r, &r, f, n);
+        if e.tstbit(bit) {
+            r = poly_mulmod(&r, &base, f, n);
+        }
+    }
+    r
+}
+
+fn poly_monic<B: BigNum>(mut f: Vec<B>, n: &B) -> Vec<B> {
+    if let Some(inv) = f.last().and_then(|lead| invert(lead, n)) {
+        for c in f.iter_mut() {
+            c.mul_mut(&inv);
+            c.modulo_mut(n);
+        }
+    }
+    f
+}
+
+/// The monic gcd of `a` and `b`.
+fn poly_gcd<B: BigNum>(mut a: Vec<B>, mut b: Vec<B>, n: &B) -> Vec<B> {
+    while !b.is_empty() {
+        b = poly_monic(b, n);
+        let r = poly_rem(&a, &b, n);
+        a = b;
+        b = r;
+    }
+    poly_monic(a, n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::{hash_to_prime, hash_to_prime_with, HashToPrimeParams};
+    use rug::Integer;
+
+    fn big(s: &str) -> DefaultBigNum {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_hilbert_class_polynomials() {
+        let discriminants = cm_discriminants(100, 3);
+        let find = |d: i64| &discriminants.iter().find(|(e, _)| *e == d).unwrap().1;
+        assert_eq!(find(-7).len(), 1);
+        assert_eq!(find(-15).len(), 2);
+        assert_eq!(find(-23).len(), 3);
+
+        let h: Vec<DefaultBigNum> = hilbert_class_polynomial(-7, find(-7));
+        assert_eq!(h, vec![big("3375"), big("1")]);
+        let h: Vec<DefaultBigNum> = hilbert_class_polynomial(-15, find(-15));
+        assert_eq!(h, vec![big("-121287375"), big("191025"), big("1")]);
+        let h: Vec<DefaultBigNum> = hilbert_class_polynomial(-23, find(-23));
+        assert_eq!(
+            h,
+            vec![big("12771880859375"), big("-5151296875"), big("3491750"), big("1")]
+        );
+    }
+
+    #[test]
+    fn test_prove_and_verify() {
+        // Below 2^64 no steps are needed.
+        let small = big("18446744073709551557");
+        let certificate = prove(&small).unwrap();
+        assert!(certificate.steps.is_empty());
+        assert_eq!(verify(&small, &certificate), Ok(()));
+
+        // A prime k 2^128 + 1 needs nothing but the factor 2 of n - 1.
+        let mut n = big("340282366920938463463374607431768211456");
+        n.add_ui_mut(1);
+        while !is_prob_prime(&n) {
+            n.add_mut(&big("340282366920938463463374607431768211456"));
+        }
+        let certificate = prove(&n).unwrap();
+        assert_eq!(certificate.steps.len(), 1);
+        assert!(matches!(certificate.steps[0], Step::Pocklington { .. }));
+        assert_eq!(verify(&n, &certificate), Ok(()));
+
+        // 2^127 - 1 has too few small factors in n - 1 and takes elliptic curve steps.
+        let m127 = big("170141183460469231731687303715884105727");
+        let certificate = prove(&m127).unwrap();
+        assert!(matches!(certificate.steps[0], Step::EllipticCurve { .. }));
+        assert_eq!(verify(&m127, &certificate), Ok(()));
+
+        // A multiple of m is still divisible by q, but is past the Hasse bound.
+        let mut inflated = certificate.clone();
+        if let Step::EllipticCurve { m, .. } = &mut inflated.steps[0] {
+            m.mul_mut(&big("340282366920938463463374607431768211456"));
+        }
+        assert_eq!(
+            verify(&m127, &inflated),
+            Err(CertificateError::InvalidStep {
+                step: 0,
+                reason: "m exceeds the Hasse bound"
+            })
+        );
+
+        for bits in &[128, 256, 384] {
+            let p = hash_to_prime_with(&HashToPrimeParams::new(*bits, b"certificate"), b"test");
+            let certificate = prove(&p).unwrap();
+            assert_eq!(verify(&p, &certificate), Ok(()), "{} bits", bits);
+        }
+
+        let composite = big("340282366920938463463374607431768211457"); // 2^128 + 1
+        assert_eq!(prove(&composite), Err(ProveError::Composite));
+    }
+
+    #[test]
+    fn test_rejects_bad_certificates() {
+        let p = hash_to_prime(b"certificate");
+        let certificate = prove(&p).unwrap();
+        assert!(!certificate.steps.is_empty());
+        assert_eq!(verify(&p, &certificate), Ok(()));
+
+        let other = hash_to_prime(b"other");
+        assert_eq!(
+            verify(&other, &certificate),
+            Err(CertificateError::NotProven)
+        );
+
+        // Dropping the last step leaves its prime unproven, unless it is below 2^64.
+        let mut truncated = certificate.clone();
+        let last = truncated.steps.pop().unwrap();
+        if last.n().bit_length() > 64 {
+            assert!(verify(&p, &truncated).is_err());
+        }
+
+        // Changing the prime the first step proves, or doubling a prime it relies on, breaks it.
+        // Other numbers, such as Pocklington witnesses, may have valid alternatives.
+        let text = certificate.to_string();
+        let first = text.lines().next().unwrap();
+        let words: Vec<&str> = first.split_whitespace().collect();
+        let primes: Vec<usize> = match words[0] {
+            "pocklington" => (2..words.len()).step_by(2).collect(),
+            _ => vec![5],
+        };
+        for &i in [1].iter().chain(primes.iter()) {
+            let mut changed = words.clone();
+            let word = words[i].parse::<Integer>().unwrap();
+            let tampered = if i == 1 { word + 2u32 } else { word * 2u32 }.to_string();
+            changed[i] = &tampered;
+            let mut tampered = changed.join(" ");
+            tampered.push('\n');
+            tampered.push_str(&text[first.len() + 1..]);
+            let tampered: Certificate<Integer> = tampered.parse().unwrap();
+            assert!(verify(&p, &tampered).is_err(), "word {}", i);
+        }
+    }
+
+    #[test]
+    fn test_prove_limits() {
+        let m127 = big("170141183460469231731687303715884105727");
+        let no_attempts = ProveParams {
+            max_attempts: 0,
+            ..ProveParams::default()
+        };
+        assert_eq!(
+            prove_with(&no_attempts, &m127),
+            Err(ProveError::LimitReached)
+        );
+        let no_depth = ProveParams {
+            max_depth: 0,
+            ..ProveParams::default()
+        };
+        assert_eq!(prove_with(&no_depth, &m127), Err(ProveError::LimitReached));
+        // Primes below 2^64 need no steps, so no limit applies.
+        let small = big("18446744073709551557");
+        assert!(prove_with(&no_attempts, &small).unwrap().steps.is_empty());
+    }
+
+    #[test]
+    fn test_text_format() {
+        let p = hash_to_prime_with(&HashToPrimeParams::new(192, b"certificate"), b"format");
+        let certificate = prove(&p).unwrap();
+        let text = certificate.to_string();
+        assert_eq!(text.parse::<Certificate<Integer>>(), Ok(certificate));
+
+        assert_eq!(
+            "ecpp 1 2 3".parse::<Certificate>(),
+            Err(CertificateError::Parse { line: 1 })
+        );
+        assert_eq!(
+            "pocklington 7 2 3\n\npocklington 7 2 x".parse::<Certificate>(),
+            Err(CertificateError::Parse { line: 3 })
+        );
+        assert!("".parse::<Certificate>().unwrap().steps.is_empty());
+    }
+
+    #[test]
+    fn test_forged_steps() {
+        // 2^64 + 1 = 274177 * 67280421310721
+        let n = big("18446744073709551617");
+        let forged = Certificate {
+            steps: vec![Step::Pocklington {
+                n: n.clone(),
+                factors: vec![(big("2"), big("3"))],
+            }],
+        };
+        assert!(matches!(
+            verify(&n, &forged),
+            Err(CertificateError::InvalidStep { step: 0, .. })
+        ));
+    }
+
+    // Made by `test_certificate_for_default_discriminant`.
+    #[test]
+    fn test_stored_certificate_for_default_discriminant() {
+        let mut n = crate::CLASS_GROUP_DISCRIMINANT.clone();
+        n.neg_mut();
+        let certificate: Certificate = include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/default_discriminant.cert"
+        ))
+        .parse()
+        .unwrap();
+        assert_eq!(certificate.steps[0].n(), &n);
+        assert_eq!(verify(&n, &certificate), Ok(()));
+    }
+
+    // Proving a 2048-bit prime takes minutes even in release builds.
+    #[test]
+    #[ignore]
+    fn test_certificate_for_default_discriminant() {
+        let mut n = crate::CLASS_GROUP_DISCRIMINANT.clone();
+        n.neg_mut();
+        let certificate = prove(&n).unwrap();
+        assert_eq!(verify(&n, &certificate), Ok(()));
+    }
+}
@@ -1,7 +1,9 @@
-//! Primality testing for U256 inputs. Use `is_prob_prime` unless you have a specific reason to use
-//! a lower-level test.
+//! Primality testing for U256 inputs, in `big` for integers of any size, and primality proofs in
+//! `certificate`. Use `is_prob_prime` unless you have a specific reason to use a lower-level test.
 use crate::uint::{u256, u512, U256};
 
+pub mod big;
+pub mod certificate;
 mod constants;
 use constants::{D_VALUES, SMALL_PRIMES};
 
@@ -39,13 +41,12 @@ pub fn passes_miller_rabin_base_2(n: &U256) -> bool {
     false
 }
 
-/// Strong Lucas probable prime test (NOT the more common Lucas primality test which requires
+/// Lucas probable prime test (NOT the more common Lucas primality test which requires
 /// factorization of `n-1`). Selects parameters `d`, `p`, `q` according to Selfridge's method.
 /// Cf. [Lucas pseudoprime](https://en.wikipedia.org/wiki/Lucas_pseudoprime) on Wikipedia
-/// If `n` passes, it is either prime or a "strong" Lucas pseudoprime. (The precise meaning of
-/// "strong" is not fixed in the literature.) Procedure can be further strengthened by implementing
-/// more tests in section 6 of [Baillie & Wagstaff 1980], but for now this is TODO.
-/// Filters perfect squares as part of `choose_d`.
+/// Checks `U_{n+1} = 0` together with the `V_{n+1} = 2q` and Euler `q^((n+1)/2)` congruences from
+/// section 6 of [Baillie & Wagstaff 1980]. The strong, extra strong and fully enhanced variants
+/// are in `big::LucasTest`. Filters perfect squares as part of `choose_d`.
 pub fn passes_lucas(n: &U256) -> bool {
     let d_ = choose_d(n);
     if d_.is_err() {
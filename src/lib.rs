@@ -13,19 +13,48 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+#[cfg(feature = "gmp")]
 #[macro_use]
 extern crate lazy_static;
 
 mod group;
-pub use group::{create_discriminant, ClassElem, ClassGroup};
+#[cfg(feature = "gmp")]
+pub use group::{
+    create_discriminant, create_discriminant_parallel, create_discriminant_with_proof,
+    verify_discriminant, DiscriminantParams, DiscriminantProof, Expansion, CLASS_GROUP_DISCRIMINANT,
+};
+#[cfg(feature = "gmp")]
+pub use group::{
+    create_discriminant_for, validate_discriminant, DiscriminantError, DiscriminantPolicy,
+    ResidueClass, SecurityLevel,
+};
+pub use group::{
+    discriminant_fingerprint, ClassCtx, ClassElem, ClassGroup, ClassGroupOf, ClassGroupStructure,
+    ClassNumberError, DecodeError, DiscreteLog, DiscreteLogError, ElementError, GroupId,
+    GroupMismatch, NonMaximalOrder, OrderError, PrimeFormError, MAX_SMALL_ORDER_BOUND,
+    MAX_STRUCTURE_BITS,
+};
 
 mod num;
+#[cfg(feature = "gmp")]
 pub use num::Mpz;
+pub use num::{BigNum, DefaultBigNum};
 
+#[cfg(feature = "gmp")]
 pub mod uint;
 
+#[cfg(feature = "gmp")]
+pub mod cl;
+
+#[cfg(feature = "gmp")]
 mod hash;
-pub use hash::hash_to_prime;
+#[cfg(feature = "gmp")]
+pub use hash::primality;
+#[cfg(feature = "gmp")]
+pub use hash::{
+    hash_to_prime, hash_to_prime_with, hash_to_prime_with_nonce, verify_hash_to_prime,
+    verify_hash_to_prime_unchecked, HashToPrimeParams,
+};
 
 // Get a tuple of mutable reference from a tuple.
 #[macro_export]
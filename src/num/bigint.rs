@@ -0,0 +1,310 @@
+//! Pure-Rust `BigNum` backend on top of `num-bigint`, for targets where GMP is not available.
+//!
+//! The in-place methods allocate a fresh `BigInt` for every result, so this backend is markedly
+//! slower than the GMP ones; it exists for portability rather than speed.
+use super::BigNum;
+use num_bigint::{BigInt, Sign};
+use std::cmp::Ordering;
+
+fn floor_div_rem(x: &BigInt, y: &BigInt) -> (BigInt, BigInt) {
+    let (mut q, mut r) = (x / y, x % y);
+    if r.sign() != Sign::NoSign && (r.sign() == Sign::Minus) != (y.sign() == Sign::Minus) {
+        q -= 1u32;
+        r += y;
+    }
+    (q, r)
+}
+
+fn ordering_to_i32(o: Ordering) -> i32 {
+    match o {
+        Ordering::Less => -1,
+        Ordering::Equal => 0,
+        Ordering::Greater => 1,
+    }
+}
+
+impl BigNum for BigInt {
+    #[inline]
+    fn abs(&mut self, x: &Self) {
+        *self = BigInt::from(x.magnitude().clone());
+    }
+
+    #[inline]
+    fn abs_mut(&mut self) {
+        if self.sign() == Sign::Minus {
+            *self = -&*self;
+        }
+    }
+
+    #[inline]
+    fn add(&mut self, x: &Self, y: &Self) {
+        *self = x + y;
+    }
+
+    #[inline]
+    fn add_mut(&mut self, x: &Self) {
+        *self += x;
+    }
+
+    #[inline]
+    fn add_ui_mut(&mut self, x: u64) {
+        *self += x;
+    }
+
+    #[inline]
+    fn add_mul(&mut self, x: &Self, y: &Self) {
+        *self += x * y;
+    }
+
+    #[inline]
+    fn sub(&mut self, x: &Self, y: &Self) {
+        *self = x - y;
+    }
+
+    #[inline]
+    fn sub_mut(&mut self, x: &Self) {
+        *self -= x;
+    }
+
+    #[inline]
+    fn sub_ui_mut(&mut self, x: u64) {
+        *self -= x;
+    }
+
+    #[inline]
+    fn submul(&mut self, x: &Self, y: &Self) {
+        *self -= x * y;
+    }
+
+    #[inline]
+    fn mul(&mut self, x: &Self, y: &Self) {
+        *self = x * y;
+    }
+
+    #[inline]
+    fn mul_mut(&mut self, x: &Self) {
+        *self *= x;
+    }
+
+    #[inline]
+    fn mul_ui(&mut self, x: &Self, val: u64) {
+        *self = x * val;
+    }
+
+    #[inline]
+    fn mul_ui_mut(&mut self, val: u64) {
+        *self *= val;
+    }
+
+    #[inline]
+    fn mul_si(&mut self, x: &Self, val: i64) {
+        *self = x * val;
+    }
+
+    #[inline]
+    fn square_mut(&mut self) {
+        *self = &*self * &*self;
+    }
+
+    #[inline]
+    fn neg(&mut self, x: &Self) {
+        *self = -x;
+    }
+
+    #[inline]
+    fn neg_mut(&mut self) {
+        *self = -&*self;
+    }
+
+    #[inline]
+    fn cmpabs(&self, other: &Self) -> i32 {
+        ordering_to_i32(self.magnitude().cmp(other.magnitude()))
+    }
+
+    #[inline]
+    fn cmp_si(&self, val: i64) -> i32 {
+        ordering_to_i32(self.cmp(&BigInt::from(val)))
+    }
+
+    #[inline]
+    fn sgn(&self) -> i32 {
+        match self.sign() {
+            Sign::Minus => -1,
+            Sign::NoSign => 0,
+            Sign::Plus => 1,
+        }
+    }
+
+    #[inline]
+    fn fdiv_q(&mut self, x: &Self, y: &Self) {
+        *self = floor_div_rem(x, y).0;
+    }
+
+    #[inline]
+    fn fdiv_q_mut(&mut self, x: &Self) {
+        *self = floor_div_rem(self, x).0;
+    }
+
+    #[inline]
+    fn fdiv_r(&mut self, x: &Self, y: &Self) {
+        *self = floor_div_rem(x, y).1;
+    }
+
+    #[inline]
+    fn fdiv_r_mut(&mut self, x: &Self) {
+        *self = floor_div_rem(self, x).1;
+    }
+
+    #[inline]
+    fn fdiv_qr(&mut self, r: &mut Self, x: &Self, y: &Self) {
+        let (q, rem) = floor_div_rem(x, y);
+        *self = q;
+        *r = rem;
+    }
+
+    #[inline]
+    fn fdiv_q_ui(&mut self, x: &Self, val: u64) {
+        *self = floor_div_rem(x, &BigInt::from(val)).0;
+    }
+
+    #[inline]
+    fn fdiv_q_ui_mut(&mut self, val: u64) {
+        *self = floor_div_rem(self, &BigInt::from(val)).0;
+    }
+
+    #[inline]
+    fn tdiv_q_mut(&mut self, x: &Self) {
+        *self = &*self / x;
+    }
+
+    #[inline]
+    fn tdiv_q_2exp(&mut self, x: &Self, bits: u64) {
+        *self = BigInt::from_biguint(x.sign(), x.magnitude() >> bits);
+    }
+
+    #[inline]
+    fn divexact(&mut self, n: &Self, d: &Self) {
+        *self = n / d;
+    }
+
+    #[inline]
+    fn divexact_mut(&mut self, d: &Self) {
+        *self = &*self / d;
+    }
+
+    #[inline]
+    fn modulo(&mut self, x: &Self, y: &Self) {
+        let m = BigInt::from(y.magnitude().clone());
+        *self = floor_div_rem(x, &m).1;
+    }
+
+    #[inline]
+    fn modulo_mut(&mut self, x: &Self) {
+        let m = BigInt::from(x.magnitude().clone());
+        *self = floor_div_rem(self, &m).1;
+    }
+
+    #[inline]
+    fn fdiv_ui(&self, m: u64) -> u64 {
+        let r = self % m;
+        let low = r.iter_u64_digits().next().unwrap_or(0);
+        if r.sign() == Sign::Minus {
+            m - low
+        } else {
+            low
+        }
+    }
+
+    fn gcd(&mut self, x: &Self, y: &Self) {
+        let (mut a, mut b) = (x.magnitude().clone(), y.magnitude().clone());
+        while b.bits() != 0 {
+            let r = &a % &b;
+            a = b;
+            b = r;
+        }
+        *self = BigInt::from(a);
+    }
+
+    #[inline]
+    fn gcd_mut(&mut self, x: &Self) {
+        let s = self.clone();
+        self.gcd(&s, x);
+    }
+
+    fn gcdext(&mut self, d: &mut Self, e: &mut Self, a: &Self, m: &Self) {
+        // Extended Euclid with truncating division keeps |r| strictly decreasing for any signs.
+        let (mut old_r, mut r) = (a.clone(), m.clone());
+        let (mut old_s, mut s) = (BigInt::from(1u32), BigInt::from(0u32));
+        let (mut old_t, mut t) = (BigInt::from(0u32), BigInt::from(1u32));
+        while r.sign() != Sign::NoSign {
+            let q = &old_r / &r;
+            let next_r = &old_r - &q * &r;
+            old_r = std::mem::replace(&mut r, next_r);
+            let next_s = &old_s - &q * &s;
+            old_s = std::mem::replace(&mut s, next_s);
+            let next_t = &old_t - &q * &t;
+            old_t = std::mem::replace(&mut t, next_t);
+        }
+        if old_r.sign() == Sign::Minus {
+            old_r = -old_r;
+            old_s = -old_s;
+            old_t = -old_t;
+        }
+        *self = old_r;
+        *d = old_s;
+        *e = old_t;
+    }
+
+    fn root_mut(&mut self, n: u64) -> i32 {
+        let root = self.nth_root(n as u32);
+        let exact = root.pow(n as u32) == *self;
+        *self = root;
+        exact as i32
+    }
+
+    #[inline]
+    fn powm(&mut self, base: &Self, exp: &Self, m: &Self) {
+        *self = base.modpow(exp, m);
+    }
+
+    #[inline]
+    fn set(&mut self, x: &Self) {
+        self.clone_from(x);
+    }
+
+    #[inline]
+    fn set_si(&mut self, val: i64) {
+        *self = BigInt::from(val);
+    }
+
+    #[inline]
+    fn set_ui(&mut self, val: u64) {
+        *self = BigInt::from(val);
+    }
+
+    #[inline]
+    fn from_be_bytes(bytes: &[u8]) -> Self {
+        BigInt::from_bytes_be(Sign::Plus, bytes)
+    }
+
+    #[inline]
+    fn get_si(&self) -> i64 {
+        // Like `mpz_get_si`: the low bits of the magnitude, with the sign applied.
+        let low = self.iter_u64_digits().next().unwrap_or(0) & (i64::MAX as u64);
+        if self.sign() == Sign::Minus {
+            -(low as i64)
+        } else {
+            low as i64
+        }
+    }
+
+    #[inline]
+    fn bit_length(&self) -> usize {
+        std::cmp::max(self.bits(), 1) as usize
+    }
+
+    #[inline]
+    fn tstbit(&self, bit: usize) -> bool {
+        self.bit(bit as u64)
+    }
+}
@@ -0,0 +1,975 @@
+//! The `BigNum` trait: the big-integer interface the class group arithmetic is written against.
+//!
+//! The methods mirror the in-place, GMP-style API of `Mpz`: the receiver is the output and the
+//! arguments are the operands, e.g. `r.add(&x, &y)` computes `r = x + y`. Methods ending with
+//! `_mut` use the receiver as the first operand as well, e.g. `r.add_mut(&x)` computes `r += x`.
+//! Division methods follow GMP's naming: `fdiv` rounds towards negative infinity and `tdiv`
+//! truncates towards zero.
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use std::str::FromStr;
+
+pub trait BigNum:
+    Clone + Default + Debug + Display + Eq + Ord + Hash + FromStr + From<u64> + Send + Sync + 'static
+{
+    /// `self = |x|`
+    fn abs(&mut self, x: &Self);
+    /// `self = |self|`
+    fn abs_mut(&mut self);
+    /// `self = x + y`
+    fn add(&mut self, x: &Self, y: &Self);
+    /// `self += x`
+    fn add_mut(&mut self, x: &Self);
+    /// `self += x`
+    fn add_ui_mut(&mut self, x: u64);
+    /// `self += x * y`
+    fn add_mul(&mut self, x: &Self, y: &Self);
+    /// `self = x - y`
+    fn sub(&mut self, x: &Self, y: &Self);
+    /// `self -= x`
+    fn sub_mut(&mut self, x: &Self);
+    /// `self -= x`
+    fn sub_ui_mut(&mut self, x: u64);
+    /// `self -= x * y`
+    fn submul(&mut self, x: &Self, y: &Self);
+    /// `self = x * y`
+    fn mul(&mut self, x: &Self, y: &Self);
+    /// `self *= x`
+    fn mul_mut(&mut self, x: &Self);
+    /// `self = x * val`
+    fn mul_ui(&mut self, x: &Self, val: u64);
+    /// `self *= val`
+    fn mul_ui_mut(&mut self, val: u64);
+    /// `self = x * val`
+    fn mul_si(&mut self, x: &Self, val: i64);
+    /// `self = self * self`
+    fn square_mut(&mut self);
+    /// `self = -x`
+    fn neg(&mut self, x: &Self);
+    /// `self = -self`
+    fn neg_mut(&mut self);
+
+    /// Compares `|self|` with `|other|`, returning a negative, zero or positive value.
+    fn cmpabs(&self, other: &Self) -> i32;
+    /// Compares `self` with `val`, returning a negative, zero or positive value.
+    fn cmp_si(&self, val: i64) -> i32;
+    /// Returns `-1`, `0` or `1` according to the sign of `self`.
+    fn sgn(&self) -> i32;
+
+    /// `self = floor(x / y)`
+    fn fdiv_q(&mut self, x: &Self, y: &Self);
+    /// `self = floor(self / x)`
+    fn fdiv_q_mut(&mut self, x: &Self);
+    /// `self = x - y * floor(x / y)`
+    fn fdiv_r(&mut self, x: &Self, y: &Self);
+    /// `self = self - x * floor(self / x)`
+    fn fdiv_r_mut(&mut self, x: &Self);
+    /// `self = floor(x / y)`, `r = x - y * self`
+    fn fdiv_qr(&mut self, r: &mut Self, x: &Self, y: &Self);
+    /// `self = floor(x / val)`
+    fn fdiv_q_ui(&mut self, x: &Self, val: u64);
+    /// `self = floor(self / val)`
+    fn fdiv_q_ui_mut(&mut self, val: u64);
+    /// `self = trunc(self / x)`
+    fn tdiv_q_mut(&mut self, x: &Self);
+    /// `self = trunc(x / 2^bits)`
+    fn tdiv_q_2exp(&mut self, x: &Self, bits: u64);
+    /// `self = n / d`, where `d` is known to divide `n`.
+    fn divexact(&mut self, n: &Self, d: &Self);
+    /// `self = self / d`, where `d` is known to divide `self`.
+    fn divexact_mut(&mut self, d: &Self);
+    /// `self = x mod |y|`, always non-negative.
+    fn modulo(&mut self, x: &Self, y: &Self);
+    /// `self mod m`, always non-negative.
+    fn fdiv_ui(&self, m: u64) -> u64;
+    /// `self = self mod |x|`, always non-negative.
+    fn modulo_mut(&mut self, x: &Self);
+
+    /// `self = gcd(x, y)`, always non-negative.
+    fn gcd(&mut self, x: &Self, y: &Self);
+    /// `self = gcd(self, x)`, always non-negative.
+    fn gcd_mut(&mut self, x: &Self);
+    /// `self = gcd(a, m) = a * d + m * e`.
+    fn gcdext(&mut self, d: &mut Self, e: &mut Self, a: &Self, m: &Self);
+    /// `self = floor(self^(1/n))`, returning non-zero if the root is exact.
+    fn root_mut(&mut self, n: u64) -> i32;
+    /// `self = base^exp mod m`, for `exp >= 0` and `m > 0`.
+    fn powm(&mut self, base: &Self, exp: &Self, m: &Self);
+
+    /// `self = x`
+    fn set(&mut self, x: &Self);
+    /// `self = val`
+    fn set_si(&mut self, val: i64);
+    /// `self = val`
+    fn set_ui(&mut self, val: u64);
+    /// The non-negative integer with big-endian byte representation `bytes`.
+    fn from_be_bytes(bytes: &[u8]) -> Self;
+
+    /// The low bits of `self` as an `i64`. Only meaningful when `self` fits.
+    fn get_si(&self) -> i64;
+    /// The number of bits of `|self|`, counting `0` as one bit wide (as GMP does).
+    fn bit_length(&self) -> usize;
+    /// Tests bit `bit` of `self` (in two's complement for negative numbers).
+    fn tstbit(&self, bit: usize) -> bool;
+
+    #[inline]
+    fn swap(&mut self, other: &mut Self) {
+        std::mem::swap(self, other);
+    }
+
+    #[inline]
+    fn is_neg(&self) -> bool {
+        self.sgn() < 0
+    }
+
+    #[inline]
+    fn is_zero(&self) -> bool {
+        self.sgn() == 0
+    }
+
+    #[inline]
+    fn is_one(&self) -> bool {
+        self.cmp_si(1) == 0
+    }
+
+    #[inline]
+    fn odd(&self) -> bool {
+        self.tstbit(0)
+    }
+
+    /// The big-endian bytes of `|self|`, without leading zeros, so empty for zero. The inverse
+    /// of `from_be_bytes` for non-negative numbers.
+    fn to_be_bytes(&self) -> Vec<u8> {
+        if self.is_zero() {
+            return Vec::new();
+        }
+        let mut n = Self::default();
+        n.abs(self);
+        (0..(n.bit_length() + 7) / 8)
+            .rev()
+            .map(|i| (0..8).fold(0, |byte, j| byte | (n.tstbit(8 * i + j) as u8) << j))
+            .collect()
+    }
+
+    /// Whether `self` is the square of an integer.
+    fn is_perfect_square(&self) -> bool {
+        if self.is_neg() {
+            return false;
+        }
+        self.clone().root_mut(2) != 0
+    }
+
+    /// The Jacobi symbol `(self / n)` for an odd positive `n`.
+    fn jacobi(&self, n: &Self) -> i32 {
+        let (mut a, mut n) = (Self::default(), n.clone());
+        a.modulo(self, &n);
+        let mut t = 1;
+        while !a.is_zero() {
+            while !a.odd() {
+                a.fdiv_q_ui_mut(2);
+                let r = n.fdiv_ui(8);
+                if r == 3 || r == 5 {
+                    t = -t;
+                }
+            }
+            a.swap(&mut n);
+            if a.fdiv_ui(4) == 3 && n.fdiv_ui(4) == 3 {
+                t = -t;
+            }
+            a.modulo_mut(&n);
+        }
+        if n.is_one() {
+            t
+        } else {
+            0
+        }
+    }
+
+    /// The Legendre symbol `(self / p)` for an odd prime `p`, which is its Jacobi symbol.
+    fn legendre(&self, p: &Self) -> i32 {
+        self.jacobi(p)
+    }
+
+    /// The Kronecker symbol `(self / n)`, which extends the Jacobi symbol to every integer `n`
+    /// with `(a / 2) = 0` for even `a`, `1` for `a = ±1 mod 8` and `-1` for `a = ±3 mod 8`, and
+    /// `(a / -1) = -1` exactly when `a < 0`. For a discriminant `D` and a prime `p` it is `1`,
+    /// `-1` or `0` as `p` splits, is inert or ramifies.
+    fn kronecker(&self, n: &Self) -> i32 {
+        if n.is_zero() {
+            return (self.cmpabs(&Self::from(1)) == 0) as i32;
+        }
+        let mut t = if n.is_neg() && self.is_neg() { -1 } else { 1 };
+        let mut m = Self::default();
+        m.abs(n);
+        while !m.odd() {
+            if !self.odd() {
+                return 0;
+            }
+            m.fdiv_q_ui_mut(2);
+            let r = self.fdiv_ui(8);
+            if r == 3 || r == 5 {
+                t = -t;
+            }
+        }
+        t * self.jacobi(&m)
+    }
+
+    /// Whether `self` is a probable prime: trial division by the primes below 40, then a strong
+    /// probable-prime test to each of them as base. This is exact below 3.1 * 10^23.
+    fn is_probable_prime(&self) -> bool {
+        const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+        if self.cmp_si(2) < 0 {
+            return false;
+        }
+        for &p in BASES.iter() {
+            if self.fdiv_ui(p) == 0 {
+                return self.cmp_si(p as i64) == 0;
+            }
+        }
+
+        // self - 1 = d * 2^s with d odd.
+        let mut minus_one = Self::default();
+        minus_one.sub(self, &Self::from(1));
+        let mut d = minus_one.clone();
+        let mut s = 0;
+        while !d.odd() {
+            d.fdiv_q_ui_mut(2);
+            s += 1;
+        }
+        let mut x = Self::default();
+        BASES.iter().all(|&a| {
+            x.powm(&Self::from(a), &d, self);
+            if x.is_one() || x == minus_one {
+                return true;
+            }
+            for _ in 1..s {
+                x.square_mut();
+                x.modulo_mut(self);
+                if x == minus_one {
+                    return true;
+                }
+            }
+            false
+        })
+    }
+
+    /// Sets `self` to a square root of `n` modulo the odd prime `p`, in `[0, p)`, by
+    /// Tonelli-Shanks. Returns `false`, leaving `self` unspecified, if `n` is not a square mod `p`.
+    /// For a composite `p` it may also return `false` when there is a root, but it returns.
+    fn sqrt_mod_prime(&mut self, n: &Self, p: &Self) -> bool {
+        let mut r = Self::default();
+        r.modulo(n, p);
+        if r.is_zero() {
+            self.set_ui(0);
+            return true;
+        }
+        if r.legendre(p) != 1 {
+            return false;
+        }
+
+        // p - 1 = q * 2^s with q odd.
+        let mut q = Self::default();
+        q.sub(p, &Self::from(1));
+        let mut s = 0;
+        while !q.odd() {
+            q.fdiv_q_ui_mut(2);
+            s += 1;
+        }
+
+        // One exponentiation gives both the first guess r^((q+1)/2) and t = r^q; for p = 3 mod 4
+        // t is already 1.
+        let (mut t, mut e) = (Self::default(), Self::default());
+        e.sub(&q, &Self::from(1));
+        e.fdiv_q_ui_mut(2);
+        t.powm(&r, &e, p);
+        self.mul(&t, &r);
+        self.modulo_mut(p);
+        t.mul_mut(self);
+        t.modulo_mut(p);
+        if t.is_one() {
+            return true;
+        }
+
+        // A prime has a non-residue below p, a composite p such as 9 need not.
+        let mut z = Self::from(2);
+        while z.legendre(p) != -1 {
+            z.add_ui_mut(1);
+            if z >= *p {
+                return false;
+            }
+        }
+        let mut c = Self::default();
+        c.powm(&z, &q, p);
+
+        let mut m = s;
+        let (mut t2, mut b) = (Self::default(), Self::default());
+        while !t.is_one() {
+            // Least i with t^(2^i) = 1; 0 < i < m since t has order dividing 2^(m-1).
+            let mut i = 0;
+            t2.set(&t);
+            while !t2.is_one() {
+                t2.square_mut();
+                t2.modulo_mut(p);
+                i += 1;
+                // Only for a composite p.
+                if i == m {
+                    return false;
+                }
+            }
+            b.set(&c);
+            for _ in 0..(m - i - 1) {
+                b.square_mut();
+                b.modulo_mut(p);
+            }
+            m = i;
+            c.mul(&b, &b);
+            c.modulo_mut(p);
+            t.mul_mut(&c);
+            t.modulo_mut(p);
+            self.mul_mut(&b);
+            self.modulo_mut(p);
+        }
+        true
+    }
+
+    /// Sets `self` to the square root of `n` modulo `4p` in `[0, p]`, for a prime `p`: the root
+    /// of `n` mod `p` with the parity of `n`, or for `p = 2` the root mod 8 in `[0, 2]`. This is
+    /// the `b` of a form `(p, b, c)` of discriminant `n`. Returns `false`, leaving `self`
+    /// unspecified, if there is none, that is if `n = 2, 3 mod 4` or `n` is not a square mod `p`.
+    fn sqrt_mod_4p(&mut self, n: &Self, p: &Self) -> bool {
+        if p.cmp_si(2) == 0 {
+            match n.fdiv_ui(8) {
+                0 => self.set_ui(0),
+                1 => self.set_ui(1),
+                4 => self.set_ui(2),
+                _ => return false,
+            }
+            return true;
+        }
+        if n.fdiv_ui(4) > 1 || !self.sqrt_mod_prime(n, p) {
+            return false;
+        }
+        // Of the roots r and p - r just one has the parity of n, which with b^2 = n mod p gives
+        // b^2 = n mod 4p.
+        if self.odd() != n.odd() {
+            let r = self.clone();
+            self.sub(p, &r);
+        }
+        true
+    }
+
+    /// Returns `(m, e)` where `e` is the bit length of `self` and `m` holds the 63 most
+    /// significant bits of `self` (with its sign), so that `self ~ m * 2^(e - 63)`.
+    fn get_si_2exp(&self) -> (i64, i64) {
+        let exp = self.bit_length() as i64;
+        let top = if exp > 63 {
+            let mut t = Self::default();
+            t.tdiv_q_2exp(self, (exp - 63) as u64);
+            t.get_si().abs()
+        } else {
+            self.get_si().abs() << (63 - exp)
+        };
+        if self.is_neg() {
+            return (-top, exp);
+        }
+        (top, exp)
+    }
+}
+
+#[cfg(feature = "gmp")]
+#[inline]
+fn log2(x: u64) -> u32 {
+    63 - x.leading_zeros()
+}
+
+#[cfg(feature = "gmp")]
+#[inline]
+fn signed_shift(op: u64, shift: i64) -> u64 {
+    match shift {
+        x if x > 0 => op << shift,
+        x if x <= -64 => 0,
+        _ => op >> (-shift),
+    }
+}
+
+// Implements `BigNum` for a type wrapping a GMP `mpz_t`, reachable through `as_raw` and
+// `as_raw_mut` (the accessor names used by `rug`).
+#[cfg(feature = "gmp")]
+macro_rules! impl_bignum_gmp {
+    ($t:ty) => {
+        impl BigNum for $t {
+            #[inline]
+            fn abs(&mut self, x: &Self) {
+                unsafe { gmp::mpz_abs(self.as_raw_mut(), x.as_raw()) }
+            }
+
+            #[inline]
+            fn abs_mut(&mut self) {
+                let s = self.as_raw_mut();
+                unsafe { gmp::mpz_abs(s, s) }
+            }
+
+            #[inline]
+            fn add(&mut self, x: &Self, y: &Self) {
+                unsafe { gmp::mpz_add(self.as_raw_mut(), x.as_raw(), y.as_raw()) }
+            }
+
+            #[inline]
+            fn add_mut(&mut self, x: &Self) {
+                let s = self.as_raw_mut();
+                unsafe { gmp::mpz_add(s, s, x.as_raw()) }
+            }
+
+            #[inline]
+            fn add_ui_mut(&mut self, x: u64) {
+                let s = self.as_raw_mut();
+                unsafe { gmp::mpz_add_ui(s, s, x as _) }
+            }
+
+            #[inline]
+            fn add_mul(&mut self, x: &Self, y: &Self) {
+                unsafe { gmp::mpz_addmul(self.as_raw_mut(), x.as_raw(), y.as_raw()) }
+            }
+
+            #[inline]
+            fn sub(&mut self, x: &Self, y: &Self) {
+                unsafe { gmp::mpz_sub(self.as_raw_mut(), x.as_raw(), y.as_raw()) }
+            }
+
+            #[inline]
+            fn sub_mut(&mut self, x: &Self) {
+                let s = self.as_raw_mut();
+                unsafe { gmp::mpz_sub(s, s, x.as_raw()) }
+            }
+
+            #[inline]
+            fn sub_ui_mut(&mut self, x: u64) {
+                let s = self.as_raw_mut();
+                unsafe { gmp::mpz_sub_ui(s, s, x as _) }
+            }
+
+            #[inline]
+            fn submul(&mut self, x: &Self, y: &Self) {
+                unsafe { gmp::mpz_submul(self.as_raw_mut(), x.as_raw(), y.as_raw()) }
+            }
+
+            #[inline]
+            fn mul(&mut self, x: &Self, y: &Self) {
+                unsafe { gmp::mpz_mul(self.as_raw_mut(), x.as_raw(), y.as_raw()) }
+            }
+
+            #[inline]
+            fn mul_mut(&mut self, x: &Self) {
+                let s = self.as_raw_mut();
+                unsafe { gmp::mpz_mul(s, s, x.as_raw()) }
+            }
+
+            #[inline]
+            fn mul_ui(&mut self, x: &Self, val: u64) {
+                unsafe { gmp::mpz_mul_ui(self.as_raw_mut(), x.as_raw(), val as _) }
+            }
+
+            #[inline]
+            fn mul_ui_mut(&mut self, val: u64) {
+                let s = self.as_raw_mut();
+                unsafe { gmp::mpz_mul_ui(s, s, val as _) }
+            }
+
+            #[inline]
+            fn mul_si(&mut self, x: &Self, val: i64) {
+                unsafe { gmp::mpz_mul_si(self.as_raw_mut(), x.as_raw(), val as _) }
+            }
+
+            #[inline]
+            fn square_mut(&mut self) {
+                let s = self.as_raw_mut();
+                unsafe { gmp::mpz_mul(s, s, s) }
+            }
+
+            #[inline]
+            fn neg(&mut self, x: &Self) {
+                unsafe { gmp::mpz_neg(self.as_raw_mut(), x.as_raw()) }
+            }
+
+            #[inline]
+            fn neg_mut(&mut self) {
+                let s = self.as_raw_mut();
+                unsafe { gmp::mpz_neg(s, s) }
+            }
+
+            #[inline]
+            fn cmpabs(&self, other: &Self) -> i32 {
+                unsafe { gmp::mpz_cmpabs(self.as_raw(), other.as_raw()) }
+            }
+
+            #[inline]
+            fn cmp_si(&self, val: i64) -> i32 {
+                unsafe { gmp::mpz_cmp_si(self.as_raw(), val as _) }
+            }
+
+            #[inline]
+            fn sgn(&self) -> i32 {
+                unsafe { gmp::mpz_sgn(self.as_raw()) }
+            }
+
+            #[inline]
+            fn fdiv_q(&mut self, x: &Self, y: &Self) {
+                unsafe { gmp::mpz_fdiv_q(self.as_raw_mut(), x.as_raw(), y.as_raw()) }
+            }
+
+            #[inline]
+            fn fdiv_q_mut(&mut self, x: &Self) {
+                let s = self.as_raw_mut();
+                unsafe { gmp::mpz_fdiv_q(s, s, x.as_raw()) }
+            }
+
+            #[inline]
+            fn fdiv_r(&mut self, x: &Self, y: &Self) {
+                unsafe { gmp::mpz_fdiv_r(self.as_raw_mut(), x.as_raw(), y.as_raw()) }
+            }
+
+            #[inline]
+            fn fdiv_r_mut(&mut self, x: &Self) {
+                let s = self.as_raw_mut();
+                unsafe { gmp::mpz_fdiv_r(s, s, x.as_raw()) }
+            }
+
+            #[inline]
+            fn fdiv_qr(&mut self, r: &mut Self, x: &Self, y: &Self) {
+                unsafe {
+                    gmp::mpz_fdiv_qr(self.as_raw_mut(), r.as_raw_mut(), x.as_raw(), y.as_raw())
+                }
+            }
+
+            #[inline]
+            fn fdiv_q_ui(&mut self, x: &Self, val: u64) {
+                unsafe {
+                    gmp::mpz_fdiv_q_ui(self.as_raw_mut(), x.as_raw(), val as _);
+                }
+            }
+
+            #[inline]
+            fn fdiv_q_ui_mut(&mut self, val: u64) {
+                let s = self.as_raw_mut();
+                unsafe {
+                    gmp::mpz_fdiv_q_ui(s, s, val as _);
+                }
+            }
+
+            #[inline]
+            fn tdiv_q_mut(&mut self, x: &Self) {
+                let s = self.as_raw_mut();
+                unsafe { gmp::mpz_tdiv_q(s, s, x.as_raw()) }
+            }
+
+            #[inline]
+            fn tdiv_q_2exp(&mut self, x: &Self, bits: u64) {
+                unsafe { gmp::mpz_tdiv_q_2exp(self.as_raw_mut(), x.as_raw(), bits as _) }
+            }
+
+            #[inline]
+            fn divexact(&mut self, n: &Self, d: &Self) {
+                unsafe { gmp::mpz_divexact(self.as_raw_mut(), n.as_raw(), d.as_raw()) }
+            }
+
+            #[inline]
+            fn divexact_mut(&mut self, d: &Self) {
+                let s = self.as_raw_mut();
+                unsafe { gmp::mpz_divexact(s, s, d.as_raw()) }
+            }
+
+            #[inline]
+            fn modulo(&mut self, x: &Self, y: &Self) {
+                unsafe { gmp::mpz_mod(self.as_raw_mut(), x.as_raw(), y.as_raw()) }
+            }
+
+            #[inline]
+            fn modulo_mut(&mut self, x: &Self) {
+                let s = self.as_raw_mut();
+                unsafe { gmp::mpz_mod(s, s, x.as_raw()) }
+            }
+
+            #[inline]
+            fn fdiv_ui(&self, m: u64) -> u64 {
+                unsafe { gmp::mpz_fdiv_ui(self.as_raw(), m as _) as u64 }
+            }
+
+            #[inline]
+            fn gcd(&mut self, x: &Self, y: &Self) {
+                unsafe { gmp::mpz_gcd(self.as_raw_mut(), x.as_raw(), y.as_raw()) }
+            }
+
+            #[inline]
+            fn gcd_mut(&mut self, x: &Self) {
+                let s = self.as_raw_mut();
+                unsafe { gmp::mpz_gcd(s, s, x.as_raw()) }
+            }
+
+            #[inline]
+            fn gcdext(&mut self, d: &mut Self, e: &mut Self, a: &Self, m: &Self) {
+                unsafe {
+                    gmp::mpz_gcdext(
+                        self.as_raw_mut(),
+                        d.as_raw_mut(),
+                        e.as_raw_mut(),
+                        a.as_raw(),
+                        m.as_raw(),
+                    )
+                }
+            }
+
+            #[inline]
+            fn root_mut(&mut self, n: u64) -> i32 {
+                let s = self.as_raw_mut();
+                unsafe { gmp::mpz_root(s, s, n as _) }
+            }
+
+            #[inline]
+            fn powm(&mut self, base: &Self, exp: &Self, m: &Self) {
+                unsafe { gmp::mpz_powm(self.as_raw_mut(), base.as_raw(), exp.as_raw(), m.as_raw()) }
+            }
+
+            #[inline]
+            fn set(&mut self, x: &Self) {
+                unsafe { gmp::mpz_set(self.as_raw_mut(), x.as_raw()) }
+            }
+
+            #[inline]
+            fn set_si(&mut self, val: i64) {
+                unsafe { gmp::mpz_set_si(self.as_raw_mut(), val as _) }
+            }
+
+            #[inline]
+            fn set_ui(&mut self, val: u64) {
+                unsafe { gmp::mpz_set_ui(self.as_raw_mut(), val as _) }
+            }
+
+            fn from_be_bytes(bytes: &[u8]) -> Self {
+                let mut ret = Self::default();
+                unsafe {
+                    gmp::mpz_import(
+                        ret.as_raw_mut(),
+                        bytes.len(),
+                        1,
+                        1,
+                        1,
+                        0,
+                        bytes.as_ptr() as *const _,
+                    )
+                };
+                ret
+            }
+
+            #[inline]
+            fn get_si(&self) -> i64 {
+                unsafe { gmp::mpz_get_si(self.as_raw()) as i64 }
+            }
+
+            #[inline]
+            fn bit_length(&self) -> usize {
+                unsafe { gmp::mpz_sizeinbase(self.as_raw(), 2) as usize }
+            }
+
+            #[inline]
+            fn tstbit(&self, bit: usize) -> bool {
+                unsafe { gmp::mpz_tstbit(self.as_raw(), bit as _) != 0 }
+            }
+
+            #[inline]
+            fn swap(&mut self, other: &mut Self) {
+                unsafe { gmp::mpz_swap(self.as_raw_mut(), other.as_raw_mut()) }
+            }
+
+            #[inline]
+            fn is_perfect_square(&self) -> bool {
+                unsafe { gmp::mpz_perfect_square_p(self.as_raw()) != 0 }
+            }
+
+            #[inline]
+            fn jacobi(&self, n: &Self) -> i32 {
+                unsafe { gmp::mpz_jacobi(self.as_raw(), n.as_raw()) }
+            }
+
+            #[inline]
+            fn kronecker(&self, n: &Self) -> i32 {
+                unsafe { gmp::mpz_kronecker(self.as_raw(), n.as_raw()) }
+            }
+
+            // Reads the two most significant limbs directly instead of shifting into a
+            // temporary, since this sits in the inner loop of form reduction.
+            #[inline]
+            fn get_si_2exp(&self) -> (i64, i64) {
+                let size = unsafe { gmp::mpz_size(self.as_raw()) } as usize;
+                if size == 0 {
+                    return (0, 0);
+                }
+                let last = unsafe { gmp::mpz_getlimbn(self.as_raw(), (size - 1) as _) } as u64;
+                let lg2 = log2(last) + 1;
+                let mut exp = lg2 as i64;
+                let mut ret = signed_shift(last, 63 - exp);
+                if size > 1 {
+                    exp += ((size - 1) * 64) as i64;
+                    let prev = unsafe { gmp::mpz_getlimbn(self.as_raw(), (size - 2) as _) } as u64;
+                    ret += signed_shift(prev, (-1 - lg2 as i32) as i64);
+                }
+                if self.is_neg() {
+                    return (-(ret as i64), exp);
+                }
+                (ret as i64, exp)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "gmp")]
+mod gmp_impls {
+    use super::*;
+    use crate::num::Mpz;
+    use gmp_mpfr_sys::gmp;
+    use rug::Integer;
+
+    impl_bignum_gmp!(Mpz);
+    impl_bignum_gmp!(Integer);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::num::DefaultBigNum;
+
+    fn big(x: i64) -> DefaultBigNum {
+        let mut r = DefaultBigNum::default();
+        r.set_si(x);
+        r
+    }
+
+    // Checks the backend against plain `i64` arithmetic with GMP's rounding conventions.
+    #[test]
+    fn test_division_rounding() {
+        for &(x, y) in &[(7, 2), (-7, 2), (7, -2), (-7, -2), (6, 3), (-6, 3), (0, 5)] {
+            let (bx, by) = (big(x), big(y));
+            let (mut q, mut r) = (DefaultBigNum::default(), DefaultBigNum::default());
+            q.fdiv_qr(&mut r, &bx, &by);
+            let fq = (x as f64 / y as f64).floor() as i64;
+            assert_eq!((q.get_si(), r.get_si()), (fq, x - y * fq));
+            q.set(&bx);
+            q.tdiv_q_mut(&by);
+            assert_eq!(q.get_si(), x / y);
+            r.modulo(&bx, &by);
+            assert_eq!(r.get_si(), x.rem_euclid(y.abs()));
+        }
+    }
+
+    #[test]
+    fn test_gcdext() {
+        for &(a, m) in &[(240, 46), (-240, 46), (17, 5), (3, -9), (0, 4)] {
+            let (ba, bm) = (big(a), big(m));
+            let (mut g, mut d, mut e) = (big(0), big(0), big(0));
+            g.gcdext(&mut d, &mut e, &ba, &bm);
+            assert!(g.sgn() >= 0);
+            assert_eq!(g.get_si(), a * d.get_si() + m * e.get_si());
+        }
+    }
+
+    #[test]
+    fn test_get_si_2exp() {
+        let mut x = big(1);
+        x.tdiv_q_2exp(&big(-12345), 0);
+        assert_eq!(x.get_si_2exp(), (-12345 << (63 - 14), 14));
+
+        // 2^100 + 2^40
+        let mut x = big(1);
+        let mut y = big(1);
+        for _ in 0..100 {
+            x.mul_ui_mut(2);
+        }
+        for _ in 0..40 {
+            y.mul_ui_mut(2);
+        }
+        x.add_mut(&y);
+        assert_eq!(x.bit_length(), 101);
+        assert_eq!(x.get_si_2exp(), ((1 << 62) + (1 << 2), 101));
+    }
+
+    #[test]
+    fn test_root_and_bits() {
+        let mut x = big(10_000);
+        assert!(x.root_mut(4) != 0);
+        assert_eq!(x.get_si(), 10);
+        let mut x = big(10_001);
+        assert_eq!(x.root_mut(4), 0);
+        assert_eq!(x.get_si(), 10);
+
+        let x = big(0b1011);
+        assert!(x.tstbit(0) && x.tstbit(1) && !x.tstbit(2) && x.tstbit(3) && !x.tstbit(4));
+        assert_eq!(x.bit_length(), 4);
+        assert_eq!(big(0).bit_length(), 1);
+    }
+
+    #[test]
+    fn test_be_bytes() {
+        assert!(big(0).to_be_bytes().is_empty());
+        assert_eq!(big(255).to_be_bytes(), [0xff]);
+        assert_eq!(big(-256).to_be_bytes(), [1, 0]);
+        let n = big(0x0123_4567_89ab_cdef);
+        assert_eq!(n.to_be_bytes(), 0x0123_4567_89ab_cdef_u64.to_be_bytes());
+        assert_eq!(DefaultBigNum::from_be_bytes(&n.to_be_bytes()), n);
+    }
+
+    #[test]
+    fn test_is_probable_prime() {
+        let mut composite = vec![false; 10_000];
+        for n in 2..100 {
+            for m in (n * n..10_000).step_by(n) {
+                composite[m] = true;
+            }
+        }
+        for n in 0..10_000 {
+            assert_eq!(big(n as i64).is_probable_prime(), n >= 2 && !composite[n]);
+        }
+        assert!(!big(-7).is_probable_prime());
+        // Carmichael numbers, a strong pseudoprime to base 2, and one to the bases up to 31.
+        for &n in &[561, 41041, 825_265, 2047, 3_825_123_056_546_413_051] {
+            assert!(!big(n).is_probable_prime());
+        }
+        assert!(big(1_000_000_007).is_probable_prime());
+        assert!(big(9_223_372_036_854_775_783).is_probable_prime());
+    }
+
+    #[test]
+    fn test_sqrt_mod_composite_returns() {
+        // No z below 9 has (z / 9) = -1, and 15 = 3 * 5 breaks Tonelli-Shanks otherwise.
+        for &(n, p) in &[(4, 9), (-23, 9), (1, 15), (4, 15), (-11, 15)] {
+            let mut root = big(0);
+            if root.sqrt_mod_prime(&big(n), &big(p)) {
+                let r = root.get_si();
+                assert_eq!((r * r - n) % p, 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sqrt_mod_prime() {
+        // 10009 = 1 + 2^3 * 1251 exercises the Tonelli-Shanks loop, 10007 = 3 mod 4 does not.
+        for &p in &[3, 5, 13, 17, 10007, 10009] {
+            let bp = big(p);
+            let mut squares = vec![false; p as usize];
+            for x in 0..p {
+                squares[(x * x % p) as usize] = true;
+            }
+            for n in 0..p {
+                let mut root = big(0);
+                let found = root.sqrt_mod_prime(&big(n), &bp);
+                assert_eq!(found, squares[n as usize]);
+                assert_eq!(
+                    big(n).legendre(&bp),
+                    if n == 0 {
+                        0
+                    } else if found {
+                        1
+                    } else {
+                        -1
+                    }
+                );
+                if found {
+                    let r = root.get_si();
+                    assert!(r >= 0 && r < p);
+                    assert_eq!(r * r % p, n);
+                }
+            }
+        }
+        // Negative inputs are reduced into [0, p) first.
+        let mut root = big(0);
+        assert!(root.sqrt_mod_prime(&big(-1), &big(13)));
+        assert_eq!(root.get_si() * root.get_si() % 13, 12);
+    }
+
+    #[test]
+    fn test_sqrt_mod_4p() {
+        for &p in &[2, 3, 5, 13, 10009] {
+            for n in -200..200 {
+                let mut b = big(0);
+                let found = b.sqrt_mod_4p(&big(n), &big(p));
+                let roots: Vec<i64> = (0..=p).filter(|b| (b * b - n) % (4 * p) == 0).collect();
+                assert_eq!(found, !roots.is_empty(), "n = {}, p = {}", n, p);
+                if found {
+                    assert_eq!(roots, [b.get_si()]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_jacobi_and_squares() {
+        // Against Euler's criterion for primes, and multiplicativity for composites.
+        for &p in &[3i64, 5, 7, 11, 13, 10007] {
+            for a in -20..20 {
+                let j = big(a).jacobi(&big(p));
+                let mut euler = big(0);
+                euler.powm(&big(a.rem_euclid(p)), &big((p - 1) / 2), &big(p));
+                let expected = if euler.is_one() { 1 } else if euler.is_zero() { 0 } else { -1 };
+                assert_eq!(j, expected);
+                assert_eq!(big(a).jacobi(&big(p * 15)), j * big(a).jacobi(&big(15)));
+            }
+        }
+        assert_eq!(big(5).jacobi(&big(9)), 1);
+        assert_eq!(big(3).jacobi(&big(9)), 0);
+        assert_eq!(big(2).jacobi(&big(15)), 1);
+        assert_eq!(big(7).jacobi(&big(15)), -1);
+
+        for x in 0..200 {
+            let root = (x as f64).sqrt() as i64;
+            assert_eq!(big(x).is_perfect_square(), root * root == x);
+        }
+        assert!(!big(-4).is_perfect_square());
+        assert_eq!(big(-7).fdiv_ui(5), 3);
+    }
+
+    #[test]
+    fn test_kronecker() {
+        for a in -30..30 {
+            assert_eq!(big(a).kronecker(&big(0)), (a == 1 || a == -1) as i32);
+            assert_eq!(big(a).kronecker(&big(1)), 1);
+            assert_eq!(big(a).kronecker(&big(-1)), if a < 0 { -1 } else { 1 });
+            let two = match a.rem_euclid(8) {
+                1 | 7 => 1,
+                3 | 5 => -1,
+                _ => 0,
+            };
+            assert_eq!(big(a).kronecker(&big(2)), two);
+            for n in 1..30 {
+                let k = big(a).kronecker(&big(n));
+                if n % 2 == 1 {
+                    assert_eq!(k, big(a).jacobi(&big(n)));
+                }
+                // Completely multiplicative in n.
+                assert_eq!(big(a).kronecker(&big(2 * n)), two * k);
+                assert_eq!(big(a).kronecker(&big(-n)), big(a).kronecker(&big(-1)) * k);
+            }
+        }
+        // -23 = 1 mod 8, so 2 splits; 3 splits, 5 is inert and 23 ramifies.
+        let d = big(-23);
+        for &(p, symbol) in &[(2, 1), (3, 1), (5, -1), (23, 0)] {
+            assert_eq!(d.kronecker(&big(p)), symbol);
+        }
+    }
+
+    #[test]
+    fn test_powm_and_bytes() {
+        let mut r = big(0);
+        r.powm(&big(3), &big(200), &big(1_000_003));
+        let mut expected = 1i64;
+        for _ in 0..200 {
+            expected = expected * 3 % 1_000_003;
+        }
+        assert_eq!(r.get_si(), expected);
+
+        let x = DefaultBigNum::from_be_bytes(&[0x01, 0x02, 0x03]);
+        assert_eq!(x.get_si(), 0x010203);
+        assert!(DefaultBigNum::from_be_bytes(&[]).is_zero());
+
+        for &n in &[0, 7, -1_234_567_890_123, i64::MIN + 1] {
+            assert_eq!(big(n).to_string(), n.to_string());
+        }
+    }
+}
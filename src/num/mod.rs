@@ -1,7 +1,26 @@
 //! BigNum Backend
 
+mod bignum;
+pub use bignum::BigNum;
+
+#[cfg(feature = "gmp")]
 mod mpz;
+#[cfg(feature = "gmp")]
 pub use mpz::Mpz;
 
+#[cfg(feature = "num-bigint")]
+mod bigint;
+
 pub mod partial;
+#[cfg(feature = "gmp")]
 pub(crate) mod rand;
+
+#[cfg(not(any(feature = "gmp", feature = "num-bigint")))]
+compile_error!("enable at least one big-number backend: `gmp` or `num-bigint`");
+
+/// The backend used when none is named: `Mpz` with GMP, `num_bigint::BigInt` without it.
+#[cfg(feature = "gmp")]
+pub type DefaultBigNum = Mpz;
+/// The backend used when none is named: `Mpz` with GMP, `num_bigint::BigInt` without it.
+#[cfg(not(feature = "gmp"))]
+pub type DefaultBigNum = num_bigint::BigInt;
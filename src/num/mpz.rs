@@ -1,10 +1,12 @@
 //! Mpz wrappers.
 
+use crate::num::BigNum;
 use gmp_mpfr_sys::gmp::{self, limb_t, mpz_t};
 use std::cmp::Ordering;
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
+use std::fmt;
 use std::hash::{Hash, Hasher};
-use std::mem::uninitialized;
+use std::mem::MaybeUninit;
 use std::os::raw::{c_int, c_ulong};
 use std::slice;
 use std::str::FromStr;
@@ -23,14 +25,20 @@ impl Eq for Mpz {}
 impl Default for Mpz {
     fn default() -> Self {
         let inner = unsafe {
-            let mut ret = uninitialized();
-            gmp::mpz_init(&mut ret);
-            ret
+            let mut ret = MaybeUninit::uninit();
+            gmp::mpz_init(ret.as_mut_ptr());
+            ret.assume_init()
         };
         Self { inner }
     }
 }
 
+impl Drop for Mpz {
+    fn drop(&mut self) {
+        unsafe { gmp::mpz_clear(&mut self.inner) }
+    }
+}
+
 impl Clone for Mpz {
     fn clone(&self) -> Self {
         let mut ret = Mpz::default();
@@ -96,11 +104,35 @@ impl FromStr for Mpz {
     }
 }
 
+impl fmt::Display for Mpz {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Room for the digits, a minus sign and the terminating NUL.
+        let mut buf = vec![0u8; unsafe { gmp::mpz_sizeinbase(&self.inner, 10) } + 2];
+        let s = unsafe {
+            gmp::mpz_get_str(buf.as_mut_ptr() as *mut _, 10, &self.inner);
+            CStr::from_ptr(buf.as_ptr() as *const _)
+        };
+        f.write_str(s.to_str().unwrap())
+    }
+}
+
 // Defines wrappers around gmp_mpfr_sys.  Functions ending
 // with `_mut` correspond to giving the underlying GMP function
 // the same Mpz variable for the first two arguments, e.g.
 // to provide an interface for operations like x += y or x /= y.
 impl Mpz {
+    /// A raw pointer to the underlying `mpz_t`, for calling GMP directly.
+    #[inline]
+    pub fn as_raw(&self) -> *const mpz_t {
+        &self.inner
+    }
+
+    /// A raw mutable pointer to the underlying `mpz_t`, for calling GMP directly.
+    #[inline]
+    pub fn as_raw_mut(&mut self) -> *mut mpz_t {
+        &mut self.inner
+    }
+
     #[inline]
     pub fn abs(&mut self, x: &Mpz) {
         unsafe { gmp::mpz_abs(&mut self.inner, &x.inner) }
@@ -433,10 +465,12 @@ impl Mpz {
     #[inline]
     pub fn one() -> Mpz {
         unsafe {
-            let mut mpz = std::mem::uninitialized();
-            gmp::mpz_init_set_ui(&mut mpz, 1);
+            let mut mpz = MaybeUninit::uninit();
+            gmp::mpz_init_set_ui(mpz.as_mut_ptr(), 1);
 
-            Mpz { inner: mpz }
+            Mpz {
+                inner: mpz.assume_init(),
+            }
         }
     }
 
@@ -478,6 +512,50 @@ impl Mpz {
         //assert!(res <= std::u16::MAX.into());
         res as u16
     }
+
+    /// The Jacobi symbol `(self / n)` for an odd positive `n`.
+    #[inline]
+    pub fn jacobi(&self, n: &Mpz) -> i32 {
+        unsafe { gmp::mpz_jacobi(&self.inner, &n.inner) }
+    }
+
+    /// The Kronecker symbol `(self / n)`, defined for every `n`, see `BigNum::kronecker`.
+    #[inline]
+    pub fn kronecker(&self, n: &Mpz) -> i32 {
+        unsafe { gmp::mpz_kronecker(&self.inner, &n.inner) }
+    }
+
+    /// `self = base^exp mod m`, for `m > 0`. A negative `exp` needs `base` to be invertible mod
+    /// `m`.
+    #[inline]
+    pub fn powm(&mut self, base: &Mpz, exp: &Mpz, m: &Mpz) {
+        unsafe { gmp::mpz_powm(&mut self.inner, &base.inner, &exp.inner, &m.inner) }
+    }
+
+    /// Sets `self` to the inverse of `x` modulo `m` in `[0, |m|)`. Returns `false`, leaving
+    /// `self` unspecified, if there is none.
+    #[inline]
+    pub fn invert(&mut self, x: &Mpz, m: &Mpz) -> bool {
+        unsafe { gmp::mpz_invert(&mut self.inner, &x.inner, &m.inner) != 0 }
+    }
+
+    /// Sets `self` to the smallest probable prime greater than `x`.
+    #[inline]
+    pub fn nextprime(&mut self, x: &Mpz) {
+        unsafe { gmp::mpz_nextprime(&mut self.inner, &x.inner) }
+    }
+
+    /// A square root of `n` modulo the odd prime `p`, see `BigNum::sqrt_mod_prime`.
+    #[inline]
+    pub fn sqrt_mod_prime(&mut self, n: &Mpz, p: &Mpz) -> bool {
+        BigNum::sqrt_mod_prime(self, n, p)
+    }
+
+    /// The square root of `n` modulo `4p` in `[0, p]`, see `BigNum::sqrt_mod_4p`.
+    #[inline]
+    pub fn sqrt_mod_4p(&mut self, n: &Mpz, p: &Mpz) -> bool {
+        BigNum::sqrt_mod_4p(self, n, p)
+    }
 }
 
 /// Flint Port:
@@ -599,6 +677,110 @@ fn raw_export(raw: &Mpz) -> Vec<u8> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use rug::integer::IsPrime;
+    use rug::rand::RandState;
+    use rug::Integer;
+
+    fn to_mpz(x: &Integer) -> Mpz {
+        Mpz::from_str(&x.to_string()).unwrap()
+    }
+
+    fn to_integer(x: &Mpz) -> Integer {
+        x.to_string().parse().unwrap()
+    }
+
+    // Random integers of up to `bits` bits, negative about a third of the time.
+    fn random(rng: &mut RandState, bits: u32) -> Integer {
+        let x = Integer::from(Integer::random_bits(bits, rng));
+        if x.mod_u(3) == 0 {
+            -x
+        } else {
+            x
+        }
+    }
+
+    // A random odd prime of about `bits` bits.
+    fn random_prime(rng: &mut RandState, bits: u32) -> Integer {
+        let x = Integer::from(Integer::random_bits(bits, rng)) | 2u32;
+        x.next_prime()
+    }
+
+    fn rng() -> RandState<'static> {
+        let mut rng = RandState::new();
+        rng.seed(&Integer::from(0x5eed));
+        rng
+    }
+
+    #[test]
+    fn test_symbols_against_rug() {
+        let mut rng = rng();
+        for i in 0..2000 {
+            let bits = 8 + (i % 4) * 100;
+            let a = random(&mut rng, bits);
+            let n = random(&mut rng, bits);
+            let odd = Integer::from(n.abs_ref()) | 1;
+            let (ma, mn) = (to_mpz(&a), to_mpz(&n));
+            assert_eq!(ma.kronecker(&mn), a.kronecker(&n), "({} / {})", a, n);
+            assert_eq!(ma.jacobi(&to_mpz(&odd)), a.jacobi(&odd));
+            assert_eq!(BigNum::kronecker(&ma, &mn), a.kronecker(&n));
+        }
+    }
+
+    #[test]
+    fn test_powm_invert_nextprime_against_rug() {
+        let mut rng = rng();
+        for i in 0..1000 {
+            let bits = 8 + (i % 4) * 100;
+            let x = random(&mut rng, bits);
+            let e = random(&mut rng, bits);
+            let m = Integer::from(Integer::random_bits(bits, &mut rng)) + 1;
+            let (mx, me, mm) = (to_mpz(&x), to_mpz(&e), to_mpz(&m));
+
+            let mut r = Mpz::default();
+            let inverse = x.clone().invert(&m);
+            assert_eq!(r.invert(&mx, &mm), inverse.is_ok());
+            if let Ok(inverse) = inverse {
+                assert_eq!(to_integer(&r), inverse);
+            }
+            if let Ok(power) = x.clone().pow_mod(&e, &m) {
+                r.powm(&mx, &me, &mm);
+                assert_eq!(to_integer(&r), power);
+            }
+
+            r.nextprime(&mx);
+            assert_eq!(to_integer(&r), x.clone().next_prime());
+            assert_ne!(to_integer(&r).is_probably_prime(25), IsPrime::No);
+        }
+    }
+
+    #[test]
+    fn test_square_roots_against_rug() {
+        let mut rng = rng();
+        for i in 0..500 {
+            let p = random_prime(&mut rng, 8 + (i % 4) * 100);
+            let n = random(&mut rng, 300);
+            let residue = (Integer::from(&n % &p) + &p) % &p;
+            let is_square = residue.legendre(&p) != -1;
+
+            let mut r = Mpz::default();
+            assert_eq!(r.sqrt_mod_prime(&to_mpz(&n), &to_mpz(&p)), is_square);
+            if is_square {
+                let r = to_integer(&r);
+                assert!(r >= 0 && r < p);
+                assert_eq!(Integer::from(&r * &r) % &p, residue);
+            }
+
+            // Discriminants are 0 or 1 mod 4.
+            let found = r.sqrt_mod_4p(&to_mpz(&n), &to_mpz(&p));
+            assert_eq!(found, n.mod_u(4) <= 1 && is_square);
+            if found {
+                let b = to_integer(&r);
+                let four_p = Integer::from(&p * 4);
+                assert!(b >= 0 && b <= p);
+                assert!(Integer::from(Integer::from(&b * &b) - &n).is_divisible(&four_p));
+            }
+        }
+    }
 
     #[test]
     fn test_import_export() {
@@ -11,29 +11,28 @@
 ///  - C_i sequence from "Solving the Pell Equation" defined as
 ///     C_{-1}=0, C_{1}=-1  C_i=C_{i-2}-q_i C_{i-1}
 ///
-use crate::num::Mpz;
-use gmp_mpfr_sys::gmp;
+use crate::num::BigNum;
 
 #[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Debug)]
-pub struct PartialGCDContext {
-    pub q: Mpz,
-    pub r: Mpz,
-    pub t1: Mpz,
-    pub t2: Mpz,
+pub struct PartialGCDContext<B: BigNum> {
+    pub q: B,
+    pub r: B,
+    pub t1: B,
+    pub t2: B,
 }
 
-impl Default for PartialGCDContext {
+impl<B: BigNum> Default for PartialGCDContext<B> {
     fn default() -> Self {
         Self {
-            q: Mpz::default(),
-            r: Mpz::default(),
-            t1: Mpz::default(),
-            t2: Mpz::default(),
+            q: B::default(),
+            r: B::default(),
+            t1: B::default(),
+            t2: B::default(),
         }
     }
 }
 
-impl PartialGCDContext {
+impl<B: BigNum> PartialGCDContext<B> {
     /// This function is an implementation of Lehmer extended GCD with early termination.
     /// It terminates early when remainders fall below the specified bound.
     /// The initial values r1 and r2 are treated as successive remainders in the Euclidean algorithm
@@ -42,11 +41,11 @@ impl PartialGCDContext {
     /// r2_orig is the starting value of r2 supplied, and r1 and r2 are the final values.
     pub fn xgcd_partial(
         &mut self,
-        c2: &mut Mpz,
-        c1: &mut Mpz,
-        r2: &mut Mpz,
-        r1: &mut Mpz,
-        bound: &Mpz,
+        c2: &mut B,
+        c1: &mut B,
+        r2: &mut B,
+        r1: &mut B,
+        bound: &B,
     ) {
         c1.set_si(-1);
         c2.set_si(0);
@@ -54,17 +53,12 @@ impl PartialGCDContext {
         //loop index
         let mut _index = 0;
 
-        while r1.sgn() != 0 && r1.cmp_mpz(&bound) > 0 {
+        while r1.sgn() != 0 && *r1 > *bound {
             let mut _t = r2.bit_length();
             let mut _t1 = r1.bit_length();
 
-            let mut bits = ((std::cmp::max(_t, _t1)) - (gmp::LIMB_BITS as usize) + 1) as u64;
-            //println!("bits: {:?}", bits);
-
-            //Bits
-            if bits < 0 {
-                bits = 0;
-            }
+            // Keep the top word of the larger remainder; small operands are not shifted at all.
+            let bits = (std::cmp::max(_t, _t1) + 1).saturating_sub(64) as u64;
 
             self.r.tdiv_q_2exp(&r2, bits);
             let mut rr2 = self.r.get_si();
@@ -132,7 +126,7 @@ impl PartialGCDContext {
                 let tmp = r2.clone();
                 self.q.fdiv_qr(r2, &tmp, &r1); //i r2 is taken here what we do
                 r2.swap(r1);
-                c2.sub_mul(&c1, &self.q);
+                c2.submul(&c1, &self.q);
                 c2.swap(c1);
             } else {
                 // recombination
@@ -177,9 +171,11 @@ impl PartialGCDContext {
     } //function end
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "gmp"))]
 mod test {
     use super::*;
+    use crate::num::Mpz;
+    use gmp_mpfr_sys::gmp;
     use crate::num::rand;
 
     #[test]
@@ -2,12 +2,14 @@
 
 use gmp_mpfr_sys::gmp::{
     // limb_t,
+    randclear,
     randinit_default,
     // LIMB_BITS,
     // mpz_rrandomb,
     // mpz_urandomm
     randstate_t,
 };
+use std::mem::MaybeUninit;
 
 /// Holds the gmp randomness
 pub struct RandState {
@@ -16,6 +18,12 @@ pub struct RandState {
     pub randval2: BigDigit,
 }
 
+impl Drop for RandState {
+    fn drop(&mut self) {
+        unsafe { randclear(&mut self.gmp) }
+    }
+}
+
 #[cfg(target_pointer_width = "32")]
 pub type BigDigit = u32;
 #[cfg(target_pointer_width = "64")]
@@ -40,10 +48,10 @@ pub type CoeffMax = i64;
 #[cfg(target_pointer_width = "32")]
 pub fn randinit() -> RandState {
     unsafe {
-        let mut mpz = std::mem::uninitialized();
-        randinit_default(&mut mpz);
+        let mut state = MaybeUninit::uninit();
+        randinit_default(state.as_mut_ptr());
         RandState {
-            gmp: mpz,
+            gmp: state.assume_init(),
             randval: u32::from(4187301858u32),
             randval2: u32::from(3721271368u32),
         }
@@ -53,10 +61,10 @@ pub fn randinit() -> RandState {
 #[cfg(target_pointer_width = "64")]
 pub fn randinit() -> RandState {
     unsafe {
-        let mut mpz = std::mem::uninitialized();
-        randinit_default(&mut mpz);
+        let mut state = MaybeUninit::uninit();
+        randinit_default(state.as_mut_ptr());
         RandState {
-            gmp: mpz,
+            gmp: state.assume_init(),
             randval: u64::from(13845646450878251009u64),
             randval2: u64::from(13142370077570254774u64),
         }
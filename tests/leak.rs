@@ -0,0 +1,168 @@
+//! Leak regression: runs group and `Mpz` operations with GMP's allocation functions wrapped by
+//! counters, and checks that the memory GMP hands out is given back.
+//!
+//! A few thousand operations run with every `cargo test`. The soak test of millions takes
+//! minutes, so it is ignored by default. Run it with
+//! `cargo test --release --test leak -- --ignored`.
+#![cfg(feature = "gmp")]
+
+use classygroup::{ClassGroup, Mpz};
+use gmp_mpfr_sys::gmp;
+use std::collections::HashSet;
+use std::os::raw::c_void;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Mutex, MutexGuard, Once};
+
+// Each iteration is two group operations, so the default is about two million. Set
+// `CLASSYGROUP_LEAK_ITERATIONS` to change it.
+const DEFAULT_ITERATIONS: usize = 1 << 20;
+
+// Net bytes handed out by GMP through the wrapped functions.
+static LIVE_BYTES: AtomicI64 = AtomicI64::new(0);
+
+// Blocks allocated through the wrapped functions. Blocks GMP handed out before the hooks were
+// installed are not counted when they are freed or reallocated.
+static BLOCKS: Mutex<Option<HashSet<usize>>> = Mutex::new(None);
+
+// Held by each test, as the counters are shared by the whole process.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+static mut ORIG_ALLOC: gmp::allocate_function = None;
+static mut ORIG_REALLOC: gmp::reallocate_function = None;
+static mut ORIG_FREE: gmp::free_function = None;
+
+// Records a block handed out by GMP.
+fn track(ptr: *mut c_void, size: usize) {
+    let mut blocks = BLOCKS.lock().unwrap();
+    blocks.get_or_insert_with(HashSet::new).insert(ptr as usize);
+    LIVE_BYTES.fetch_add(size as i64, Ordering::SeqCst);
+}
+
+// Forgets a block returned to GMP, if it was recorded.
+fn untrack(ptr: *mut c_void, size: usize) {
+    let mut blocks = BLOCKS.lock().unwrap();
+    if blocks
+        .get_or_insert_with(HashSet::new)
+        .remove(&(ptr as usize))
+    {
+        LIVE_BYTES.fetch_sub(size as i64, Ordering::SeqCst);
+    }
+}
+
+extern "C" fn counting_alloc(size: usize) -> *mut c_void {
+    let ptr = unsafe { ORIG_ALLOC.unwrap()(size) };
+    track(ptr, size);
+    ptr
+}
+
+extern "C" fn counting_realloc(ptr: *mut c_void, old: usize, new: usize) -> *mut c_void {
+    untrack(ptr, old);
+    let ptr = unsafe { ORIG_REALLOC.unwrap()(ptr, old, new) };
+    track(ptr, new);
+    ptr
+}
+
+extern "C" fn counting_free(ptr: *mut c_void, size: usize) {
+    untrack(ptr, size);
+    unsafe { ORIG_FREE.unwrap()(ptr, size) }
+}
+
+fn live_blocks() -> usize {
+    BLOCKS.lock().unwrap().as_ref().map_or(0, HashSet::len)
+}
+
+// Installs the hooks once, before the test allocates its first `Mpz`, and runs the test alone.
+fn install_counting_hooks() -> MutexGuard<'static, ()> {
+    let serial = SERIAL
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| unsafe {
+        gmp::get_memory_functions(
+            std::ptr::addr_of_mut!(ORIG_ALLOC),
+            std::ptr::addr_of_mut!(ORIG_REALLOC),
+            std::ptr::addr_of_mut!(ORIG_FREE),
+        );
+        gmp::set_memory_functions(
+            Some(counting_alloc),
+            Some(counting_realloc),
+            Some(counting_free),
+        );
+    });
+    serial
+}
+
+#[test]
+fn test_ops_free_their_memory() {
+    let _serial = install_counting_hooks();
+
+    // Everything allocated here is dropped before returning, apart from the thread-local context.
+    fn run(iterations: u64) {
+        let g = ClassGroup::unknown_order_elem();
+        let exp = Mpz::from(0xdead_beef);
+        let mut x = ClassGroup::op(&g, &g);
+        let mut y = g.clone();
+        let mut acc = Mpz::from(1);
+        for i in 0..iterations {
+            x = ClassGroup::op(&x, &y);
+            ClassGroup::square(&mut y);
+            if i % 512 == 0 {
+                y = ClassGroup::pow(&x, &exp);
+            }
+
+            let mut a = Mpz::from(i + 2);
+            a.mul_mut(&x.c);
+            acc.mul_mut(&a.clone());
+            acc.fdiv_r_mut(&x.a);
+            let mut digits = a.to_string();
+            digits.push('1');
+            drop(digits.parse::<Mpz>().unwrap());
+        }
+    }
+
+    // Let the thread-local context and its scratch values reach their working size first.
+    run(100);
+    let baseline = live_blocks();
+    run(4000);
+    assert_eq!(live_blocks(), baseline);
+}
+
+#[test]
+#[ignore]
+fn test_group_ops_do_not_leak() {
+    let _serial = install_counting_hooks();
+    let iterations = std::env::var("CLASSYGROUP_LEAK_ITERATIONS")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(DEFAULT_ITERATIONS);
+
+    let g = ClassGroup::unknown_order_elem();
+    let mut x = ClassGroup::op(&g, &g);
+    let mut y = g.clone();
+
+    // Let the thread-local context and the scratch values grow to their working size first.
+    for _ in 0..1000 {
+        x = ClassGroup::op(&x, &g);
+        ClassGroup::square(&mut y);
+    }
+    let baseline = LIVE_BYTES.load(Ordering::SeqCst);
+
+    let exp = Mpz::from(0xdead_beef);
+    for i in 0..iterations {
+        x = ClassGroup::op(&x, &y);
+        ClassGroup::square(&mut y);
+        if i % 4096 == 0 {
+            y = ClassGroup::pow(&x, &exp);
+        }
+    }
+
+    // Limbs are sized to the operands, so allow a little slack for coefficients that happen to
+    // be one limb wider than during warm-up; a leak grows by hundreds of bytes per operation.
+    let growth = LIVE_BYTES.load(Ordering::SeqCst) - baseline;
+    assert!(
+        growth < 64 * 1024,
+        "GMP live bytes grew by {} over {} iterations",
+        growth,
+        iterations
+    );
+}